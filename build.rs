@@ -0,0 +1,22 @@
+use std::process::Command;
+
+/// Exposes the current git commit to the crate as the `GIT_SHA` environment variable.
+/// Builds without a git checkout (e.g. inside Docker) can pass it in via `GIT_SHA`.
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let git_sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    });
+    println!(
+        "cargo:rustc-env=GIT_SHA={}",
+        git_sha.unwrap_or_else(|| "unknown".into())
+    );
+}
@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions ADD COLUMN confirmed_source TEXT NULL;
+ALTER TABLE subscriptions ADD COLUMN consented_at TIMESTAMPTZ NULL;
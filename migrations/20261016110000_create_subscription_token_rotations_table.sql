@@ -0,0 +1,7 @@
+CREATE TABLE subscription_token_rotations (
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    rotated_at timestamptz NOT NULL
+);
+CREATE INDEX subscription_token_rotations_subscriber_id_idx
+    ON subscription_token_rotations (subscriber_id, rotated_at);
@@ -0,0 +1,7 @@
+CREATE TABLE subscriber_tags (
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (subscriber_id, tag)
+);
+CREATE INDEX subscriber_tags_tag_idx ON subscriber_tags (tag);
@@ -0,0 +1,8 @@
+CREATE TABLE confirmation_email_outbox (
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    subscription_token TEXT NOT NULL,
+    attempts INT NOT NULL,
+    next_attempt_at timestamptz NOT NULL,
+    PRIMARY KEY (subscriber_id)
+);
@@ -0,0 +1,15 @@
+BEGIN;
+
+    ALTER TABLE newsletter_issues ADD COLUMN status TEXT NOT NULL DEFAULT 'completed';
+    ALTER TABLE newsletter_issues ALTER COLUMN status DROP DEFAULT;
+    ALTER TABLE newsletter_issues ADD COLUMN delivered_count INT NOT NULL DEFAULT 0;
+    ALTER TABLE newsletter_issues ADD COLUMN skipped_count INT NOT NULL DEFAULT 0;
+
+    UPDATE newsletter_issues i
+    SET status = 'in_progress'
+    WHERE EXISTS (
+        SELECT 1 FROM issue_delivery_queue q
+        WHERE q.newsletter_issue_id = i.newsletter_issue_id
+    );
+
+COMMIT;
@@ -0,0 +1,2 @@
+-- The new address of a subscriber who asked to change it, kept until the change is confirmed.
+ALTER TABLE subscriptions ADD COLUMN pending_email TEXT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions ADD COLUMN locale TEXT NULL;
+ALTER TABLE subscriptions ADD COLUMN timezone TEXT NULL;
@@ -0,0 +1,7 @@
+BEGIN;
+
+    ALTER TABLE issue_delivery_receipts RENAME COLUMN delivered_at TO recorded_at;
+    ALTER TABLE issue_delivery_receipts ADD COLUMN status TEXT NOT NULL DEFAULT 'delivered';
+    ALTER TABLE issue_delivery_receipts ALTER COLUMN status DROP DEFAULT;
+
+COMMIT;
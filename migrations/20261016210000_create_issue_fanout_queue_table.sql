@@ -0,0 +1,5 @@
+CREATE TABLE issue_fanout_queue (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues(newsletter_issue_id),
+    segment TEXT NULL,
+    PRIMARY KEY (newsletter_issue_id)
+);
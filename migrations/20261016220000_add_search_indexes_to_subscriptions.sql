@@ -0,0 +1,5 @@
+-- Trigram indexes let `ILIKE '%term%'` searches on email and name use an index.
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX subscriptions_email_trgm_idx ON subscriptions USING gin (email gin_trgm_ops);
+CREATE INDEX subscriptions_name_trgm_idx ON subscriptions USING gin (name gin_trgm_ops);
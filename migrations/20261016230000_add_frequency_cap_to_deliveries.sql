@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions ADD COLUMN last_emailed_at TIMESTAMPTZ NULL;
+ALTER TABLE issue_delivery_queue ADD COLUMN execute_after TIMESTAMPTZ NOT NULL DEFAULT now();
@@ -0,0 +1,2 @@
+-- Rows written before this migration have no fingerprint and keep replaying as before.
+ALTER TABLE idempotency ADD COLUMN request_fingerprint TEXT NULL;
@@ -0,0 +1,8 @@
+-- One draft per user, overwritten on every save and deleted once an issue is published.
+CREATE TABLE newsletter_drafts (
+    user_id uuid PRIMARY KEY REFERENCES users(user_id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,2 @@
+-- When a confirmation email was last sent, so that resends can be throttled.
+ALTER TABLE subscriptions ADD COLUMN last_confirmation_sent_at TIMESTAMPTZ NULL;
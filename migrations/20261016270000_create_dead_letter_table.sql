@@ -0,0 +1,13 @@
+-- Delivery tasks are retried up to `worker.max_retries` times before being dead-lettered.
+ALTER TABLE issue_delivery_queue ADD COLUMN n_retries INT NOT NULL DEFAULT 0;
+
+-- Deliveries the worker gave up on, kept until they are requeued by an administrator.
+CREATE TABLE dead_letter (
+    id uuid PRIMARY KEY,
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues(newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    n_retries INT NOT NULL,
+    reason TEXT NOT NULL,
+    dead_lettered_at TIMESTAMPTZ NOT NULL,
+    UNIQUE (newsletter_issue_id, subscriber_email)
+);
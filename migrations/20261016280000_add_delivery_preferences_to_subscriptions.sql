@@ -0,0 +1,3 @@
+-- Chosen by subscribers in the preference center.
+ALTER TABLE subscriptions ADD COLUMN content_format TEXT NOT NULL DEFAULT 'html';
+ALTER TABLE subscriptions ADD COLUMN frequency TEXT NOT NULL DEFAULT 'every_issue';
@@ -0,0 +1,3 @@
+-- Set by `/admin/security/rehash` so that passwords are hashed again with the current parameters
+-- on the next login.
+ALTER TABLE users ADD COLUMN needs_rehash BOOLEAN NOT NULL DEFAULT FALSE;
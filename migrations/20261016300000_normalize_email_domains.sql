@@ -0,0 +1,49 @@
+-- `SubscriberEmail::parse` lowercases the domain of new addresses; bring the stored ones in line.
+CREATE FUNCTION pg_temp.normalize_email(email TEXT) RETURNS TEXT AS $$
+    SELECT left(email, length(email) - strpos(reverse(email), '@'))
+        || lower(right(email, strpos(reverse(email), '@')))
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- Addresses already stored under several casings are duplicates of one another: only the oldest
+-- is normalized, and the others are left for an administrator to merge rather than dropped here.
+UPDATE subscriptions s
+SET email = pg_temp.normalize_email(s.email)
+FROM (
+    SELECT DISTINCT ON (pg_temp.normalize_email(email)) id
+    FROM subscriptions
+    WHERE email <> pg_temp.normalize_email(email)
+    ORDER BY pg_temp.normalize_email(email), subscribed_at
+) oldest
+WHERE s.id = oldest.id
+  AND NOT EXISTS (SELECT 1 FROM subscriptions o WHERE o.email = pg_temp.normalize_email(s.email));
+
+-- Pending and past deliveries follow the subscriptions that have just been renamed.
+UPDATE issue_delivery_queue q
+SET subscriber_email = pg_temp.normalize_email(q.subscriber_email)
+WHERE NOT EXISTS (SELECT 1 FROM subscriptions WHERE email = q.subscriber_email)
+  AND EXISTS (SELECT 1 FROM subscriptions WHERE email = pg_temp.normalize_email(q.subscriber_email))
+  AND NOT EXISTS (
+      SELECT 1 FROM issue_delivery_queue o
+      WHERE o.newsletter_issue_id = q.newsletter_issue_id
+        AND o.subscriber_email = pg_temp.normalize_email(q.subscriber_email)
+  );
+
+UPDATE issue_delivery_receipts r
+SET subscriber_email = pg_temp.normalize_email(r.subscriber_email)
+WHERE NOT EXISTS (SELECT 1 FROM subscriptions WHERE email = r.subscriber_email)
+  AND EXISTS (SELECT 1 FROM subscriptions WHERE email = pg_temp.normalize_email(r.subscriber_email))
+  AND NOT EXISTS (
+      SELECT 1 FROM issue_delivery_receipts o
+      WHERE o.newsletter_issue_id = r.newsletter_issue_id
+        AND o.subscriber_email = pg_temp.normalize_email(r.subscriber_email)
+  );
+
+UPDATE dead_letter d
+SET subscriber_email = pg_temp.normalize_email(d.subscriber_email)
+WHERE NOT EXISTS (SELECT 1 FROM subscriptions WHERE email = d.subscriber_email)
+  AND EXISTS (SELECT 1 FROM subscriptions WHERE email = pg_temp.normalize_email(d.subscriber_email))
+  AND NOT EXISTS (
+      SELECT 1 FROM dead_letter o
+      WHERE o.newsletter_issue_id = d.newsletter_issue_id
+        AND o.subscriber_email = pg_temp.normalize_email(d.subscriber_email)
+  );
@@ -0,0 +1,5 @@
+-- A misspelled status is rejected by the database instead of silently matching no row.
+CREATE TYPE subscription_status AS ENUM ('pending_confirmation', 'confirmed', 'unsubscribed');
+
+ALTER TABLE subscriptions
+    ALTER COLUMN status TYPE subscription_status USING status::subscription_status;
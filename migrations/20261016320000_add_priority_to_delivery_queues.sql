@@ -0,0 +1,7 @@
+-- Transactional issues are delivered ahead of the bulk ones, oldest first within a priority.
+ALTER TABLE issue_fanout_queue ADD COLUMN priority SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE issue_delivery_queue
+    ADD COLUMN priority SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN enqueued_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX issue_delivery_queue_dequeue_idx
+    ON issue_delivery_queue (priority DESC, enqueued_at);
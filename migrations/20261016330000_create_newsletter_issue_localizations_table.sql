@@ -0,0 +1,10 @@
+-- Translations of an issue, delivered to the subscribers whose locale matches.
+-- The content of `newsletter_issues` is the default for everyone else.
+CREATE TABLE newsletter_issue_localizations (
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    locale TEXT NOT NULL,
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, locale)
+);
@@ -0,0 +1,12 @@
+-- Bearer tokens for the subscribe API, e.g. one per site embedding the form.
+-- Only a SHA-256 hash of each token is stored.
+CREATE TABLE api_tokens (
+    api_token_id uuid PRIMARY KEY,
+    user_id uuid NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    source TEXT NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- The `source` of the API token the subscription was made with.
+ALTER TABLE subscriptions ADD COLUMN source TEXT NULL;
@@ -0,0 +1,81 @@
+use actix_web::http::header::{self, HeaderMap};
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+/// Hashes an API token for storage. Tokens are long and random,
+/// so a fast hash is enough to keep them out of a database dump.
+pub fn hash_api_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Reads the token of an `Authorization: Bearer <token>` header.
+///
+/// `Ok(None)` when there is no `Authorization` header,
+/// and an error when it is not a bearer token.
+pub fn bearer_token(headers: &HeaderMap) -> Result<Option<&str>, anyhow::Error> {
+    let Some(value) = headers.get(header::AUTHORIZATION) else {
+        return Ok(None);
+    };
+    let value = value
+        .to_str()
+        .context("The `Authorization` header is not a valid UTF-8 string.")?;
+    let token = value
+        .strip_prefix("Bearer ")
+        .context("The authorization scheme is not `Bearer`.")?
+        .trim();
+    anyhow::ensure!(!token.is_empty(), "The bearer token is empty.");
+    Ok(Some(token))
+}
+
+/// Returns the `source` of an API token, or `None` when the token is unknown
+/// or belongs to a deactivated user.
+#[tracing::instrument(name = "Look up an API token", skip_all)]
+pub async fn api_token_source(pool: &PgPool, token: &str) -> Result<Option<String>, sqlx::Error> {
+    let source = sqlx::query_scalar!(
+        r#"
+        SELECT t.source
+        FROM api_tokens t
+        JOIN users u ON u.user_id = t.user_id
+        WHERE t.token_hash = $1 AND u.is_active
+        "#,
+        hash_api_token(token)
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderValue;
+    use claim::{assert_err, assert_none};
+
+    fn headers(authorization: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static(authorization),
+        );
+        headers
+    }
+
+    #[test]
+    fn a_bearer_token_is_read_from_the_header() {
+        let headers = headers("Bearer abc123");
+        assert_eq!(bearer_token(&headers).unwrap(), Some("abc123"));
+    }
+
+    #[test]
+    fn a_missing_header_is_no_token() {
+        assert_none!(bearer_token(&HeaderMap::new()).unwrap());
+    }
+
+    #[test]
+    fn other_schemes_and_empty_tokens_are_rejected() {
+        for authorization in ["Basic dXNlcjpwYXNz", "Bearer ", "abc123"] {
+            assert_err!(bearer_token(&headers(authorization)), "{}", authorization);
+        }
+    }
+}
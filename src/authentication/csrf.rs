@@ -0,0 +1,88 @@
+use crate::session_state::TypedSession;
+use crate::utils::{e400, e500};
+use actix_web::HttpRequest;
+use rand::distributions::Alphanumeric;
+use rand::rngs::OsRng;
+use rand::Rng;
+
+const CSRF_TOKEN_LENGTH: usize = 32;
+
+/// The header carrying the CSRF token on admin requests that are not HTML forms:
+/// JSON and CSV bodies, and actions without a body.
+pub const CSRF_TOKEN_HEADER: &str = "X-CSRF-Token";
+
+/// The body of admin forms that carry nothing but the CSRF token, such as the logout form.
+#[derive(serde::Deserialize)]
+pub struct CsrfForm {
+    #[serde(default)]
+    pub csrf_token: String,
+}
+
+/// Returns the CSRF token of the session, generating one on first use.
+///
+/// The token is embedded in every admin form and checked by [verify_csrf_token]
+/// when the form is submitted. Other clients read it from `/admin/me` and send it
+/// in the [CSRF_TOKEN_HEADER], which is checked by [verify_csrf_header]. It is generated at login already, so that pages
+/// loaded concurrently right afterwards do not each store a different token.
+pub fn csrf_token(session: &TypedSession) -> Result<String, anyhow::Error> {
+    if let Some(token) = session.get_csrf_token()? {
+        return Ok(token);
+    }
+    let token: String = std::iter::repeat_with(|| OsRng.sample(Alphanumeric))
+        .map(char::from)
+        .take(CSRF_TOKEN_LENGTH)
+        .collect();
+    session.insert_csrf_token(&token)?;
+    Ok(token)
+}
+
+/// Rejects the request with a `400 Bad Request` unless `submitted` matches the session token.
+pub fn verify_csrf_token(session: &TypedSession, submitted: &str) -> Result<(), actix_web::Error> {
+    let expected = session.get_csrf_token().map_err(e500)?;
+    match expected {
+        Some(expected) if tokens_match(expected.as_bytes(), submitted.as_bytes()) => Ok(()),
+        _ => Err(e400("Missing or invalid CSRF token.")),
+    }
+}
+
+/// Rejects the request with a `400 Bad Request` unless its [CSRF_TOKEN_HEADER]
+/// matches the session token.
+pub fn verify_csrf_header(
+    session: &TypedSession,
+    request: &HttpRequest,
+) -> Result<(), actix_web::Error> {
+    let submitted = request
+        .headers()
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    verify_csrf_token(session, submitted)
+}
+
+/// Compares two tokens in constant time, so that the comparison does not leak
+/// how many leading characters of a guess are correct.
+fn tokens_match(expected: &[u8], submitted: &[u8]) -> bool {
+    expected.len() == submitted.len()
+        && expected
+            .iter()
+            .zip(submitted)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokens_match;
+
+    #[test]
+    fn identical_tokens_match() {
+        assert!(tokens_match(b"abcdef", b"abcdef"));
+    }
+
+    #[test]
+    fn different_tokens_do_not_match() {
+        assert!(!tokens_match(b"abcdef", b"abcdeg"));
+        assert!(!tokens_match(b"abcdef", b"abcde"));
+        assert!(!tokens_match(b"abcdef", b""));
+    }
+}
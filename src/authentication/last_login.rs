@@ -0,0 +1,43 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// When and from where a user last logged in.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct LastLogin {
+    pub at: DateTime<Utc>,
+    pub ip: Option<String>,
+}
+
+/// Records a successful login and returns the one before it, if any.
+#[tracing::instrument(name = "Record login", skip(pool))]
+pub async fn record_login(
+    pool: &PgPool,
+    user_id: Uuid,
+    ip: Option<&str>,
+) -> Result<Option<LastLogin>, anyhow::Error> {
+    let row = sqlx::query!(
+        r#"
+        UPDATE users u
+        SET last_login_at = now(), last_login_ip = $2
+        FROM (
+            SELECT user_id, last_login_at, last_login_ip
+            FROM users
+            WHERE user_id = $1
+            FOR UPDATE
+        ) previous
+        WHERE u.user_id = previous.user_id
+        RETURNING previous.last_login_at AS "last_login_at?", previous.last_login_ip AS "last_login_ip?"
+        "#,
+        user_id,
+        ip,
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to record the login.")?;
+    Ok(row.last_login_at.map(|at| LastLogin {
+        at,
+        ip: row.last_login_ip,
+    }))
+}
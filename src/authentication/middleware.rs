@@ -1,13 +1,18 @@
 use crate::session_state::TypedSession;
-use crate::utils::{e500, see_other};
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app};
 use actix_web::body::MessageBody;
 use actix_web::dev::{ServiceRequest, ServiceResponse};
 use actix_web::error::InternalError;
-use actix_web::{FromRequest, HttpMessage};
+use actix_web::http::Method;
+use actix_web::{web, FromRequest, HttpMessage};
 use actix_web_lab::middleware::Next;
 use std::ops::Deref;
 
-#[derive(Copy, Clone, Debug)]
+/// The id of the logged-in user, set by [reject_anonymous_user].
+///
+/// It is serialized as the bare UUID.
+#[derive(Copy, Clone, Debug, serde::Serialize)]
 pub struct UserId(uuid::Uuid);
 
 impl std::fmt::Display for UserId {
@@ -40,9 +45,35 @@ pub async fn reject_anonymous_user(
             next.call(req).await
         }
         None => {
-            let response = see_other("/login");
+            let base_path = BasePath(
+                req.app_data::<web::Data<BasePath>>()
+                    .map(|base_path| base_path.0.clone())
+                    .unwrap_or_default(),
+            );
+            let response = see_other_in_app(&base_path, &login_location(&req, &base_path));
             let e = anyhow::anyhow!("The user has not logged in.");
             Err(InternalError::from_response(e, response).into())
         }
     }
 }
+
+/// The login page, with the requested page in `next` so that logging in leads back to it.
+///
+/// Only `GET` requests are remembered: following the redirect after logging in
+/// cannot replay the body of any other method.
+/// Both paths are relative to the `base_path` the application is mounted at.
+fn login_location(req: &ServiceRequest, base_path: &BasePath) -> String {
+    if req.method() != Method::GET {
+        return "/login".into();
+    }
+    let destination = req
+        .uri()
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_else(|| req.path());
+    let destination = destination
+        .strip_prefix(base_path.0.as_str())
+        .unwrap_or(destination);
+    let next: String = url::form_urlencoded::byte_serialize(destination.as_bytes()).collect();
+    format!("/login?next={}", next)
+}
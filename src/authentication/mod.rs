@@ -1,5 +1,15 @@
+mod api_token;
+mod csrf;
+mod last_login;
 mod middleware;
 mod password;
+mod password_policy;
+mod totp;
 
+pub use api_token::{api_token_source, bearer_token, hash_api_token};
+pub use csrf::{csrf_token, verify_csrf_header, verify_csrf_token, CsrfForm, CSRF_TOKEN_HEADER};
+pub use last_login::{record_login, LastLogin};
 pub use middleware::{reject_anonymous_user, UserId};
-pub use password::{change_password, validate_credentials, AuthError, Credentials};
+pub use password::{change_password, create_user, validate_credentials, AuthError, Credentials};
+pub use password_policy::{validate_new_password, WeakPasswordError};
+pub use totp::{enable_totp, generate_totp_secret, get_totp_secret, totp_uri, verify_totp_code};
@@ -23,41 +23,69 @@ pub async fn validate_credentials(
     pool: &PgPool,
     credentials: Credentials,
 ) -> Result<uuid::Uuid, AuthError> {
-    let (user_id, expected_password_hash) = match get_stored_credentials(pool, &credentials).await {
-        Ok(Some((stored_user_id, stored_password_hash))) => (Some(stored_user_id), stored_password_hash),
+    let (user_id, expected_password_hash, flagged) = match get_stored_credentials(pool, &credentials).await {
+        Ok(Some(stored)) => (Some(stored.user_id), stored.password_hash, stored.needs_rehash),
         // For removal early return when the user is not found. This prevents timing attacks.
         _ => (None, Secret::new(
             "$argon2id$v=19$m=15000,t=2,p=1$gZiV/M1gPc22E1AH/Jh1Hw$CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
                 .to_string()
-        ))
+        ), false)
     };
 
-    spawn_blocking_with_tracing(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
+    let password = credentials.password;
+    let (password, outdated) = spawn_blocking_with_tracing(move || {
+        verify_password_hash(&expected_password_hash, &password)?;
+        let outdated = flagged || is_outdated(&expected_password_hash);
+        Ok::<_, AuthError>((password, outdated))
     })
     .await
     .context("Failed to spawn blocking task.")
     .map_err(AuthError::UnexpectedError)??;
 
-    user_id
+    let user_id = user_id
         .ok_or_else(|| anyhow::anyhow!("Unknown username."))
-        .map_err(AuthError::InvalidCredentials)
+        .map_err(AuthError::InvalidCredentials)?;
+    if outdated {
+        // The login succeeds anyway: the old hash is still valid, and the next login retries.
+        if let Err(e) = change_password(pool, user_id, password).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to re-hash the password with the current parameters."
+            );
+        }
+    }
+    Ok(user_id)
+}
+
+struct StoredCredentials {
+    user_id: uuid::Uuid,
+    password_hash: Secret<String>,
+    needs_rehash: bool,
 }
 
 #[tracing::instrument(name = "Get stored credentials", skip(pool, credentials))]
 async fn get_stored_credentials(
     pool: &PgPool,
     credentials: &Credentials,
-) -> Result<Option<(uuid::Uuid, Secret<String>)>, AuthError> {
+) -> Result<Option<StoredCredentials>, AuthError> {
     let row: Option<_> = sqlx::query!(
-        "SELECT user_id, password_hash FROM users WHERE username = $1",
+        r#"
+        SELECT user_id, password_hash, needs_rehash
+        FROM users
+        WHERE username = $1 AND is_active
+        "#,
         credentials.username,
     )
     .fetch_optional(pool)
     .await
     .context("Failed to query to retrieve stored credentials.")
     .map_err(AuthError::UnexpectedError)?
-    .map(|r| (r.user_id, Secret::new(r.password_hash)));
+    .map(|r| StoredCredentials {
+        user_id: r.user_id,
+        password_hash: Secret::new(r.password_hash),
+        needs_rehash: r.needs_rehash,
+    });
 
     Ok(row)
 }
@@ -67,8 +95,8 @@ async fn get_stored_credentials(
     skip(expected_password_hash, password_candidate)
 )]
 fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
+    expected_password_hash: &Secret<String>,
+    password_candidate: &Secret<String>,
 ) -> Result<(), AuthError> {
     // Using PHC string format
     let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
@@ -95,7 +123,7 @@ pub async fn change_password(
         .context("Failed to hash password.")?;
 
     sqlx::query!(
-        "UPDATE users SET password_hash = $1 WHERE user_id = $2",
+        "UPDATE users SET password_hash = $1, needs_rehash = false WHERE user_id = $2",
         password_hash.expose_secret(),
         user_id,
     )
@@ -106,15 +134,85 @@ pub async fn change_password(
     Ok(())
 }
 
+/// Creates an active user. Returns `None` if the username is already taken.
+#[tracing::instrument(name = "Create user", skip(pool, password))]
+pub async fn create_user(
+    pool: &PgPool,
+    username: &str,
+    password: Secret<String>,
+) -> Result<Option<uuid::Uuid>, anyhow::Error> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(password))
+        .await?
+        .context("Failed to hash password.")?;
+
+    let row = sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (username) DO NOTHING
+        RETURNING user_id
+        "#,
+        uuid::Uuid::new_v4(),
+        username,
+        password_hash.expose_secret(),
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to insert the new user in the database.")?;
+
+    Ok(row.map(|r| r.user_id))
+}
+
+/// The parameters new password hashes are computed with.
+fn current_params() -> Params {
+    Params::new(15000, 2, 1, None).unwrap()
+}
+
 fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, anyhow::Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
-    let password_hash = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
-    )
-    .hash_password(password.expose_secret().as_bytes(), &salt)?
-    .to_string();
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, current_params())
+        .hash_password(password.expose_secret().as_bytes(), &salt)?
+        .to_string();
 
     Ok(Secret::new(password_hash))
 }
+
+/// Whether a stored hash was computed with another algorithm or other parameters
+/// than [compute_password_hash] uses now.
+fn is_outdated(password_hash: &Secret<String>) -> bool {
+    let Ok(password_hash) = PasswordHash::new(password_hash.expose_secret()) else {
+        return true;
+    };
+    let current = current_params();
+    password_hash.algorithm != Algorithm::Argon2id.ident()
+        || Params::try_from(&password_hash).map_or(true, |params| {
+            params.m_cost() != current.m_cost()
+                || params.t_cost() != current.t_cost()
+                || params.p_cost() != current.p_cost()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes_computed_with_the_current_parameters_are_up_to_date() {
+        let password_hash = compute_password_hash(Secret::new("password".into())).unwrap();
+        assert!(!is_outdated(&password_hash));
+    }
+
+    #[test]
+    fn hashes_computed_with_other_parameters_are_outdated() {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let password_hash = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8192, 1, 1, None).unwrap(),
+        )
+        .hash_password(b"password", &salt)
+        .unwrap()
+        .to_string();
+        assert!(is_outdated(&Secret::new(password_hash)));
+    }
+}
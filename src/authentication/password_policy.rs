@@ -0,0 +1,182 @@
+use crate::configuration::PasswordPolicySettings;
+use secrecy::{ExposeSecret, Secret};
+
+/// Passwords that are rejected regardless of the other rules, compared case-insensitively.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// The rules of the password policy that a new password breaks.
+#[derive(thiserror::Error, Debug)]
+#[error("{}", .0.join(" "))]
+pub struct WeakPasswordError(Vec<String>);
+
+/// Checks that a new password satisfies the password policy.
+///
+/// Every broken rule is reported, with one message per rule.
+pub fn validate_new_password(
+    policy: &PasswordPolicySettings,
+    new_password: Secret<String>,
+) -> Result<(), WeakPasswordError> {
+    let password = new_password.expose_secret();
+    let length = password.chars().count();
+    let mut errors = Vec::new();
+    if length < policy.min_length {
+        errors.push(format!(
+            "The new password must be at least {} characters long.",
+            policy.min_length
+        ));
+    }
+    if length > policy.max_length {
+        errors.push(format!(
+            "The new password must be at most {} characters long.",
+            policy.max_length
+        ));
+    }
+    if policy.require_mixed_case
+        && !(password.chars().any(char::is_lowercase) && password.chars().any(char::is_uppercase))
+    {
+        errors.push("The new password must contain both lowercase and uppercase letters.".into());
+    }
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        errors.push("The new password must contain at least one digit.".into());
+    }
+    if policy.require_symbol && password.chars().all(char::is_alphanumeric) {
+        errors.push("The new password must contain at least one symbol.".into());
+    }
+    if policy.reject_common_passwords && is_common_password(password) {
+        errors.push("The new password is too common.".into());
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(WeakPasswordError(errors))
+    }
+}
+
+fn is_common_password(password: &str) -> bool {
+    let password = password.to_lowercase();
+    COMMON_PASSWORDS.lines().any(|common| common == password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    fn strict_policy() -> PasswordPolicySettings {
+        PasswordPolicySettings {
+            min_length: 12,
+            max_length: 128,
+            require_mixed_case: true,
+            require_digit: true,
+            require_symbol: true,
+            reject_common_passwords: true,
+        }
+    }
+
+    fn lenient_policy() -> PasswordPolicySettings {
+        PasswordPolicySettings {
+            require_mixed_case: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_common_passwords: false,
+            ..strict_policy()
+        }
+    }
+
+    fn errors(policy: &PasswordPolicySettings, password: &str) -> Vec<String> {
+        match validate_new_password(policy, Secret::new(password.to_owned())) {
+            Ok(()) => Vec::new(),
+            Err(WeakPasswordError(errors)) => errors,
+        }
+    }
+
+    #[test]
+    fn a_strong_password_is_accepted() {
+        assert_ok!(validate_new_password(
+            &strict_policy(),
+            Secret::new("Correct-Horse-Battery-9".to_owned())
+        ));
+    }
+
+    #[test]
+    fn a_common_password_is_rejected() {
+        assert_err!(validate_new_password(
+            &strict_policy(),
+            Secret::new("password123456".to_owned())
+        ));
+        assert_eq!(
+            errors(&lenient_policy(), "password123456"),
+            Vec::<String>::new()
+        );
+        let policy = PasswordPolicySettings {
+            reject_common_passwords: true,
+            ..lenient_policy()
+        };
+        assert_eq!(
+            errors(&policy, "Password123456"),
+            vec!["The new password is too common."]
+        );
+    }
+
+    #[test]
+    fn a_password_shorter_than_the_minimum_is_rejected() {
+        assert_eq!(
+            errors(&strict_policy(), "Sh0rt-pass!"),
+            vec!["The new password must be at least 12 characters long."]
+        );
+    }
+
+    #[test]
+    fn a_password_longer_than_the_maximum_is_rejected() {
+        let password = format!("Aa1-{}", "a".repeat(125));
+        assert_eq!(
+            errors(&strict_policy(), &password),
+            vec!["The new password must be at most 128 characters long."]
+        );
+    }
+
+    #[test]
+    fn a_password_without_mixed_case_is_rejected() {
+        let expected = vec!["The new password must contain both lowercase and uppercase letters."];
+        assert_eq!(errors(&strict_policy(), "all-lower-case-1"), expected);
+        assert_eq!(errors(&strict_policy(), "ALL-UPPER-CASE-1"), expected);
+    }
+
+    #[test]
+    fn a_password_without_a_digit_is_rejected() {
+        assert_eq!(
+            errors(&strict_policy(), "No-Digits-Here"),
+            vec!["The new password must contain at least one digit."]
+        );
+    }
+
+    #[test]
+    fn a_password_without_a_symbol_is_rejected() {
+        assert_eq!(
+            errors(&strict_policy(), "NoSymbolsHere1"),
+            vec!["The new password must contain at least one symbol."]
+        );
+    }
+
+    #[test]
+    fn every_broken_rule_is_reported() {
+        assert_eq!(
+            errors(&strict_policy(), "short"),
+            vec![
+                "The new password must be at least 12 characters long.",
+                "The new password must contain both lowercase and uppercase letters.",
+                "The new password must contain at least one digit.",
+                "The new password must contain at least one symbol.",
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_rules_are_not_enforced() {
+        assert_eq!(
+            errors(&lenient_policy(), "nosymbolsordigits"),
+            Vec::<String>::new()
+        );
+    }
+}
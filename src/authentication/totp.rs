@@ -0,0 +1,97 @@
+use anyhow::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use totp_rs::{Algorithm, TOTP};
+use uuid::Uuid;
+
+const ISSUER: &str = "Newsletter";
+
+/// Generates a new random TOTP secret, encoded in Base32.
+pub fn generate_totp_secret() -> Secret<String> {
+    Secret::new(totp_rs::Secret::generate_secret().to_encoded().to_string())
+}
+
+/// Builds the `otpauth://` URI to be registered in an authenticator app.
+pub fn totp_uri(secret: &Secret<String>, username: &str) -> Result<String, anyhow::Error> {
+    Ok(totp(secret, username)?.get_url())
+}
+
+/// Checks a 6-digit code against the given secret, allowing one step of clock skew.
+pub fn verify_totp_code(secret: &Secret<String>, code: &str) -> Result<bool, anyhow::Error> {
+    totp(secret, "")?
+        .check_current(code.trim())
+        .context("Failed to read the system time.")
+}
+
+fn totp(secret: &Secret<String>, username: &str) -> Result<TOTP, anyhow::Error> {
+    let secret = totp_rs::Secret::Encoded(secret.expose_secret().to_owned())
+        .to_bytes()
+        .map_err(|e| anyhow::anyhow!("Invalid TOTP secret: {:?}", e))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        secret,
+        Some(ISSUER.to_string()),
+        username.to_string(),
+    )
+    .context("Failed to build TOTP.")
+}
+
+#[tracing::instrument(name = "Get TOTP secret", skip(pool))]
+pub async fn get_totp_secret(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<Secret<String>>, anyhow::Error> {
+    let row = sqlx::query!("SELECT totp_secret FROM users WHERE user_id = $1", user_id)
+        .fetch_one(pool)
+        .await
+        .context("Failed to fetch TOTP secret.")?;
+    Ok(row.totp_secret.map(Secret::new))
+}
+
+#[tracing::instrument(name = "Enable two-factor authentication", skip(pool, secret))]
+pub async fn enable_totp(
+    pool: &PgPool,
+    user_id: Uuid,
+    secret: Secret<String>,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE users SET totp_secret = $1 WHERE user_id = $2",
+        secret.expose_secret(),
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to store TOTP secret.")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_code_generated_from_the_secret_is_accepted() {
+        let secret = generate_totp_secret();
+        let code = totp(&secret, "").unwrap().generate_current().unwrap();
+        assert!(verify_totp_code(&secret, &code).unwrap());
+    }
+
+    #[test]
+    fn a_wrong_code_is_rejected() {
+        let secret = generate_totp_secret();
+        let code = totp(&secret, "").unwrap().generate_current().unwrap();
+        let wrong_code = format!("{:06}", (code.parse::<u32>().unwrap() + 1) % 1_000_000);
+        assert!(!verify_totp_code(&secret, &wrong_code).unwrap());
+    }
+
+    #[test]
+    fn the_uri_contains_the_issuer_and_the_secret() {
+        let secret = generate_totp_secret();
+        let uri = totp_uri(&secret, "admin").unwrap();
+        assert!(uri.starts_with("otpauth://totp/Newsletter:admin?"));
+        assert!(uri.contains(&format!("secret={}", secret.expose_secret())));
+    }
+}
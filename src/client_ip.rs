@@ -0,0 +1,72 @@
+use actix_web::{web, HttpRequest};
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Whether the application only receives requests from a reverse proxy
+/// that sets `X-Forwarded-For`.
+#[derive(Clone, Copy)]
+pub struct TrustedProxy(pub bool);
+
+/// The IP address of the client that made the request.
+///
+/// `X-Forwarded-For` is only read behind a trusted proxy, taking the last address,
+/// i.e. the one the proxy appended: the ones before it are sent by the client and can be forged.
+/// Otherwise, or if the header is missing or malformed, the peer address is used.
+/// [Ipv4Addr::UNSPECIFIED] is returned when neither is available, which only happens in tests.
+pub fn client_ip(req: &HttpRequest) -> IpAddr {
+    let trusted_proxy = req
+        .app_data::<web::Data<TrustedProxy>>()
+        .is_some_and(|trusted_proxy| trusted_proxy.0);
+    let forwarded_for = if trusted_proxy {
+        forwarded_for(req)
+    } else {
+        None
+    };
+    forwarded_for
+        .or_else(|| req.peer_addr().map(|address| address.ip()))
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+fn forwarded_for(req: &HttpRequest) -> Option<IpAddr> {
+    req.headers()
+        .get_all("X-Forwarded-For")
+        .last()?
+        .to_str()
+        .ok()?
+        .rsplit(',')
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    fn request(trusted_proxy: bool, forwarded_for: &str) -> HttpRequest {
+        TestRequest::default()
+            .peer_addr("10.0.0.1:54321".parse().unwrap())
+            .insert_header(("X-Forwarded-For", forwarded_for))
+            .app_data(web::Data::new(TrustedProxy(trusted_proxy)))
+            .to_http_request()
+    }
+
+    #[test]
+    fn the_forwarded_address_is_ignored_without_a_trusted_proxy() {
+        let req = request(false, "203.0.113.7");
+        assert_eq!(client_ip(&req), "10.0.0.1".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn the_address_appended_by_a_trusted_proxy_is_used() {
+        let req = request(true, "198.51.100.1, 203.0.113.7");
+        assert_eq!(client_ip(&req), "203.0.113.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[test]
+    fn a_malformed_forwarded_address_falls_back_to_the_peer_address() {
+        let req = request(true, "not-an-ip");
+        assert_eq!(client_ip(&req), "10.0.0.1".parse::<IpAddr>().unwrap());
+    }
+}
@@ -0,0 +1,319 @@
+use crate::configuration::{ConfirmationRetrySettings, Settings};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::email_templates::EmailTemplates;
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::routes::send_confirmation_email;
+use anyhow::Context;
+use chrono::Utc;
+use rand::Rng;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+pub async fn run_outbox_until_stopped(
+    configuration: Settings,
+    email_client: EmailClient,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = configuration.database.connection_pool();
+    let email_templates = EmailTemplates::new(&configuration.email_templates)
+        .context("Failed to load the email templates.")?;
+
+    outbox_loop(
+        connection_pool,
+        email_client,
+        email_templates,
+        configuration.application.public_url(),
+        configuration.confirmation_retry,
+    )
+    .await
+}
+
+async fn outbox_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    email_templates: EmailTemplates,
+    base_url: Url,
+    settings: ConfirmationRetrySettings,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_confirmation_task(
+            &pool,
+            &email_client,
+            &email_templates,
+            &base_url,
+            &settings,
+        )
+        .await
+        {
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(1)).await,
+            Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// The delay before the given retry (starting from 1).
+///
+/// The base delay doubles on every attempt, and a random jitter is added
+/// so that retries scheduled during a provider outage do not all fire at the same instant.
+pub fn retry_delay(settings: &ConfirmationRetrySettings, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let base = settings
+        .base_delay_milliseconds
+        .saturating_mul(1 << exponent);
+    let jitter = rand::thread_rng().gen_range(0..=settings.max_jitter_milliseconds);
+    Duration::from_millis(base.saturating_add(jitter))
+}
+
+/// Schedules a retry of a confirmation email, in the transaction that stores its token.
+///
+/// Handlers call this before committing and before sending the email inline,
+/// then call [cancel_confirmation_retry] once the email has been sent.
+/// If the handler fails or is dropped by the request timeout in between,
+/// the outbox still sends the email.
+#[tracing::instrument(
+    name = "Schedule a confirmation email retry",
+    skip(transaction, settings, subscription_token)
+)]
+pub async fn schedule_confirmation_retry(
+    transaction: &mut Transaction<'_, Postgres>,
+    settings: &ConfirmationRetrySettings,
+    subscriber_id: Uuid,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    let next_attempt_at = Utc::now() + retry_delay(settings, 1);
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            INSERT INTO confirmation_email_outbox (
+                subscriber_id, subscription_token, attempts, next_attempt_at
+            )
+            VALUES ($1, $2, 1, $3)
+            ON CONFLICT (subscriber_id) DO UPDATE
+            SET subscription_token = EXCLUDED.subscription_token,
+                attempts = 1,
+                next_attempt_at = EXCLUDED.next_attempt_at
+            "#,
+            subscriber_id,
+            subscription_token,
+            next_attempt_at,
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Hands a confirmation email over to the outbox without trying to send it inline,
+/// e.g. for bulk imports. It is sent on the outbox's next pass.
+#[tracing::instrument(
+    name = "Enqueue a confirmation email",
+    skip(transaction, subscription_token)
+)]
+pub async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    transaction
+        .execute(sqlx::query!(
+            r#"
+            INSERT INTO confirmation_email_outbox (
+                subscriber_id, subscription_token, attempts, next_attempt_at
+            )
+            VALUES ($1, $2, 0, now())
+            ON CONFLICT (subscriber_id) DO UPDATE
+            SET subscription_token = EXCLUDED.subscription_token,
+                attempts = 0,
+                next_attempt_at = EXCLUDED.next_attempt_at
+            "#,
+            subscriber_id,
+            subscription_token,
+        ))
+        .await?;
+    Ok(())
+}
+
+/// Removes the retry scheduled by [schedule_confirmation_retry] once the email has been sent.
+///
+/// A failure is only logged: the outbox then sends the email a second time.
+#[tracing::instrument(name = "Cancel a confirmation email retry", skip(pool))]
+pub async fn cancel_confirmation_retry(pool: &PgPool, subscriber_id: Uuid) {
+    let result = sqlx::query!(
+        "DELETE FROM confirmation_email_outbox WHERE subscriber_id = $1",
+        subscriber_id
+    )
+    .execute(pool)
+    .await;
+    if let Err(e) = result {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to cancel the retry of a confirmation email that has been sent."
+        );
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(subscriber_id = tracing::field::Empty, attempts = tracing::field::Empty)
+)]
+pub async fn try_execute_confirmation_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    email_templates: &EmailTemplates,
+    base_url: &Url,
+    settings: &ConfirmationRetrySettings,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((mut tx, task)) = dequeue_task(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    tracing::Span::current()
+        .record(
+            "subscriber_id",
+            tracing::field::display(&task.subscriber_id),
+        )
+        .record("attempts", task.attempts);
+
+    let email = SubscriberEmail::parse(task.email)
+        .context("The subscriber's stored email address is invalid.")?;
+    let attempts = task.attempts + 1;
+    match send_confirmation_email(
+        email_client,
+        email_templates,
+        &email,
+        base_url,
+        &task.subscription_token,
+    )
+    .await
+    {
+        Ok(()) => delete_task(&mut tx, task.subscriber_id).await?,
+        Err(e) if attempts >= settings.max_attempts => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a confirmation email after {} attempts. Giving up.",
+                attempts
+            );
+            delete_task(&mut tx, task.subscriber_id).await?;
+        }
+        Err(e) => {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send a confirmation email. Retrying later."
+            );
+            let next_attempt_at = Utc::now() + retry_delay(settings, attempts as u32);
+            reschedule_task(&mut tx, task.subscriber_id, attempts, next_attempt_at).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+struct OutboxTask {
+    subscriber_id: Uuid,
+    subscription_token: String,
+    attempts: i32,
+    email: String,
+}
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, OutboxTask)>, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    let task = sqlx::query_as!(
+        OutboxTask,
+        r#"
+        SELECT
+            o.subscriber_id,
+            o.subscription_token,
+            o.attempts,
+            COALESCE(s.pending_email, s.email) AS "email!"
+        FROM confirmation_email_outbox o
+        JOIN subscriptions s ON s.id = o.subscriber_id
+        WHERE o.next_attempt_at <= now()
+        FOR UPDATE OF o SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *tx)
+    .await?;
+    Ok(task.map(|task| (tx, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(tx: &mut PgTransaction, subscriber_id: Uuid) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        "DELETE FROM confirmation_email_outbox WHERE subscriber_id = $1",
+        subscriber_id
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    tx: &mut PgTransaction,
+    subscriber_id: Uuid,
+    attempts: i32,
+    next_attempt_at: chrono::DateTime<Utc>,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE confirmation_email_outbox
+        SET attempts = $2, next_attempt_at = $3
+        WHERE subscriber_id = $1
+        "#,
+        subscriber_id,
+        attempts,
+        next_attempt_at
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(
+        base_delay_milliseconds: u64,
+        max_jitter_milliseconds: u64,
+    ) -> ConfirmationRetrySettings {
+        ConfirmationRetrySettings {
+            base_delay_milliseconds,
+            max_jitter_milliseconds,
+            max_attempts: 5,
+            immediate_attempts: 1,
+            immediate_backoff_milliseconds: 0,
+        }
+    }
+
+    #[test]
+    fn the_base_delay_doubles_on_every_attempt() {
+        let settings = settings(1000, 0);
+        assert_eq!(retry_delay(&settings, 1), Duration::from_millis(1000));
+        assert_eq!(retry_delay(&settings, 2), Duration::from_millis(2000));
+        assert_eq!(retry_delay(&settings, 4), Duration::from_millis(8000));
+    }
+
+    #[test]
+    fn the_jitter_stays_within_the_configured_range() {
+        let settings = settings(1000, 500);
+        for _ in 0..100 {
+            let delay = retry_delay(&settings, 1);
+            assert!(delay >= Duration::from_millis(1000));
+            assert!(delay <= Duration::from_millis(1500));
+        }
+    }
+
+    #[test]
+    fn retries_are_spread_over_the_jitter_range() {
+        let settings = settings(1000, 60_000);
+        let delays: std::collections::HashSet<_> =
+            (0..20).map(|_| retry_delay(&settings, 1)).collect();
+        assert!(delays.len() > 1);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::domain::SubscriberEmail;
+use anyhow::Context;
+use std::collections::HashSet;
+
+/// Email domains that subscriptions are refused from, e.g. disposable inbox providers.
+///
+/// The list is loaded from [crate::configuration::SubscriptionSettings::disposable_domains_file]
+/// at startup. Nothing is blocked when no file is configured.
+#[derive(Debug, Default)]
+pub struct DisposableDomains(HashSet<String>);
+
+impl DisposableDomains {
+    pub fn load(path: Option<&str>) -> Result<Self, anyhow::Error> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the disposable domains in `{}`.", path))?;
+        Ok(Self::parse(&contents))
+    }
+
+    /// Reads one domain per line. Blank lines and lines starting with `#` are ignored.
+    pub fn parse(contents: &str) -> Self {
+        let domains = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_lowercase)
+            .collect();
+        Self(domains)
+    }
+
+    /// Whether the domain of the address is blocked.
+    ///
+    /// [SubscriberEmail] lowercases its domain, so the comparison is case-insensitive.
+    pub fn is_blocked(&self, email: &SubscriberEmail) -> bool {
+        email
+            .as_ref()
+            .rsplit_once('@')
+            .is_some_and(|(_, domain)| self.0.contains(domain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(s: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(s.into()).unwrap()
+    }
+
+    #[test]
+    fn listed_domains_are_blocked_whatever_their_case() {
+        let domains = DisposableDomains::parse("# Disposable inboxes\n\nMailinator.com\n");
+        assert!(domains.is_blocked(&email("user@mailinator.com")));
+        assert!(domains.is_blocked(&email("user@MAILINATOR.COM")));
+        assert!(!domains.is_blocked(&email("user@example.com")));
+    }
+
+    #[test]
+    fn nothing_is_blocked_without_a_file() {
+        let domains = DisposableDomains::load(None).unwrap();
+        assert!(!domains.is_blocked(&email("user@mailinator.com")));
+    }
+}
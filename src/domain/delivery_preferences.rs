@@ -0,0 +1,37 @@
+/// The format in which a subscriber receives newsletter issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFormat {
+    /// The HTML content, with the plain text as an alternative.
+    Html,
+    /// The plain text content only.
+    Text,
+}
+
+impl ContentFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentFormat::Html => "html",
+            ContentFormat::Text => "text",
+        }
+    }
+}
+
+/// How often a subscriber wants to receive newsletter issues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryFrequency {
+    /// Every issue, as soon as it is published.
+    EveryIssue,
+    /// At most one issue a week. Later issues wait until a week has passed.
+    Weekly,
+}
+
+impl DeliveryFrequency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryFrequency::EveryIssue => "every_issue",
+            DeliveryFrequency::Weekly => "weekly",
+        }
+    }
+}
@@ -0,0 +1,35 @@
+/// The lane of the delivery queue an issue is sent through, as stored in the `priority` columns.
+///
+/// Deliveries of a higher priority are dequeued first, so a time-sensitive issue
+/// is not stuck behind the tail of a large bulk send.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[repr(i16)]
+pub enum DeliveryPriority {
+    /// Regular newsletter issues.
+    #[default]
+    Bulk = 0,
+    /// Announcements that must go out ahead of the bulk queue, e.g. a security notice.
+    Transactional = 1,
+}
+
+impl DeliveryPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryPriority::Bulk => "bulk",
+            DeliveryPriority::Transactional => "transactional",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeliveryPriority;
+
+    #[test]
+    fn transactional_deliveries_rank_above_bulk_ones() {
+        assert!(DeliveryPriority::Transactional as i16 > DeliveryPriority::Bulk as i16);
+    }
+}
@@ -1,7 +1,21 @@
+pub mod delivery_preferences;
+pub mod delivery_priority;
 pub mod new_subscriber;
+pub mod newsletter_issue;
 pub mod subscriber_email;
+pub mod subscriber_locale;
 pub mod subscriber_name;
+pub mod subscriber_tag;
+pub mod subscriber_timezone;
+pub mod subscription_status;
 
-pub use new_subscriber::NewSubscriber;
+pub use delivery_preferences::{ContentFormat, DeliveryFrequency};
+pub use delivery_priority::DeliveryPriority;
+pub use new_subscriber::{NewSubscriber, NewSubscriberError};
+pub use newsletter_issue::{IssueLimits, IssueParsingError, NewsletterIssue};
 pub use subscriber_email::SubscriberEmail;
+pub use subscriber_locale::SubscriberLocale;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
+pub use subscriber_timezone::SubscriberTimezone;
+pub use subscription_status::SubscriptionStatus;
@@ -1,7 +1,83 @@
-use crate::domain::subscriber_email::SubscriberEmail;
-use crate::domain::subscriber_name::SubscriberName;
+use crate::domain::subscriber_email::{EmailParsingError, SubscriberEmail};
+use crate::domain::subscriber_locale::{LocaleParsingError, SubscriberLocale};
+use crate::domain::subscriber_name::{NameParsingError, SubscriberName};
+use crate::domain::subscriber_tag::{SubscriberTag, TagParsingError};
+use crate::domain::subscriber_timezone::{SubscriberTimezone, TimezoneParsingError};
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    pub tags: Vec<SubscriberTag>,
+    pub locale: Option<SubscriberLocale>,
+    pub timezone: Option<SubscriberTimezone>,
+}
+
+/// Why a [NewSubscriber] could not be built. Every invalid field is set,
+/// so that they can all be reported at once.
+#[derive(Debug, Default)]
+pub struct NewSubscriberError {
+    pub email: Option<EmailParsingError>,
+    pub name: Option<NameParsingError>,
+    pub tags: Option<TagParsingError>,
+    pub locale: Option<LocaleParsingError>,
+    pub timezone: Option<TimezoneParsingError>,
+}
+
+impl NewSubscriberError {
+    /// Whether every field is valid.
+    pub fn is_empty(&self) -> bool {
+        self.field_errors().is_empty()
+    }
+
+    /// The name and error message of each invalid field, in the order of the form.
+    pub fn field_errors(&self) -> Vec<(&'static str, String)> {
+        fn message(e: &Option<impl ToString>) -> Option<String> {
+            e.as_ref().map(ToString::to_string)
+        }
+        [
+            ("email", message(&self.email)),
+            ("name", message(&self.name)),
+            ("tags", message(&self.tags)),
+            ("locale", message(&self.locale)),
+            ("timezone", message(&self.timezone)),
+        ]
+        .into_iter()
+        .filter_map(|(field, message)| Some((field, message?)))
+        .collect()
+    }
+}
+
+impl std::fmt::Display for NewSubscriberError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let messages: Vec<_> = self
+            .field_errors()
+            .into_iter()
+            .map(|(_, message)| message)
+            .collect();
+        write!(f, "{}", messages.join(" "))
+    }
+}
+
+impl std::error::Error for NewSubscriberError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_invalid_fields_are_reported() {
+        let error = NewSubscriberError {
+            name: Some(NameParsingError),
+            timezone: Some(TimezoneParsingError),
+            ..Default::default()
+        };
+        let fields: Vec<_> = error
+            .field_errors()
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        assert_eq!(fields, ["name", "timezone"]);
+        assert!(!error.is_empty());
+        assert!(NewSubscriberError::default().is_empty());
+    }
 }
@@ -0,0 +1,207 @@
+use std::fmt::Display;
+
+/// Upper bounds on the size of an issue, as set in [crate::configuration::NewsletterSettings].
+#[derive(Debug, Clone, Copy)]
+pub struct IssueLimits {
+    /// Maximum length of the title, in characters.
+    pub max_title_length: usize,
+    /// Maximum size of each body, in bytes.
+    pub max_content_length: usize,
+}
+
+impl IssueLimits {
+    /// No upper bound, for issues whose size was checked when they were published.
+    pub const UNBOUNDED: Self = Self {
+        max_title_length: usize::MAX,
+        max_content_length: usize::MAX,
+    };
+}
+
+/// The content of a newsletter issue: a title, and the same body as HTML and as plain text.
+#[derive(Debug)]
+pub struct NewsletterIssue {
+    title: String,
+    html_content: String,
+    text_content: String,
+}
+
+impl NewsletterIssue {
+    pub fn parse(
+        title: String,
+        html_content: String,
+        text_content: String,
+        limits: &IssueLimits,
+    ) -> Result<Self, IssueParsingError> {
+        check_field(
+            "title",
+            &title,
+            title.chars().count(),
+            limits.max_title_length,
+        )?;
+        check_field(
+            "html_content",
+            &html_content,
+            html_content.len(),
+            limits.max_content_length,
+        )?;
+        check_field(
+            "text_content",
+            &text_content,
+            text_content.len(),
+            limits.max_content_length,
+        )?;
+        Ok(Self {
+            title,
+            html_content,
+            text_content,
+        })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn html_content(&self) -> &str {
+        &self.html_content
+    }
+
+    pub fn text_content(&self) -> &str {
+        &self.text_content
+    }
+}
+
+fn check_field(
+    field: &'static str,
+    value: &str,
+    length: usize,
+    max_length: usize,
+) -> Result<(), IssueParsingError> {
+    if value.trim().is_empty() {
+        return Err(IssueParsingError::Empty { field });
+    }
+    if length > max_length {
+        return Err(IssueParsingError::TooLong { field, max_length });
+    }
+    Ok(())
+}
+
+/// Why the content of a [NewsletterIssue] is invalid.
+#[derive(Debug, PartialEq, Eq)]
+pub enum IssueParsingError {
+    /// The field is empty or only made of whitespace.
+    Empty { field: &'static str },
+    /// The field is longer than its limit, in characters for the title and bytes for the bodies.
+    TooLong {
+        field: &'static str,
+        max_length: usize,
+    },
+}
+
+impl Display for IssueParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IssueParsingError::Empty { field } => write!(f, "`{}` must not be empty.", field),
+            IssueParsingError::TooLong {
+                field: "title",
+                max_length,
+            } => write!(f, "`title` must be at most {} characters long.", max_length),
+            IssueParsingError::TooLong { field, max_length } => {
+                write!(f, "`{}` must be at most {} bytes long.", field, max_length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for IssueParsingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits() -> IssueLimits {
+        IssueLimits {
+            max_title_length: 10,
+            max_content_length: 16,
+        }
+    }
+
+    fn parse(title: &str, html_content: &str, text_content: &str) -> Result<(), IssueParsingError> {
+        NewsletterIssue::parse(
+            title.into(),
+            html_content.into(),
+            text_content.into(),
+            &limits(),
+        )
+        .map(|_| ())
+    }
+
+    #[test]
+    fn a_valid_issue_is_accepted() {
+        assert_eq!(parse("Title", "<p>Body</p>", "Body"), Ok(()));
+    }
+
+    #[test]
+    fn an_empty_title_is_rejected() {
+        for title in ["", "  \n"] {
+            assert_eq!(
+                parse(title, "<p>Body</p>", "Body"),
+                Err(IssueParsingError::Empty { field: "title" })
+            );
+        }
+    }
+
+    #[test]
+    fn empty_bodies_are_rejected() {
+        assert_eq!(
+            parse("Title", "", "Body"),
+            Err(IssueParsingError::Empty {
+                field: "html_content"
+            })
+        );
+        assert_eq!(
+            parse("Title", "<p>Body</p>", " "),
+            Err(IssueParsingError::Empty {
+                field: "text_content"
+            })
+        );
+    }
+
+    #[test]
+    fn the_title_is_limited_in_characters() {
+        assert_eq!(parse("éééééééééé", "<p>Body</p>", "Body"), Ok(()));
+        assert_eq!(
+            parse("A title that is too long", "<p>Body</p>", "Body"),
+            Err(IssueParsingError::TooLong {
+                field: "title",
+                max_length: 10
+            })
+        );
+    }
+
+    #[test]
+    fn an_oversized_body_is_rejected() {
+        let error = parse("Title", "<p>Body</p>", &"a".repeat(17)).unwrap_err();
+        assert_eq!(
+            error,
+            IssueParsingError::TooLong {
+                field: "text_content",
+                max_length: 16
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "`text_content` must be at most 16 bytes long."
+        );
+    }
+
+    #[test]
+    fn unbounded_limits_still_reject_empty_fields() {
+        let issue = NewsletterIssue::parse(
+            "Title".repeat(1000),
+            "<p>Body</p>".repeat(1000),
+            String::new(),
+            &IssueLimits::UNBOUNDED,
+        );
+        assert!(issue.is_err());
+    }
+}
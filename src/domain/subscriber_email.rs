@@ -2,17 +2,31 @@ use crate::utils::ParsingError;
 use std::fmt::{Debug, Display};
 use validator::ValidateEmail;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
+    /// Parses an email address, normalizing it to the form stored in `subscriptions.email`.
+    ///
+    /// Only the domain is lowercased. Domains are case-insensitive, so `ursula@Example.com`
+    /// and `ursula@example.com` are the same mailbox and must collide on the unique constraint.
+    /// The local part is kept as typed: RFC 5321 leaves its case to the receiving server,
+    /// and lowercasing it could merge two distinct mailboxes.
     pub fn parse(s: String) -> Result<Self, EmailParsingError> {
-        if ValidateEmail::validate_email(&s) {
-            Ok(Self(s))
-        } else {
-            Err(EmailParsingError)
+        if !ValidateEmail::validate_email(&s) {
+            return Err(EmailParsingError);
+        }
+        match s.rsplit_once('@') {
+            Some((local_part, domain)) => {
+                Ok(Self(format!("{}@{}", local_part, domain.to_lowercase())))
+            }
+            None => Err(EmailParsingError),
         }
     }
+
+    pub fn into_inner(self) -> String {
+        self.0
+    }
 }
 
 impl AsRef<str> for SubscriberEmail {
@@ -66,6 +80,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn display_shows_the_stored_email() {
+        let email = SubscriberEmail::parse("ursula@domain.com".to_string()).unwrap();
+        assert_eq!(email.to_string(), "ursula@domain.com");
+        assert_eq!(email.into_inner(), "ursula@domain.com");
+    }
+
     #[test]
     fn empty_string_is_rejected() {
         let email = "".to_string();
@@ -90,6 +111,19 @@ mod tests {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[test]
+    fn the_domain_is_lowercased() {
+        let email = SubscriberEmail::parse("Ursula@Example.COM".to_string()).unwrap();
+        assert_eq!(email.as_ref(), "Ursula@example.com");
+    }
+
+    #[test]
+    fn differently_cased_domains_parse_to_the_same_email() {
+        let first = SubscriberEmail::parse("ursula@Example.com".to_string()).unwrap();
+        let second = SubscriberEmail::parse("ursula@example.com".to_string()).unwrap();
+        assert_eq!(first.as_ref(), second.as_ref());
+    }
+
     #[quickcheck_macros::quickcheck]
     fn valid_emails_are_parsed_successfully(valid_email: ValidEmailFixture) -> bool {
         SubscriberEmail::parse(valid_email.0).is_ok()
@@ -0,0 +1,92 @@
+use crate::utils::ParsingError;
+use language_tags::LanguageTag;
+
+/// The preferred locale of a subscriber, as a BCP-47 language tag, e.g. `en-US`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberLocale(LanguageTag);
+
+impl SubscriberLocale {
+    /// The tag must be well-formed and only use subtags from the IANA registry.
+    pub fn parse(s: String) -> Result<Self, LocaleParsingError> {
+        let tag = LanguageTag::parse(s.trim()).map_err(|_| LocaleParsingError)?;
+        tag.validate().map_err(|_| LocaleParsingError)?;
+        Ok(Self(tag))
+    }
+
+    /// The tags to look a translation up with, most specific first, in lowercase:
+    /// `zh-Hant-TW` falls back to `zh-hant-tw`, `zh-hant` and `zh`.
+    pub fn fallbacks(&self) -> Vec<String> {
+        let tag = self.0.as_str().to_lowercase();
+        let mut fallbacks = vec![tag.clone()];
+        let mut rest = tag.as_str();
+        while let Some((prefix, _)) = rest.rsplit_once('-') {
+            // A prefix ending with a singleton, e.g. the `x` of `en-x-private`, is not a tag.
+            let last_subtag = prefix.rsplit('-').next().unwrap_or(prefix);
+            if last_subtag.len() > 1 {
+                fallbacks.push(prefix.to_owned());
+            }
+            rest = prefix;
+        }
+        fallbacks
+    }
+}
+
+impl AsRef<str> for SubscriberLocale {
+    fn as_ref(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+#[derive(Debug)]
+pub struct LocaleParsingError;
+
+impl std::fmt::Display for LocaleParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid subscriber locale.")
+    }
+}
+
+impl From<Box<LocaleParsingError>> for Box<dyn ParsingError> {
+    fn from(value: Box<LocaleParsingError>) -> Self {
+        value
+    }
+}
+
+impl std::error::Error for LocaleParsingError {}
+impl ParsingError for LocaleParsingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::assert_err;
+
+    #[test]
+    fn bcp_47_tags_are_valid() {
+        for locale in ["en", "en-US", "ko-KR", "zh-Hant-TW", "es-419"] {
+            let parsed = SubscriberLocale::parse(locale.to_string()).unwrap();
+            assert_eq!(parsed.as_ref(), locale);
+        }
+    }
+
+    #[test]
+    fn malformed_tags_are_rejected() {
+        for locale in ["", "english", "en_US", "en-", "a-very-long-subtag-indeed"] {
+            assert_err!(SubscriberLocale::parse(locale.to_string()));
+        }
+    }
+
+    #[test]
+    fn fallbacks_drop_subtags_from_the_end() {
+        let locale = SubscriberLocale::parse("zh-Hant-TW".to_string()).unwrap();
+        assert_eq!(locale.fallbacks(), ["zh-hant-tw", "zh-hant", "zh"]);
+        let locale = SubscriberLocale::parse("en-x-private".to_string()).unwrap();
+        assert_eq!(locale.fallbacks(), ["en-x-private", "en"]);
+    }
+
+    #[test]
+    fn unregistered_subtags_are_rejected() {
+        for locale in ["xx-US", "en-UU", "zz"] {
+            assert_err!(SubscriberLocale::parse(locale.to_string()), "{}", locale);
+        }
+    }
+}
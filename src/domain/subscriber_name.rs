@@ -1,4 +1,5 @@
 use crate::utils::ParsingError;
+use std::fmt::Display;
 use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Debug)]
@@ -16,6 +17,10 @@ impl SubscriberName {
         }
     }
 
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
     fn is_empty_or_whitespace(s: &str) -> bool {
         s.trim().is_empty()
     }
@@ -39,6 +44,12 @@ impl AsRef<str> for SubscriberName {
     }
 }
 
+impl Display for SubscriberName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 #[derive(Debug)]
 pub struct NameParsingError;
 
@@ -101,4 +112,11 @@ mod tests {
         let name = "Ursula Le Guin".to_string();
         assert_ok!(SubscriberName::parse(name));
     }
+
+    #[test]
+    fn display_shows_the_stored_name() {
+        let name = SubscriberName::parse("Ursula Le Guin".to_string()).unwrap();
+        assert_eq!(name.to_string(), "Ursula Le Guin");
+        assert_eq!(name.into_inner(), "Ursula Le Guin");
+    }
 }
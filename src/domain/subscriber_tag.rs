@@ -0,0 +1,100 @@
+use crate::utils::ParsingError;
+
+/// A lowercase label used to group subscribers into segments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    /// Tags are case-insensitive and stored in lowercase.
+    /// Only ASCII letters, digits, `-` and `_` are allowed, up to 64 characters.
+    pub fn parse(s: String) -> Result<Self, TagParsingError> {
+        let s = s.trim().to_lowercase();
+        if s.is_empty()
+            || s.len() > 64
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            Err(TagParsingError)
+        } else {
+            Ok(Self(s))
+        }
+    }
+
+    /// Parses a comma-separated list of tags, ignoring empty entries and duplicates.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, TagParsingError> {
+        let mut tags: Vec<Self> = Vec::new();
+        for tag in s.split(',').filter(|t| !t.trim().is_empty()) {
+            let tag = Self::parse(tag.to_owned())?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug)]
+pub struct TagParsingError;
+
+impl std::fmt::Display for TagParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid subscriber tag.")
+    }
+}
+
+impl From<Box<TagParsingError>> for Box<dyn ParsingError> {
+    fn from(value: Box<TagParsingError>) -> Self {
+        value
+    }
+}
+
+impl std::error::Error for TagParsingError {}
+impl ParsingError for TagParsingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::{assert_err, assert_ok};
+
+    #[test]
+    fn tags_are_lowercased_and_trimmed() {
+        let tag = SubscriberTag::parse(" Rust-Lang ".to_string()).unwrap();
+        assert_eq!(tag.as_ref(), "rust-lang");
+    }
+
+    #[test]
+    fn empty_tags_are_rejected() {
+        assert_err!(SubscriberTag::parse(" ".to_string()));
+    }
+
+    #[test]
+    fn tags_with_invalid_characters_are_rejected() {
+        for tag in ["rust lang", "rust,lang", "<script>", "ü"] {
+            assert_err!(SubscriberTag::parse(tag.to_string()));
+        }
+    }
+
+    #[test]
+    fn a_64_character_tag_is_valid() {
+        assert_ok!(SubscriberTag::parse("a".repeat(64)));
+    }
+
+    #[test]
+    fn a_tag_longer_than_64_characters_is_rejected() {
+        assert_err!(SubscriberTag::parse("a".repeat(65)));
+    }
+
+    #[test]
+    fn a_list_is_split_on_commas_without_duplicates() {
+        let tags = SubscriberTag::parse_list("rust, Go,,rust").unwrap();
+        let tags: Vec<_> = tags.iter().map(AsRef::as_ref).collect();
+        assert_eq!(tags, vec!["rust", "go"]);
+    }
+}
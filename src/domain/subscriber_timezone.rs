@@ -0,0 +1,66 @@
+use crate::utils::ParsingError;
+
+/// The IANA time zone of a subscriber, e.g. `America/New_York`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberTimezone(chrono_tz::Tz);
+
+impl SubscriberTimezone {
+    /// Only names from the IANA time zone database are accepted.
+    pub fn parse(s: String) -> Result<Self, TimezoneParsingError> {
+        s.trim()
+            .parse::<chrono_tz::Tz>()
+            .map(Self)
+            .map_err(|_| TimezoneParsingError)
+    }
+}
+
+impl AsRef<str> for SubscriberTimezone {
+    fn as_ref(&self) -> &str {
+        self.0.name()
+    }
+}
+
+#[derive(Debug)]
+pub struct TimezoneParsingError;
+
+impl std::fmt::Display for TimezoneParsingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid subscriber timezone.")
+    }
+}
+
+impl From<Box<TimezoneParsingError>> for Box<dyn ParsingError> {
+    fn from(value: Box<TimezoneParsingError>) -> Self {
+        value
+    }
+}
+
+impl std::error::Error for TimezoneParsingError {}
+impl ParsingError for TimezoneParsingError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claim::assert_err;
+
+    #[test]
+    fn iana_time_zones_are_valid() {
+        for timezone in ["America/New_York", "Europe/Paris", "Asia/Seoul", "UTC"] {
+            let parsed = SubscriberTimezone::parse(timezone.to_string()).unwrap();
+            assert_eq!(parsed.as_ref(), timezone);
+        }
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        let parsed = SubscriberTimezone::parse(" Europe/Paris ".to_string()).unwrap();
+        assert_eq!(parsed.as_ref(), "Europe/Paris");
+    }
+
+    #[test]
+    fn unknown_time_zones_are_rejected() {
+        for timezone in ["", "Mars/Phobos", "New York", "+09:00"] {
+            assert_err!(SubscriberTimezone::parse(timezone.to_string()));
+        }
+    }
+}
@@ -0,0 +1,42 @@
+/// Where a subscriber stands in the subscription lifecycle, as stored in `subscriptions.status`.
+///
+/// The column is backed by the `subscription_status` Postgres enum, so a misspelled status
+/// in a query is rejected by the database instead of silently matching no row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    /// Subscribed, but the email address has not been confirmed yet.
+    PendingConfirmation,
+    /// Receives newsletter issues.
+    Confirmed,
+    /// Opted out. Kept so that the address is not subscribed again by mistake.
+    Unsubscribed,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SubscriptionStatus::PendingConfirmation => "pending_confirmation",
+            SubscriptionStatus::Confirmed => "confirmed",
+            SubscriptionStatus::Unsubscribed => "unsubscribed",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus;
+
+    #[test]
+    fn statuses_serialize_to_their_stored_names() {
+        for status in [
+            SubscriptionStatus::PendingConfirmation,
+            SubscriptionStatus::Confirmed,
+            SubscriptionStatus::Unsubscribed,
+        ] {
+            let json = serde_json::to_string(&status).unwrap();
+            assert_eq!(json, format!("\"{}\"", status.as_str()));
+        }
+    }
+}
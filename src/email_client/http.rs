@@ -0,0 +1,347 @@
+use super::{Email, EmailClientError, EmailTransport};
+use anyhow::Context;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use std::time::Duration;
+
+/// Sends emails through the Postmark HTTP API.
+pub struct HttpTransport {
+    http_client: Client,
+    base_url: reqwest::Url,
+    authorization_token: Secret<String>,
+}
+
+impl HttpTransport {
+    pub fn new(
+        base_url: String,
+        authorization_token: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("Failed to create HTTP client");
+        Self {
+            http_client,
+            base_url: reqwest::Url::parse(&base_url).expect("Invalid base URL"),
+            authorization_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for HttpTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailClientError> {
+        let url = self.base_url.join("email").expect("Failed to create URL");
+
+        let from = email.from.to_string();
+        let request_body = SendEmailRequest {
+            from: &from,
+            to: email.to.as_ref(),
+            subject: email.subject,
+            html_body: email.html_body,
+            text_body: email.text_body,
+            reply_to: email.reply_to.map(AsRef::as_ref),
+            headers: email
+                .headers
+                .iter()
+                .map(|&(name, value)| EmailHeader { name, value })
+                .collect(),
+        };
+
+        // Nothing has been accepted when the request itself fails, e.g. on a timeout.
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(EmailClientError::transient)?;
+
+        let status = response.status();
+        let retry_after = retry_after(response.headers());
+        match response.error_for_status() {
+            Ok(_) => Ok(()),
+            Err(e) if is_transient(status) => Err(EmailClientError::Transient {
+                retry_after,
+                source: e.into(),
+            }),
+            Err(e) => Err(EmailClientError::permanent(e)),
+        }
+    }
+
+    /// Fetches the server details, which requires a valid server token.
+    async fn check_connection(&self) -> Result<(), anyhow::Error> {
+        let url = self.base_url.join("server")?;
+        self.http_client
+            .get(url.clone())
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach `{}`.", url))?
+            .error_for_status()
+            .with_context(|| format!("`{}` rejected the request.", url))?;
+        Ok(())
+    }
+}
+
+/// Rate limiting and server-side failures are worth retrying; any other error status is not.
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// The longest `Retry-After` delay honoured. Longer delays are cut down to it.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Reads a `Retry-After` header given in seconds. The HTTP-date form is not supported.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds: u64 = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    /// Left out of plain-text emails.
+    #[serde(skip_serializing_if = "str::is_empty")]
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<EmailHeader<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailClient, Sender};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use std::sync::Arc;
+    use wiremock::matchers::{body_partial_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            if let Ok(body) = serde_json::from_slice::<serde_json::Value>(&request.body) {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email_client(base_url: String) -> EmailClient {
+        let transport = HttpTransport::new(
+            base_url,
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+        );
+        EmailClient::new(Sender::new(email(), None), Arc::new(transport))
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+    }
+
+    #[tokio::test]
+    async fn the_from_field_includes_the_sender_name() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let sender = Sender::new(
+            SubscriberEmail::parse("sender@example.com".into()).unwrap(),
+            Some("Newsletter Team".into()),
+        );
+        let transport = HttpTransport::new(
+            mock_server.uri(),
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+        );
+        let email_client = EmailClient::new(sender, Arc::new(transport));
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "From": "Newsletter Team <sender@example.com>"
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn a_cloned_client_shares_the_transport_and_sends_correctly() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::builder()
+            .sender(Sender::new(email(), None))
+            .transport(HttpTransport::new(
+                mock_server.uri(),
+                Secret::new(Faker.fake()),
+                std::time::Duration::from_millis(200),
+            ))
+            .build()
+            .unwrap();
+        let cloned_client = email_client.clone();
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let original_outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        let cloned_outcome = cloned_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert!(Arc::ptr_eq(
+            &email_client.transport,
+            &cloned_client.transport
+        ));
+        assert!(original_outcome.is_ok());
+        assert!(cloned_outcome.is_ok());
+    }
+
+    #[tokio::test]
+    async fn the_retry_after_delay_of_a_rate_limited_request_is_reported() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "30"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        match outcome {
+            Err(EmailClientError::Transient { retry_after, .. }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)))
+            }
+            _ => panic!("Expected a transient failure."),
+        }
+    }
+
+    #[test]
+    fn server_errors_and_rate_limiting_are_transient() {
+        assert!(is_transient(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_transient(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!is_transient(StatusCode::UNPROCESSABLE_ENTITY));
+        assert!(!is_transient(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn a_retry_after_date_is_ignored() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn a_huge_retry_after_delay_is_capped() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, u64::MAX.to_string().parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn building_a_client_without_a_transport_fails() {
+        let outcome = EmailClient::builder()
+            .sender(Sender::new(email(), None))
+            .build();
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,593 @@
+mod http;
+mod smtp;
+
+use crate::domain::SubscriberEmail;
+use crate::utils::error_chain_fmt;
+use anyhow::Context;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use std::time::Duration;
+
+pub use http::HttpTransport;
+pub use smtp::SmtpTransport;
+
+/// The address emails are sent from, with an optional display name.
+#[derive(Debug, Clone)]
+pub struct Sender {
+    email: SubscriberEmail,
+    name: Option<String>,
+}
+
+impl Sender {
+    /// A blank display name is treated as no display name.
+    pub fn new(email: SubscriberEmail, name: Option<String>) -> Self {
+        let name = name
+            .map(|name| name.trim().to_owned())
+            .filter(|name| !name.is_empty());
+        Self { email, name }
+    }
+
+    pub fn email(&self) -> &SubscriberEmail {
+        &self.email
+    }
+}
+
+/// Formats the sender as a `From` header value, e.g. `Newsletter Team <sender@example.com>`.
+///
+/// The display name is quoted when it contains characters that are not allowed
+/// in an unquoted phrase, such as commas or dots.
+impl std::fmt::Display for Sender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(name) = &self.name else {
+            return write!(f, "{}", self.email.as_ref());
+        };
+        let is_atext =
+            |c: char| c.is_alphanumeric() || c == ' ' || "!#$%&'*+-/=?^_`{|}~".contains(c);
+        if name.chars().all(is_atext) {
+            write!(f, "{} <{}>", name, self.email.as_ref())
+        } else {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+            write!(f, "\"{}\" <{}>", escaped, self.email.as_ref())
+        }
+    }
+}
+
+/// An email ready to be handed to an [EmailTransport].
+pub struct Email<'a> {
+    pub from: &'a Sender,
+    pub to: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    pub reply_to: Option<&'a SubscriberEmail>,
+    /// Extra headers as name-value pairs, e.g. `List-Unsubscribe`.
+    pub headers: &'a [(&'a str, &'a str)],
+}
+
+/// The error type for sending an email, telling whether it is worth trying again.
+#[derive(thiserror::Error)]
+pub enum EmailClientError {
+    /// The failure may go away on its own, e.g. a timeout, a `429` or a `5xx`.
+    /// `retry_after` is set when the provider said how long to wait.
+    #[error("Failed to send the email. Trying again later may succeed.")]
+    Transient {
+        retry_after: Option<Duration>,
+        #[source]
+        source: anyhow::Error,
+    },
+    /// The email has been rejected as it is, e.g. with a `4xx`. Sending it again will fail too.
+    #[error("The email has been rejected.")]
+    Permanent(#[source] anyhow::Error),
+}
+
+impl EmailClientError {
+    pub fn transient(source: impl Into<anyhow::Error>) -> Self {
+        Self::Transient {
+            retry_after: None,
+            source: source.into(),
+        }
+    }
+
+    pub fn permanent(source: impl Into<anyhow::Error>) -> Self {
+        Self::Permanent(source.into())
+    }
+}
+
+impl Debug for EmailClientError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// A way of delivering emails, e.g. an HTTP API or an SMTP relay.
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailClientError>;
+
+    /// Checks that the provider is reachable and accepts the credentials, without sending anything.
+    async fn check_connection(&self) -> Result<(), anyhow::Error>;
+}
+
+/// Sends emails from the configured sender through an [EmailTransport].
+///
+/// Clones share the same transport, and therefore the same connection pool.
+#[derive(Clone)]
+pub struct EmailClient {
+    sender: Sender,
+    reply_to: Option<SubscriberEmail>,
+    transport: Arc<dyn EmailTransport>,
+}
+
+impl EmailClient {
+    pub fn new(sender: Sender, transport: Arc<dyn EmailTransport>) -> Self {
+        Self {
+            sender,
+            reply_to: None,
+            transport,
+        }
+    }
+
+    pub fn builder() -> EmailClientBuilder {
+        EmailClientBuilder::default()
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<(), EmailClientError> {
+        self.send_email_with_headers(recipient, subject, html_content, text_content, &[])
+            .await
+    }
+
+    /// Checks that the sender's domain resolves and that the provider accepts the credentials,
+    /// so that a misconfigured deployment fails before sending its first email.
+    pub async fn verify(&self) -> Result<(), anyhow::Error> {
+        let sender_email = self.sender.email().as_ref();
+        let domain = sender_email
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+            .ok_or_else(|| anyhow::anyhow!("`{}` has no domain.", sender_email))?;
+        let mut addresses = tokio::net::lookup_host((domain, 0))
+            .await
+            .with_context(|| format!("The sender domain `{}` does not resolve.", domain))?;
+        anyhow::ensure!(
+            addresses.next().is_some(),
+            "The sender domain `{}` has no address.",
+            domain
+        );
+        self.check_connection()
+            .await
+            .context("The email provider cannot be reached with the configured credentials.")
+    }
+
+    /// Checks that the provider is reachable and accepts the credentials, without sending anything.
+    pub async fn check_connection(&self) -> Result<(), anyhow::Error> {
+        self.transport.check_connection().await
+    }
+
+    /// Sends an email with extra headers, such as `List-Unsubscribe` for newsletter issues.
+    pub async fn send_email_with_headers(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<(), EmailClientError> {
+        let email = Email {
+            from: &self.sender,
+            to: recipient,
+            subject,
+            html_body: html_content,
+            text_body: text_content,
+            reply_to: self.reply_to.as_ref(),
+            headers,
+        };
+        self.transport.send(&email).await
+    }
+}
+
+/// Builds an [EmailClient] from a [Sender] and an [EmailTransport].
+#[derive(Default)]
+pub struct EmailClientBuilder {
+    sender: Option<Sender>,
+    reply_to: Option<SubscriberEmail>,
+    transport: Option<Arc<dyn EmailTransport>>,
+}
+
+impl EmailClientBuilder {
+    pub fn sender(mut self, sender: Sender) -> Self {
+        self.sender = Some(sender);
+        self
+    }
+
+    /// The address replies are sent to, when it differs from the sender's.
+    pub fn reply_to(mut self, reply_to: Option<SubscriberEmail>) -> Self {
+        self.reply_to = reply_to;
+        self
+    }
+
+    pub fn transport(mut self, transport: impl EmailTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Uses a transport that is already shared with other clients.
+    pub fn shared_transport(mut self, transport: Arc<dyn EmailTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn build(self) -> Result<EmailClient, anyhow::Error> {
+        let sender = self
+            .sender
+            .ok_or_else(|| anyhow::anyhow!("The email client needs a sender."))?;
+        let transport = self
+            .transport
+            .ok_or_else(|| anyhow::anyhow!("The email client needs a transport."))?;
+        Ok(EmailClient {
+            sender,
+            reply_to: self.reply_to,
+            transport,
+        })
+    }
+}
+
+/// The test suite every transport has to pass, run against a fake server for each of them.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::SmtpSettings;
+    use claim::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use wiremock::matchers::any;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const SUBJECT: &str = "Weekly digest";
+    const HTML_BODY: &str = "<p>Hello from the newsletter!</p>";
+    const TEXT_BODY: &str = "Hello from the newsletter!";
+
+    /// How the fake server answers the email it is given.
+    #[derive(Clone, Copy, PartialEq)]
+    enum Reply {
+        Accept,
+        Reject,
+        /// Asks to try again later, e.g. because of rate limiting.
+        Defer,
+        Stall,
+    }
+
+    /// What the fake server received: the recipient and the raw message.
+    struct ReceivedEmail {
+        to: String,
+        raw: String,
+    }
+
+    trait FakeServer: Sized {
+        async fn start(reply: Reply) -> Self;
+        fn transport(&self) -> Arc<dyn EmailTransport>;
+        async fn received(&self) -> Vec<ReceivedEmail>;
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn sender() -> Sender {
+        Sender::new(email(), Some("Newsletter Team".into()))
+    }
+
+    fn timeout() -> Duration {
+        Duration::from_millis(200)
+    }
+
+    async fn send<S: FakeServer>(
+        server: &S,
+        recipient: &SubscriberEmail,
+    ) -> Result<(), EmailClientError> {
+        EmailClient::new(sender(), server.transport())
+            .send_email(recipient, SUBJECT, HTML_BODY, TEXT_BODY)
+            .await
+    }
+
+    async fn the_email_reaches_the_recipient<S: FakeServer>() {
+        // Arrange
+        let server = S::start(Reply::Accept).await;
+        let recipient = email();
+
+        // Act
+        let outcome = send(&server, &recipient).await;
+
+        // Assert
+        assert_ok!(outcome);
+        let received = server.received().await;
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].to, recipient.as_ref());
+        assert!(received[0].raw.contains("Newsletter Team"));
+        assert!(received[0].raw.contains(SUBJECT));
+        assert!(received[0].raw.contains(TEXT_BODY));
+    }
+
+    async fn the_reply_to_and_extra_headers_are_sent<S: FakeServer>() {
+        // Arrange
+        let server = S::start(Reply::Accept).await;
+        let reply_to = SubscriberEmail::parse("replies@example.com".into()).unwrap();
+        let client = EmailClient::builder()
+            .sender(sender())
+            .reply_to(Some(reply_to))
+            .shared_transport(server.transport())
+            .build()
+            .unwrap();
+        let headers = [("List-Unsubscribe", "<https://example.com/unsubscribe>")];
+
+        // Act
+        let outcome = client
+            .send_email_with_headers(&email(), SUBJECT, HTML_BODY, TEXT_BODY, &headers)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let received = server.received().await;
+        assert!(received[0].raw.contains("replies@example.com"));
+        assert!(received[0].raw.contains("List-Unsubscribe"));
+        assert!(received[0]
+            .raw
+            .contains("<https://example.com/unsubscribe>"));
+    }
+
+    async fn sending_fails_permanently_if_the_server_rejects_the_email<S: FakeServer>() {
+        // Arrange
+        let server = S::start(Reply::Reject).await;
+
+        // Act
+        let outcome = send(&server, &email()).await;
+
+        // Assert
+        assert!(matches!(
+            assert_err!(outcome),
+            EmailClientError::Permanent(_)
+        ));
+    }
+
+    async fn sending_fails_transiently_if_the_server_defers_the_email<S: FakeServer>() {
+        // Arrange
+        let server = S::start(Reply::Defer).await;
+
+        // Act
+        let outcome = send(&server, &email()).await;
+
+        // Assert
+        assert!(matches!(
+            assert_err!(outcome),
+            EmailClientError::Transient { .. }
+        ));
+    }
+
+    async fn sending_times_out_if_the_server_takes_too_long<S: FakeServer>() {
+        // Arrange
+        let server = S::start(Reply::Stall).await;
+
+        // Act
+        let outcome = send(&server, &email()).await;
+
+        // Assert
+        assert!(matches!(
+            assert_err!(outcome),
+            EmailClientError::Transient { .. }
+        ));
+    }
+
+    struct FakeHttpServer(MockServer);
+
+    impl FakeServer for FakeHttpServer {
+        async fn start(reply: Reply) -> Self {
+            let server = MockServer::start().await;
+            let response = match reply {
+                Reply::Accept => ResponseTemplate::new(200),
+                Reply::Reject => ResponseTemplate::new(422),
+                Reply::Defer => ResponseTemplate::new(429),
+                Reply::Stall => ResponseTemplate::new(200).set_delay(Duration::from_secs(30)),
+            };
+            Mock::given(any())
+                .respond_with(response)
+                .expect(1)
+                .mount(&server)
+                .await;
+            Self(server)
+        }
+
+        fn transport(&self) -> Arc<dyn EmailTransport> {
+            Arc::new(HttpTransport::new(
+                self.0.uri(),
+                Secret::new(Faker.fake()),
+                timeout(),
+            ))
+        }
+
+        async fn received(&self) -> Vec<ReceivedEmail> {
+            let requests = self.0.received_requests().await.unwrap();
+            requests
+                .iter()
+                .map(|request| {
+                    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                    ReceivedEmail {
+                        to: body["To"].as_str().unwrap().to_owned(),
+                        raw: body.to_string(),
+                    }
+                })
+                .collect()
+        }
+    }
+
+    /// A minimal SMTP server that records every message it accepts.
+    struct FakeSmtpServer {
+        port: u16,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+    }
+
+    impl FakeSmtpServer {
+        async fn handle(
+            socket: tokio::net::TcpStream,
+            reply: Reply,
+            received: Arc<Mutex<Vec<ReceivedEmail>>>,
+        ) -> std::io::Result<()> {
+            let (reader, mut writer) = socket.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            writer.write_all(b"220 fake.example.com ESMTP\r\n").await?;
+            let mut to = String::new();
+            while let Some(line) = lines.next_line().await? {
+                let command = line.to_ascii_uppercase();
+                let response = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    "250 fake.example.com"
+                } else if command.starts_with("RCPT TO") {
+                    to = line[line.find('<').unwrap() + 1..line.rfind('>').unwrap()].to_owned();
+                    "250 OK"
+                } else if command.starts_with("DATA") {
+                    match reply {
+                        Reply::Reject => "554 Transaction failed",
+                        Reply::Defer => "451 Try again later",
+                        Reply::Stall => {
+                            tokio::time::sleep(Duration::from_secs(30)).await;
+                            "354 Go ahead"
+                        }
+                        Reply::Accept => {
+                            writer.write_all(b"354 Go ahead\r\n").await?;
+                            let mut raw = String::new();
+                            while let Some(line) = lines.next_line().await? {
+                                if line == "." {
+                                    break;
+                                }
+                                raw.push_str(&line);
+                                raw.push('\n');
+                            }
+                            received.lock().unwrap().push(ReceivedEmail {
+                                to: to.clone(),
+                                raw,
+                            });
+                            "250 OK"
+                        }
+                    }
+                } else if command.starts_with("QUIT") {
+                    writer.write_all(b"221 Bye\r\n").await?;
+                    break;
+                } else {
+                    "250 OK"
+                };
+                writer
+                    .write_all(format!("{}\r\n", response).as_bytes())
+                    .await?;
+            }
+            Ok(())
+        }
+    }
+
+    impl FakeServer for FakeSmtpServer {
+        async fn start(reply: Reply) -> Self {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let received = Arc::new(Mutex::new(Vec::new()));
+            let server_received = received.clone();
+            tokio::spawn(async move {
+                while let Ok((socket, _)) = listener.accept().await {
+                    tokio::spawn(Self::handle(socket, reply, server_received.clone()));
+                }
+            });
+            Self { port, received }
+        }
+
+        fn transport(&self) -> Arc<dyn EmailTransport> {
+            let settings = SmtpSettings {
+                host: "127.0.0.1".into(),
+                port: self.port,
+                username: None,
+                password: None,
+                starttls: false,
+            };
+            Arc::new(SmtpTransport::new(&settings, timeout()).unwrap())
+        }
+
+        async fn received(&self) -> Vec<ReceivedEmail> {
+            std::mem::take(&mut *self.received.lock().unwrap())
+        }
+    }
+
+    #[test]
+    fn the_sender_is_formatted_with_its_display_name() {
+        let cases = [
+            (None, "sender@example.com"),
+            (Some("  "), "sender@example.com"),
+            (
+                Some("Newsletter Team"),
+                "Newsletter Team <sender@example.com>",
+            ),
+            (Some("Team, Inc."), r#""Team, Inc." <sender@example.com>"#),
+            (
+                Some(r#"The "Best" Team"#),
+                r#""The \"Best\" Team" <sender@example.com>"#,
+            ),
+        ];
+        for (name, expected) in cases {
+            let email = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+            let sender = Sender::new(email, name.map(str::to_owned));
+            assert_eq!(sender.to_string(), expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn http_the_email_reaches_the_recipient() {
+        the_email_reaches_the_recipient::<FakeHttpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn http_the_reply_to_and_extra_headers_are_sent() {
+        the_reply_to_and_extra_headers_are_sent::<FakeHttpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn http_sending_fails_permanently_if_the_server_rejects_the_email() {
+        sending_fails_permanently_if_the_server_rejects_the_email::<FakeHttpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn http_sending_fails_transiently_if_the_server_defers_the_email() {
+        sending_fails_transiently_if_the_server_defers_the_email::<FakeHttpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn http_sending_times_out_if_the_server_takes_too_long() {
+        sending_times_out_if_the_server_takes_too_long::<FakeHttpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn smtp_the_email_reaches_the_recipient() {
+        the_email_reaches_the_recipient::<FakeSmtpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn smtp_the_reply_to_and_extra_headers_are_sent() {
+        the_reply_to_and_extra_headers_are_sent::<FakeSmtpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn smtp_sending_fails_permanently_if_the_server_rejects_the_email() {
+        sending_fails_permanently_if_the_server_rejects_the_email::<FakeSmtpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn smtp_sending_fails_transiently_if_the_server_defers_the_email() {
+        sending_fails_transiently_if_the_server_defers_the_email::<FakeSmtpServer>().await;
+    }
+
+    #[tokio::test]
+    async fn smtp_sending_times_out_if_the_server_takes_too_long() {
+        sending_times_out_if_the_server_takes_too_long::<FakeSmtpServer>().await;
+    }
+}
@@ -0,0 +1,89 @@
+use super::{Email, EmailClientError, EmailTransport};
+use crate::configuration::SmtpSettings;
+use anyhow::Context;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+/// Sends emails through an SMTP relay.
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+    timeout: std::time::Duration,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        settings: &SmtpSettings,
+        timeout: std::time::Duration,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let builder = if settings.starttls {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+        };
+        let mut builder = builder.port(settings.port).timeout(Some(timeout));
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(
+                username.to_owned(),
+                password.expose_secret().to_owned(),
+            ));
+        }
+        Ok(Self {
+            mailer: builder.build(),
+            timeout,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send(&self, email: &Email<'_>) -> Result<(), EmailClientError> {
+        let message = build_message(email).map_err(EmailClientError::permanent)?;
+        // The transport's own timeout only covers connecting, so bound the whole exchange.
+        match tokio::time::timeout(self.timeout, self.mailer.send(message)).await {
+            Err(elapsed) => Err(EmailClientError::transient(
+                anyhow::Error::new(elapsed).context("Timed out while sending the email."),
+            )),
+            // `5xx` replies are permanent; `4xx` replies and connection failures are not.
+            Ok(Err(e)) if e.is_permanent() => Err(EmailClientError::permanent(e)),
+            Ok(Err(e)) => Err(EmailClientError::transient(e)),
+            Ok(Ok(_)) => Ok(()),
+        }
+    }
+
+    /// Opens a connection to the relay, authenticating when credentials are configured.
+    async fn check_connection(&self) -> Result<(), anyhow::Error> {
+        let connected = tokio::time::timeout(self.timeout, self.mailer.test_connection())
+            .await
+            .context("Timed out while connecting to the SMTP relay.")?
+            .context("Failed to connect to the SMTP relay.")?;
+        anyhow::ensure!(connected, "The SMTP relay did not accept the connection.");
+        Ok(())
+    }
+}
+
+fn build_message(email: &Email<'_>) -> Result<Message, anyhow::Error> {
+    let mut builder = Message::builder()
+        .from(email.from.to_string().parse()?)
+        .to(email.to.as_ref().parse()?)
+        .subject(email.subject);
+    if let Some(reply_to) = email.reply_to {
+        builder = builder.reply_to(reply_to.as_ref().parse()?);
+    }
+    for &(name, value) in email.headers {
+        let name = HeaderName::new_from_ascii(name.to_owned())
+            .with_context(|| format!("`{}` is not a valid header name.", name))?;
+        builder = builder.raw_header(HeaderValue::new(name, value.to_owned()));
+    }
+    let message = if email.html_body.is_empty() {
+        builder.singlepart(SinglePart::plain(email.text_body.to_owned()))?
+    } else {
+        builder.multipart(MultiPart::alternative_plain_html(
+            email.text_body.to_owned(),
+            email.html_body.to_owned(),
+        ))?
+    };
+    Ok(message)
+}
@@ -0,0 +1,89 @@
+use crate::configuration::EmailTemplateSettings;
+use tera::{Context, Tera};
+
+/// The templates used to render transactional emails.
+///
+/// Templates are loaded from [EmailTemplateSettings::directory] at startup,
+/// so deployments can change the content of their emails without recompiling.
+pub struct EmailTemplates {
+    tera: Tera,
+    confirmation_subject: String,
+    prefer_plain_text: bool,
+}
+
+/// An email ready to be handed to the email client.
+/// `html_body` is empty when the email is sent as plain text only.
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+impl EmailTemplates {
+    pub fn new(settings: &EmailTemplateSettings) -> Result<Self, tera::Error> {
+        let tera = Tera::new(&format!("{}/**/*", settings.directory))?;
+        Ok(Self {
+            tera,
+            confirmation_subject: settings.confirmation_subject.clone(),
+            prefer_plain_text: settings.prefer_plain_text,
+        })
+    }
+
+    /// Renders `confirmation.html` and `confirmation.txt`
+    /// with the confirmation link available as `confirmation_link`.
+    ///
+    /// `confirmation.html` is not rendered when [EmailTemplateSettings::prefer_plain_text] is set.
+    pub fn confirmation(&self, confirmation_link: &str) -> Result<RenderedEmail, tera::Error> {
+        let mut context = Context::new();
+        context.insert("confirmation_link", confirmation_link);
+        let html_body = if self.prefer_plain_text {
+            String::new()
+        } else {
+            self.tera.render("confirmation.html", &context)?
+        };
+        Ok(RenderedEmail {
+            subject: self.confirmation_subject.clone(),
+            html_body,
+            text_body: self.tera.render("confirmation.txt", &context)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn templates(prefer_plain_text: bool) -> EmailTemplates {
+        EmailTemplates::new(&EmailTemplateSettings {
+            directory: "templates/emails".into(),
+            confirmation_subject: "Welcome!".into(),
+            prefer_plain_text,
+        })
+        .unwrap()
+    }
+
+    fn default_templates() -> EmailTemplates {
+        templates(false)
+    }
+
+    #[test]
+    fn the_confirmation_link_is_rendered_in_both_bodies() {
+        let link = "https://example.com/subscriptions/confirm?subscription_token=abc";
+
+        let email = default_templates().confirmation(link).unwrap();
+
+        assert_eq!(email.subject, "Welcome!");
+        assert!(email.html_body.contains(&format!("href=\"{}\"", link)));
+        assert!(email.text_body.contains(link));
+    }
+
+    #[test]
+    fn plain_text_confirmations_have_no_html_body() {
+        let link = "https://example.com/subscriptions/confirm?subscription_token=abc";
+
+        let email = templates(true).confirmation(link).unwrap();
+
+        assert!(email.html_body.is_empty());
+        assert!(email.text_body.contains(link));
+    }
+}
@@ -0,0 +1,66 @@
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::dev::ResponseHead;
+use actix_web::http::header::{HeaderValue, SET_COOKIE};
+use actix_web::HttpRequest;
+use actix_web_flash_messages::storage::{
+    CookieMessageStore, FlashMessageStore, LoadError, StoreError,
+};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+
+/// The name of the cookie holding the flash messages.
+const FLASH_COOKIE_NAME: &str = "_flash";
+
+/// A [CookieMessageStore] whose cookie attributes follow the application settings.
+///
+/// `CookieMessageStore` always marks its cookie as `Secure` and `SameSite=Lax`,
+/// so the attributes are rewritten on the way out.
+pub struct FlashCookieStore {
+    inner: CookieMessageStore,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl FlashCookieStore {
+    pub fn new(inner: CookieMessageStore, secure: bool, same_site: SameSite) -> Self {
+        Self {
+            inner,
+            secure,
+            same_site,
+        }
+    }
+
+    fn apply_attributes(&self, response: &mut ResponseHead) -> Result<(), anyhow::Error> {
+        let cookies: Vec<HeaderValue> = response.headers().get_all(SET_COOKIE).cloned().collect();
+        response.headers_mut().remove(SET_COOKIE);
+        for value in cookies {
+            let header = value.to_str().context("Invalid Set-Cookie header.")?;
+            let mut cookie = Cookie::parse(header).context("Invalid Set-Cookie header.")?;
+            if cookie.name() == FLASH_COOKIE_NAME {
+                cookie.set_secure(self.secure);
+                cookie.set_same_site(self.same_site);
+                cookie.set_http_only(true);
+            }
+            let value = HeaderValue::from_str(&cookie.to_string())?;
+            response.headers_mut().append(SET_COOKIE, value);
+        }
+        Ok(())
+    }
+}
+
+impl FlashMessageStore for FlashCookieStore {
+    fn load(&self, request: &HttpRequest) -> Result<Vec<FlashMessage>, LoadError> {
+        self.inner.load(request)
+    }
+
+    fn store(
+        &self,
+        messages: &[FlashMessage],
+        request: HttpRequest,
+        response: &mut ResponseHead,
+    ) -> Result<(), StoreError> {
+        self.inner.store(messages, request, response)?;
+        self.apply_attributes(response)
+            .map_err(StoreError::GenericError)
+    }
+}
@@ -0,0 +1,121 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::Method;
+use actix_web::{web, HttpResponse};
+use actix_web_lab::middleware::Next;
+
+/// How long browsers should only reach the application over HTTPS, sent as `Strict-Transport-Security`.
+pub struct HstsPolicy {
+    pub max_age_seconds: u64,
+}
+
+impl HstsPolicy {
+    fn header_value(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!(
+            "max-age={}; includeSubDomains",
+            self.max_age_seconds
+        ))
+        .expect("A max-age directive is always a valid header value")
+    }
+}
+
+/// Whether the request reached the TLS-terminating proxy over plain HTTP.
+///
+/// The scheme is taken from `Forwarded` or `X-Forwarded-Proto`, as set by the proxy.
+fn is_insecure(req: &ServiceRequest) -> bool {
+    req.connection_info().scheme() == "http"
+}
+
+/// The health checks are probed over plain HTTP by the load balancer and are never redirected.
+fn is_exempt(req: &ServiceRequest) -> bool {
+    let path = req.path().trim_end_matches('/');
+    path.ends_with("/health_check") || path.ends_with("/health_check/details")
+}
+
+/// The same URL as the request, over HTTPS.
+fn https_location(req: &ServiceRequest) -> String {
+    let path_and_query = req
+        .uri()
+        .path_and_query()
+        .map_or("/", |path_and_query| path_and_query.as_str());
+    format!("https://{}{}", req.connection_info().host(), path_and_query)
+}
+
+/// Redirects requests made over plain HTTP to HTTPS and adds `Strict-Transport-Security`
+/// to every response.
+///
+/// Browser navigations (`GET` and `HEAD`) are redirected with `301 Moved Permanently`.
+/// Other requests are rejected with `400 Bad Request`, since their body has already been sent
+/// in the clear and should not be replayed silently.
+pub async fn enforce_https(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let hsts = req
+        .app_data::<web::Data<HstsPolicy>>()
+        .map(|policy| policy.header_value());
+
+    let mut response = if is_insecure(&req) && !is_exempt(&req) {
+        let response = match *req.method() {
+            Method::GET | Method::HEAD => HttpResponse::MovedPermanently()
+                .insert_header((header::LOCATION, https_location(&req)))
+                .finish(),
+            _ => HttpResponse::BadRequest().body("HTTPS is required."),
+        };
+        req.into_response(response).map_into_right_body()
+    } else {
+        next.call(req).await?.map_into_left_body()
+    };
+
+    if let Some(hsts) = hsts {
+        response
+            .headers_mut()
+            .insert(header::STRICT_TRANSPORT_SECURITY, hsts);
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn the_forwarded_scheme_decides_whether_a_request_is_insecure() {
+        let http = TestRequest::get()
+            .insert_header(("X-Forwarded-Proto", "http"))
+            .to_srv_request();
+        let https = TestRequest::get()
+            .insert_header(("X-Forwarded-Proto", "https"))
+            .to_srv_request();
+        assert!(is_insecure(&http));
+        assert!(!is_insecure(&https));
+    }
+
+    #[test]
+    fn the_redirect_keeps_the_host_path_and_query() {
+        let req = TestRequest::get()
+            .uri("/subscriptions/confirm?subscription_token=abc")
+            .insert_header(("Host", "example.com"))
+            .to_srv_request();
+        assert_eq!(
+            https_location(&req),
+            "https://example.com/subscriptions/confirm?subscription_token=abc"
+        );
+    }
+
+    #[test]
+    fn health_checks_are_exempt() {
+        for uri in [
+            "/health_check",
+            "/newsletter/health_check",
+            "/health_check/details",
+        ] {
+            let req = TestRequest::get().uri(uri).to_srv_request();
+            assert!(is_exempt(&req), "{}", uri);
+        }
+        let req = TestRequest::get().uri("/login").to_srv_request();
+        assert!(!is_exempt(&req));
+    }
+}
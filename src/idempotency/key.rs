@@ -1,17 +1,21 @@
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, Debug)]
 pub struct IdempotencyKey(String);
 
-impl TryFrom<String> for IdempotencyKey {
-    type Error = anyhow::Error;
-
-    fn try_from(s: String) -> Result<Self, Self::Error> {
+impl IdempotencyKey {
+    /// Parses a key of at most `max_length` characters.
+    ///
+    /// Only ASCII letters, digits and dashes are accepted, which covers UUIDs,
+    /// so that a key never carries control characters into the logs.
+    pub fn parse(s: String, max_length: usize) -> Result<Self, anyhow::Error> {
         if s.is_empty() {
             anyhow::bail!("Idempotency key cannot be empty.");
         }
-        let max_length = 50;
         if s.len() > max_length {
             anyhow::bail!("Idempotency key must be shorter than {max_length} characters.");
         }
+        if !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            anyhow::bail!("Idempotency key can only contain letters, digits and dashes.");
+        }
         Ok(Self(s))
     }
 }
@@ -27,3 +31,49 @@ impl AsRef<str> for IdempotencyKey {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::IdempotencyKey;
+    use claim::{assert_err, assert_ok};
+
+    const MAX_LENGTH: usize = 50;
+
+    #[test]
+    fn an_empty_key_is_rejected() {
+        assert_err!(IdempotencyKey::parse("".into(), MAX_LENGTH));
+    }
+
+    #[test]
+    fn a_key_longer_than_the_max_length_is_rejected() {
+        assert_err!(IdempotencyKey::parse(
+            "a".repeat(MAX_LENGTH + 1),
+            MAX_LENGTH
+        ));
+    }
+
+    #[test]
+    fn a_key_of_the_max_length_is_accepted() {
+        assert_ok!(IdempotencyKey::parse("a".repeat(MAX_LENGTH), MAX_LENGTH));
+    }
+
+    #[test]
+    fn a_key_with_a_newline_is_rejected() {
+        assert_err!(IdempotencyKey::parse(
+            "abc\nINFO forged log line".into(),
+            MAX_LENGTH
+        ));
+    }
+
+    #[test]
+    fn a_key_with_other_punctuation_is_rejected() {
+        assert_err!(IdempotencyKey::parse("abc def".into(), MAX_LENGTH));
+        assert_err!(IdempotencyKey::parse("abc/def".into(), MAX_LENGTH));
+    }
+
+    #[test]
+    fn a_uuid_is_accepted() {
+        let key = uuid::Uuid::new_v4().to_string();
+        assert_ok!(IdempotencyKey::parse(key, MAX_LENGTH));
+    }
+}
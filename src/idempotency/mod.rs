@@ -2,4 +2,7 @@ mod key;
 mod persistence;
 
 pub use key::IdempotencyKey;
-pub use persistence::{get_saved_response, save_response, try_processing, NextAction};
+pub use persistence::{
+    get_saved_response, request_fingerprint, save_response, try_processing, NextAction,
+    TryProcessingError,
+};
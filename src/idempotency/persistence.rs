@@ -1,10 +1,13 @@
 use crate::authentication::UserId;
 use crate::idempotency::IdempotencyKey;
+use crate::utils::error_chain_fmt;
 use actix_web::body::to_bytes;
 use actix_web::http::StatusCode;
-use actix_web::HttpResponse;
+use actix_web::{HttpResponse, ResponseError};
+use sha2::{Digest, Sha256};
 use sqlx::postgres::{PgHasArrayType, PgTypeInfo};
 use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::fmt::{Debug, Formatter};
 
 #[derive(Debug, sqlx::Type)]
 #[sqlx(type_name = "header_pair")]
@@ -19,31 +22,84 @@ impl PgHasArrayType for HeaderPairRecord {
     }
 }
 
+#[allow(clippy::large_enum_variant)]
 pub enum NextAction {
     StartProcessing(Transaction<'static, Postgres>),
     ReturnSavedResponse(HttpResponse),
 }
 
+/// The error type for [try_processing].
+#[derive(thiserror::Error)]
+pub enum TryProcessingError {
+    /// The key has already been used for a request with a different payload.
+    #[error("The idempotency key has already been used with a different request payload.")]
+    PayloadMismatch,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for TryProcessingError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TryProcessingError::PayloadMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            TryProcessingError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Debug for TryProcessingError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Hashes the fields of a request into a fingerprint to be passed to [try_processing].
+///
+/// Every field is prefixed by its length, so that moving text from one field
+/// to the next one yields a different fingerprint.
+pub fn request_fingerprint(fields: &[&str]) -> String {
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update((field.len() as u64).to_be_bytes());
+        hasher.update(field.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// Claims the idempotency key for a new request, or returns the response saved for it.
+///
+/// A key presented again with a different `request_fingerprint` is rejected
+/// with [TryProcessingError::PayloadMismatch] instead of replaying the saved response.
 pub async fn try_processing(
     pool: &PgPool,
     idempotency_key: &IdempotencyKey,
     user_id: &UserId,
-) -> Result<NextAction, anyhow::Error> {
-    let mut tx = pool.begin().await?;
+    request_fingerprint: &str,
+) -> Result<NextAction, TryProcessingError> {
+    let mut tx = pool.begin().await.map_err(anyhow::Error::from)?;
     let query = sqlx::query!(
         r#"
-        INSERT INTO idempotency (user_id, idempotency_key, created_at)
-        VALUES ($1, $2, now())
+        INSERT INTO idempotency (user_id, idempotency_key, request_fingerprint, created_at)
+        VALUES ($1, $2, $3, now())
         ON CONFLICT DO NOTHING
         "#,
         **user_id,
         idempotency_key.as_ref(),
+        request_fingerprint,
     );
-    let n_inserted_rows = tx.execute(query).await?.rows_affected();
+    let n_inserted_rows = tx
+        .execute(query)
+        .await
+        .map_err(anyhow::Error::from)?
+        .rows_affected();
 
     if n_inserted_rows > 0 {
         Ok(NextAction::StartProcessing(tx))
     } else {
+        let saved_fingerprint = get_saved_fingerprint(pool, idempotency_key, user_id).await?;
+        if saved_fingerprint.is_some_and(|saved| saved != request_fingerprint) {
+            return Err(TryProcessingError::PayloadMismatch);
+        }
         let saved_response = get_saved_response(pool, idempotency_key, user_id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("Expected a saved response, but didn't find it."))?;
@@ -51,6 +107,26 @@ pub async fn try_processing(
     }
 }
 
+/// Returns `None` for rows saved before fingerprints were recorded.
+async fn get_saved_fingerprint(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: &UserId,
+) -> Result<Option<String>, anyhow::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT request_fingerprint
+        FROM idempotency
+        WHERE user_id = $1 AND idempotency_key = $2
+        "#,
+        **user_id,
+        idempotency_key.as_ref(),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(record.and_then(|r| r.request_fingerprint))
+}
+
 pub async fn get_saved_response(
     pool: &PgPool,
     idempotency_key: &IdempotencyKey,
@@ -127,3 +203,32 @@ pub async fn save_response(
     let http_response = response_head.set_body(body).map_into_boxed_body();
     Ok(http_response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::request_fingerprint;
+
+    #[test]
+    fn the_same_fields_yield_the_same_fingerprint() {
+        assert_eq!(
+            request_fingerprint(&["title", "body"]),
+            request_fingerprint(&["title", "body"])
+        );
+    }
+
+    #[test]
+    fn a_changed_field_yields_a_different_fingerprint() {
+        assert_ne!(
+            request_fingerprint(&["title", "body"]),
+            request_fingerprint(&["another title", "body"])
+        );
+    }
+
+    #[test]
+    fn moving_text_across_fields_yields_a_different_fingerprint() {
+        assert_ne!(
+            request_fingerprint(&["ab", "c"]),
+            request_fingerprint(&["a", "bc"])
+        );
+    }
+}
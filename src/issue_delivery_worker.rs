@@ -1,25 +1,99 @@
-use crate::configuration::Settings;
-use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
-use sqlx::postgres::PgPoolOptions;
+use crate::configuration::{Settings, WorkerSettings};
+use crate::domain::{
+    ContentFormat, DeliveryFrequency, DeliveryPriority, IssueLimits, NewsletterIssue,
+    SubscriberEmail, SubscriberLocale, SubscriptionStatus,
+};
+use crate::email_client::{EmailClient, EmailClientError};
+use crate::routes::UnsubscribeLinks;
+use crate::telemetry::timed_query;
+use crate::utils::error_chain_fmt;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use sqlx::{Executor, PgPool, Postgres, Row, Transaction};
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::task::JoinSet;
 use tracing::field::display;
-use tracing::Span;
+use tracing::{Instrument, Span};
 use uuid::Uuid;
 
-pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
-    let connection_pool = PgPoolOptions::new()
-        .acquire_timeout(Duration::from_secs(2))
-        .connect_lazy_with(configuration.database.with_db());
-    let email_client = configuration.email_client.client();
+/// Runs [WorkerSettings::concurrency] delivery loops side by side, restarting any of them
+/// that exits or panics.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    state: WorkerState,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = configuration.database.connection_pool();
+    let email_client = configuration.email_client.newsletter_client();
+    let unsubscribe_links = UnsubscribeLinks::new(
+        configuration.application.public_url(),
+        configuration.application.hmac_secret,
+    );
+    let settings = configuration.worker;
+    // The throttle is shared, so that the rate limit applies to the workers as a whole.
+    let throttle = Arc::new(DeliveryThrottle::new(&settings));
 
-    worker_loop(connection_pool, email_client).await
+    let mut workers = JoinSet::new();
+    let mut worker_ids = HashMap::new();
+    let spawn_worker = |workers: &mut JoinSet<_>, worker_id: usize| {
+        let worker = worker_loop(
+            connection_pool.clone(),
+            email_client.clone(),
+            settings.clone(),
+            unsubscribe_links.clone(),
+            state.clone(),
+            throttle.clone(),
+        )
+        .instrument(tracing::info_span!("Delivery worker", worker_id));
+        workers.spawn(worker).id()
+    };
+    for worker_id in 0..settings.concurrency.max(1) {
+        worker_ids.insert(spawn_worker(&mut workers, worker_id), worker_id);
+    }
+
+    // The loops never stop on their own, so any of them exiting is restarted.
+    while let Some(exit) = workers.join_next_with_id().await {
+        let task_id = match exit {
+            Ok((task_id, result)) => {
+                let error = result.err().map(|e| format!("{:#}", e));
+                tracing::error!(error, "A delivery worker has exited. Restarting it.");
+                task_id
+            }
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "A delivery worker has panicked. Restarting it."
+                );
+                e.id()
+            }
+        };
+        if let Some(worker_id) = worker_ids.remove(&task_id) {
+            worker_ids.insert(spawn_worker(&mut workers, worker_id), worker_id);
+        }
+    }
+    Ok(())
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyhow::Error> {
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    settings: WorkerSettings,
+    unsubscribe_links: UnsubscribeLinks,
+    state: WorkerState,
+    throttle: Arc<DeliveryThrottle>,
+) -> Result<(), anyhow::Error> {
+    let mut stats = WorkerStats::default();
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        throttle.until_ready().await;
+        let outcome = try_execute_task(&pool, &email_client, &settings, &unsubscribe_links).await;
+        state.record(&outcome);
+        stats.record(&outcome, settings.stats_log_interval);
+        match outcome {
             Ok(ExecutionOutcome::TaskCompleted) => {}
             Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(10)).await,
             Err(_) => tokio::time::sleep(Duration::from_secs(1)).await,
@@ -27,11 +101,199 @@ async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), anyh
     }
 }
 
+/// Processes the queue until it is empty, with [WorkerSettings::concurrency] loops
+/// running side by side. Returns the number of tasks processed.
+///
+/// Every task is reported to `state`. Tasks are locked with `FOR UPDATE SKIP LOCKED`,
+/// so no two loops ever work on the same delivery. A loop stops at its first error,
+/// while the others are left to finish, and the first error is returned.
+pub async fn drain_queue(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    settings: &WorkerSettings,
+    unsubscribe_links: &UnsubscribeLinks,
+    state: &WorkerState,
+) -> Result<u64, anyhow::Error> {
+    let throttle = Arc::new(DeliveryThrottle::new(settings));
+    let mut loops = JoinSet::new();
+    for _ in 0..settings.concurrency.max(1) {
+        let (pool, email_client, settings, unsubscribe_links, state, throttle) = (
+            pool.clone(),
+            email_client.clone(),
+            settings.clone(),
+            unsubscribe_links.clone(),
+            state.clone(),
+            throttle.clone(),
+        );
+        loops.spawn(
+            async move {
+                let mut processed = 0;
+                loop {
+                    throttle.until_ready().await;
+                    let outcome =
+                        try_execute_task(&pool, &email_client, &settings, &unsubscribe_links).await;
+                    state.record(&outcome);
+                    match outcome? {
+                        ExecutionOutcome::TaskCompleted => processed += 1,
+                        ExecutionOutcome::EmptyQueue => return Ok::<_, anyhow::Error>(processed),
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+    }
+
+    let mut processed = 0;
+    let mut first_error = None;
+    while let Some(exit) = loops.join_next().await {
+        match exit.context("A delivery loop has panicked.") {
+            Ok(Ok(count)) => processed += count,
+            Ok(Err(e)) | Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(processed),
+    }
+}
+
+/// Paces deliveries to stay under the email provider's sending limit.
+///
+/// Bursts are not allowed: tasks are spread evenly, one every `1 / max_emails_per_second`
+/// seconds, so a full queue never goes out faster than the configured rate.
+pub struct DeliveryThrottle(Option<DefaultDirectRateLimiter>);
+
+impl DeliveryThrottle {
+    /// No throttling is applied when `max_emails_per_second` is `0`.
+    pub fn new(settings: &WorkerSettings) -> Self {
+        let limiter = NonZeroU32::new(settings.max_emails_per_second).map(|rate| {
+            let quota = Quota::per_second(rate).allow_burst(NonZeroU32::MIN);
+            RateLimiter::direct(quota)
+        });
+        Self(limiter)
+    }
+
+    /// Waits until the next task is allowed to run.
+    pub async fn until_ready(&self) {
+        if let Some(limiter) = &self.0 {
+            limiter.until_ready().await;
+        }
+    }
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
 }
 
+/// Why a delivery task could not be executed.
+#[derive(thiserror::Error)]
+pub enum WorkerError {
+    /// The database or the email provider failed. The task is retried later.
+    #[error(transparent)]
+    Transient(#[from] anyhow::Error),
+    /// The task itself is broken, e.g. its issue cannot be loaded,
+    /// so retrying it would fail forever. The task is dead-lettered right away.
+    #[error("{0}")]
+    Fatal(#[source] anyhow::Error),
+}
+
+impl Debug for WorkerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Whether the worker is currently draining the queue or waiting for new tasks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkerActivity {
+    Idle,
+    Working,
+}
+
+/// A snapshot of the worker's state, as exposed by the worker status endpoint.
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct WorkerStatus {
+    pub activity: WorkerActivity,
+    pub last_task_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub tasks_processed: u64,
+}
+
+impl Default for WorkerStatus {
+    fn default() -> Self {
+        Self {
+            activity: WorkerActivity::Idle,
+            last_task_at: None,
+            last_error: None,
+            tasks_processed: 0,
+        }
+    }
+}
+
+/// State shared between the worker and the API, updated after every attempt to execute a task.
+#[derive(Clone, Default)]
+pub struct WorkerState(Arc<Mutex<WorkerStatus>>);
+
+impl WorkerState {
+    pub fn status(&self) -> WorkerStatus {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn record(&self, outcome: &Result<ExecutionOutcome, anyhow::Error>) {
+        let mut status = self.0.lock().unwrap();
+        match outcome {
+            Ok(ExecutionOutcome::TaskCompleted) => {
+                status.activity = WorkerActivity::Working;
+                status.last_task_at = Some(Utc::now());
+                status.tasks_processed += 1;
+            }
+            Ok(ExecutionOutcome::EmptyQueue) => status.activity = WorkerActivity::Idle,
+            Err(e) => {
+                status.activity = WorkerActivity::Idle;
+                status.last_error = Some(format!("{:#}", e));
+            }
+        }
+    }
+}
+
+/// Counters accumulated by the worker loop and logged every `stats_log_interval` iterations,
+/// so that a stuck queue shows up as a growing number of errors or empty polls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub iterations: u64,
+    pub tasks_completed: u64,
+    pub empty_polls: u64,
+    pub errors: u64,
+}
+
+impl WorkerStats {
+    pub fn record(&mut self, outcome: &Result<ExecutionOutcome, anyhow::Error>, log_interval: u64) {
+        self.iterations += 1;
+        match outcome {
+            Ok(ExecutionOutcome::TaskCompleted) => self.tasks_completed += 1,
+            Ok(ExecutionOutcome::EmptyQueue) => self.empty_polls += 1,
+            Err(_) => self.errors += 1,
+        }
+        if log_interval > 0 && self.iterations.is_multiple_of(log_interval) {
+            self.log();
+        }
+    }
+
+    fn log(&self) {
+        tracing::info!(
+            iterations = self.iterations,
+            tasks_completed = self.tasks_completed,
+            empty_polls = self.empty_polls,
+            errors = self.errors,
+            "Worker statistics."
+        );
+    }
+}
+
 #[tracing::instrument(
     skip_all,
     fields(
@@ -42,14 +304,96 @@ pub enum ExecutionOutcome {
 pub async fn try_execute_task(
     pool: &PgPool,
     email_client: &EmailClient,
+    settings: &WorkerSettings,
+    unsubscribe_links: &UnsubscribeLinks,
 ) -> Result<ExecutionOutcome, anyhow::Error> {
+    if let ExecutionOutcome::TaskCompleted = try_fan_out_issue(pool).await? {
+        return Ok(ExecutionOutcome::TaskCompleted);
+    }
     match dequeue_task(pool).await? {
-        Some((mut tx, issue_id, email)) => {
+        Some((mut tx, issue_id, email, n_retries)) => {
             Span::current()
-                .record("issue_id", &display(&issue_id))
-                .record("email", &display(&email));
-            send_newsletter_issue(pool, email_client, issue_id, &email).await?;
-            delete_task(&mut tx, issue_id, &email).await?;
+                .record("issue_id", display(&issue_id))
+                .record("email", display(&email));
+            if is_cancelled(&mut tx, issue_id).await? {
+                tracing::info!("The issue has been cancelled. Dropping the task.");
+                delete_task(&mut tx, issue_id, &email).await?;
+                tx.commit().await?;
+                return Ok(ExecutionOutcome::TaskCompleted);
+            }
+            let outcome = match send_newsletter_issue(
+                pool,
+                email_client,
+                settings,
+                unsubscribe_links,
+                issue_id,
+                &email,
+            )
+            .await
+            {
+                Ok(outcome) => outcome,
+                Err(WorkerError::Fatal(e)) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "The delivery task cannot succeed. Dead-lettering it."
+                    );
+                    store_receipt(pool, issue_id, &email, ReceiptStatus::Failed).await?;
+                    DeliveryOutcome::Rejected {
+                        n_retries,
+                        reason: format!("{:#}", e),
+                    }
+                }
+                Err(WorkerError::Transient(e))
+                    if settings.max_retries > 0 && n_retries + 1 >= settings.max_retries =>
+                {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "The delivery task has run out of retries. Dead-lettering it."
+                    );
+                    store_receipt(pool, issue_id, &email, ReceiptStatus::Failed).await?;
+                    DeliveryOutcome::Rejected {
+                        n_retries: n_retries + 1,
+                        reason: format!("{:#}", e),
+                    }
+                }
+                Err(WorkerError::Transient(e)) => {
+                    let execute_after = next_attempt_at(settings, n_retries);
+                    record_failed_attempt(&mut tx, issue_id, &email, execute_after).await?;
+                    tx.commit().await?;
+                    return Err(e);
+                }
+            };
+            let outcome = match outcome {
+                DeliveryOutcome::Deferred { .. }
+                    if settings.max_retries > 0 && n_retries + 1 >= settings.max_retries =>
+                {
+                    tracing::error!(
+                        "The email provider keeps deferring the delivery task. Dead-lettering it."
+                    );
+                    store_receipt(pool, issue_id, &email, ReceiptStatus::Failed).await?;
+                    DeliveryOutcome::Rejected {
+                        n_retries: n_retries + 1,
+                        reason: "The email provider deferred every attempt.".to_owned(),
+                    }
+                }
+                outcome => outcome,
+            };
+            match &outcome {
+                DeliveryOutcome::Throttled { retry_at } => {
+                    postpone_task(&mut tx, issue_id, &email, *retry_at).await?
+                }
+                DeliveryOutcome::Deferred { retry_at } => {
+                    record_failed_attempt(&mut tx, issue_id, &email, *retry_at).await?
+                }
+                DeliveryOutcome::Rejected { n_retries, reason } => {
+                    dead_letter_task(&mut tx, issue_id, &email, *n_retries, reason).await?;
+                    delete_task(&mut tx, issue_id, &email).await?
+                }
+                _ => delete_task(&mut tx, issue_id, &email).await?,
+            }
+            record_delivery(&mut tx, issue_id, &outcome, settings).await?;
             tx.commit().await?;
             Ok(ExecutionOutcome::TaskCompleted)
         }
@@ -57,61 +401,443 @@ pub async fn try_execute_task(
     }
 }
 
+/// Enqueues a delivery task for every recipient of a newly published issue.
+///
+/// Publishing only schedules the fan-out, so that the request does not get slower
+/// as the number of subscribers grows. An issue without any recipient is completed right away.
+#[tracing::instrument(skip_all, fields(newsletter_issue_id = tracing::field::Empty))]
+pub async fn try_fan_out_issue(pool: &PgPool) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+    let Some(task) = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, segment, priority AS "priority: DeliveryPriority"
+        FROM issue_fanout_queue
+        FOR UPDATE SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    Span::current().record("newsletter_issue_id", display(&task.newsletter_issue_id));
+
+    let enqueued = enqueue_delivery_tasks(
+        &mut tx,
+        task.newsletter_issue_id,
+        task.segment,
+        task.priority,
+    )
+    .await?;
+    if enqueued == 0 {
+        let query = sqlx::query!(
+            "UPDATE newsletter_issues SET status = $2 WHERE newsletter_issue_id = $1",
+            task.newsletter_issue_id,
+            COMPLETED
+        );
+        tx.execute(query).await?;
+    }
+    let query = sqlx::query!(
+        "DELETE FROM issue_fanout_queue WHERE newsletter_issue_id = $1",
+        task.newsletter_issue_id
+    );
+    tx.execute(query).await?;
+    tx.commit().await?;
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// Enqueues the issue for every confirmed subscriber, restricted to the segment if there is one.
+///
+/// The recipients are selected and inserted by a single `INSERT ... SELECT`, so the subscriber
+/// list never leaves Postgres and memory use does not grow with the number of subscribers.
+#[tracing::instrument(skip_all)]
+async fn enqueue_delivery_tasks(
+    tx: &mut PgTransaction,
+    issue_id: Uuid,
+    segment: Option<String>,
+    priority: DeliveryPriority,
+) -> Result<u64, anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email,
+            priority
+        )
+        SELECT $1, email, $4
+        FROM subscriptions s
+        WHERE status = $3
+          AND (
+            $2::text IS NULL
+            OR EXISTS (
+                SELECT 1 FROM subscriber_tags t
+                WHERE t.subscriber_id = s.id AND t.tag = $2
+            )
+          )
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO NOTHING
+        "#,
+        issue_id,
+        segment,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+        priority as DeliveryPriority,
+    );
+    let result = timed_query("enqueue_delivery_tasks", tx.execute(query)).await?;
+    Ok(result.rows_affected())
+}
+
+/// What happened to the recipient of a delivery task.
+enum DeliveryOutcome {
+    Delivered,
+    /// The stored email address is invalid, so the task was dropped without sending.
+    /// A `failed` receipt is recorded, so that the delivery can be retried once it is fixed.
+    Skipped,
+    /// The issue has already been delivered to this address, so it is not sent again.
+    AlreadyDelivered,
+    /// The worker runs with [WorkerSettings::dry_run], so nothing was sent.
+    /// A `dry_run` receipt is recorded, and the task is dropped without counting as delivered.
+    DryRun,
+    /// The recipient is no longer a confirmed subscriber, e.g. they unsubscribed after
+    /// the issue was published. A `skipped` receipt is recorded, and the task is dropped
+    /// without counting against the issue.
+    NotConfirmed,
+    /// The subscriber has reached [WorkerSettings::max_emails_per_week].
+    /// A `throttled` receipt is recorded, and the task is postponed until `retry_at`.
+    Throttled {
+        retry_at: DateTime<Utc>,
+    },
+    /// The email provider asked to try again after a delay, e.g. when rate limiting.
+    /// No receipt is recorded, and the task is postponed until `retry_at`.
+    /// Deferrals count towards [WorkerSettings::max_retries].
+    Deferred {
+        retry_at: DateTime<Utc>,
+    },
+    /// The email provider rejected the email permanently, the task is broken (see
+    /// [WorkerError::Fatal]), or it has failed [WorkerSettings::max_retries] times,
+    /// so the task is dead-lettered:
+    /// it is moved to the `dead_letter` table with a `failed` receipt instead of being retried.
+    Rejected {
+        n_retries: i32,
+        reason: String,
+    },
+}
+
 async fn send_newsletter_issue(
     pool: &PgPool,
     email_client: &EmailClient,
+    settings: &WorkerSettings,
+    unsubscribe_links: &UnsubscribeLinks,
     issue_id: Uuid,
     email: &str,
-) -> Result<(), anyhow::Error> {
+) -> Result<DeliveryOutcome, WorkerError> {
+    if is_delivered(pool, issue_id, email).await? {
+        tracing::warn!("The issue has already been delivered to this address. Skipping.");
+        return Ok(DeliveryOutcome::AlreadyDelivered);
+    }
+    let Some(preferences) = get_delivery_preferences(pool, email)
+        .await?
+        .filter(|preferences| preferences.status == SubscriptionStatus::Confirmed)
+    else {
+        tracing::info!("The recipient is no longer a confirmed subscriber. Skipping.");
+        store_receipt(pool, issue_id, email, ReceiptStatus::Skipped).await?;
+        return Ok(DeliveryOutcome::NotConfirmed);
+    };
+    let max_emails_per_week = preferences.max_emails_per_week(settings.max_emails_per_week);
+    if let Some(retry_at) = frequency_cap_reached(pool, email, max_emails_per_week).await? {
+        tracing::info!(%retry_at, "The subscriber has reached the weekly cap. Postponing.");
+        store_receipt(pool, issue_id, email, ReceiptStatus::Throttled).await?;
+        return Ok(DeliveryOutcome::Throttled { retry_at });
+    }
     match SubscriberEmail::parse(email.to_owned()) {
         Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
+            let issue = get_issue(pool, issue_id, preferences.locale()).await?;
+            if settings.dry_run {
+                tracing::info!(
+                    recipient = %email,
+                    title = %issue.title(),
+                    "Dry run: skipping the delivery of a newsletter issue."
+                );
+                store_receipt(pool, issue_id, email.as_ref(), ReceiptStatus::DryRun).await?;
+                return Ok(DeliveryOutcome::DryRun);
+            }
+            let unsubscribe_link = format!("<{}>", unsubscribe_links.link(email.as_ref())?);
+            let headers = [
+                ("List-Unsubscribe", unsubscribe_link.as_str()),
+                ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+            ];
             match email_client
-                .send_email(
+                .send_email_with_headers(
                     &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
+                    issue.title(),
+                    preferences.html_content(&issue),
+                    issue.text_content(),
+                    &headers,
                 )
                 .await
             {
+                Err(EmailClientError::Permanent(e)) => {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "The email provider rejected the issue. Dead-lettering the task."
+                    );
+                    store_receipt(pool, issue_id, email.as_ref(), ReceiptStatus::Failed).await?;
+                    Ok(DeliveryOutcome::Rejected {
+                        n_retries: 0,
+                        reason: format!("{:#}", e),
+                    })
+                }
+                Err(EmailClientError::Transient {
+                    retry_after: Some(retry_after),
+                    source: e,
+                }) => {
+                    let Some(retry_at) = chrono::Duration::from_std(retry_after)
+                        .ok()
+                        .and_then(|delay| Utc::now().checked_add_signed(delay))
+                    else {
+                        return Err(WorkerError::Transient(e));
+                    };
+                    tracing::warn!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        %retry_at,
+                        "The email provider deferred the issue. Postponing."
+                    );
+                    Ok(DeliveryOutcome::Deferred { retry_at })
+                }
                 Err(e) => {
-                    let message = "Failed to deliver issue to a confirmed subscriber. Skipping.";
+                    let message = "Failed to deliver issue to a confirmed subscriber. \
+                        Retrying later, or dead-lettering the task once it runs out of retries.";
                     tracing::error!(error.cause_chain = ?e,error.message = %e,message);
-                    Err(e.into())
+                    Err(WorkerError::Transient(e.into()))
+                }
+                Ok(_) => {
+                    store_receipt(pool, issue_id, email.as_ref(), ReceiptStatus::Delivered).await?;
+                    record_last_emailed_at(pool, email.as_ref()).await?;
+                    Ok(DeliveryOutcome::Delivered)
                 }
-                Ok(_) => Ok(()),
             }
         }
         Err(e) => {
             let message = "A confirmed subscriber's stored contact details are invalid. Skipping.";
             tracing::error!(error.cause_chain = ?e,error.message = %e,message);
-            Err(e.into())
+            store_receipt(pool, issue_id, email, ReceiptStatus::Failed).await?;
+            Ok(DeliveryOutcome::Skipped)
+        }
+    }
+}
+
+/// How a subscriber wants to receive issues, as set in the preference center.
+struct DeliveryPreferences {
+    status: SubscriptionStatus,
+    content_format: String,
+    frequency: String,
+    locale: Option<String>,
+}
+
+impl DeliveryPreferences {
+    /// Weekly subscribers receive at most one issue per week, whatever the global cap.
+    fn max_emails_per_week(&self, global_cap: i64) -> i64 {
+        if self.frequency != DeliveryFrequency::Weekly.as_str() {
+            global_cap
+        } else if global_cap <= 0 {
+            1
+        } else {
+            global_cap.min(1)
         }
     }
+
+    /// Plain-text subscribers get an email without an HTML part.
+    fn html_content<'a>(&self, issue: &'a NewsletterIssue) -> &'a str {
+        if self.content_format == ContentFormat::Text.as_str() {
+            ""
+        } else {
+            issue.html_content()
+        }
+    }
+
+    /// The locale to pick a translation of the issue with. An invalid stored locale
+    /// is ignored, so the subscriber receives the default content.
+    fn locale(&self) -> Option<SubscriberLocale> {
+        self.locale
+            .clone()
+            .and_then(|locale| SubscriberLocale::parse(locale).ok())
+    }
+}
+
+/// Returns `None` if the address no longer belongs to a subscriber.
+#[tracing::instrument(skip(pool, email))]
+async fn get_delivery_preferences(
+    pool: &PgPool,
+    email: &str,
+) -> Result<Option<DeliveryPreferences>, anyhow::Error> {
+    let preferences = sqlx::query_as!(
+        DeliveryPreferences,
+        r#"
+        SELECT status AS "status: SubscriptionStatus", content_format, frequency, locale
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(preferences)
+}
+
+/// The last outcome of delivering an issue to an email address.
+#[derive(Clone, Copy)]
+enum ReceiptStatus {
+    Delivered,
+    Failed,
+    Throttled,
+    Skipped,
+    DryRun,
+}
+
+impl ReceiptStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            ReceiptStatus::Delivered => "delivered",
+            ReceiptStatus::Failed => "failed",
+            ReceiptStatus::Throttled => "throttled",
+            ReceiptStatus::Skipped => "skipped",
+            ReceiptStatus::DryRun => "dry_run",
+        }
+    }
+}
+
+/// Returns when the next delivery is allowed if the address has already received
+/// `max_emails_per_week` issues over the last 7 days, and `None` otherwise.
+///
+/// `last_emailed_at` rules out most recipients without looking at their receipts.
+#[tracing::instrument(skip(pool, email))]
+async fn frequency_cap_reached(
+    pool: &PgPool,
+    email: &str,
+    max_emails_per_week: i64,
+) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    if max_emails_per_week <= 0 {
+        return Ok(None);
+    }
+    let record = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions
+            WHERE email = $1 AND last_emailed_at > now() - interval '7 days'
+        ) AS "recent!"
+        "#,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+    if !record.recent {
+        return Ok(None);
+    }
+    // The cap is reached if the `max_emails_per_week`-th most recent delivery is in the window,
+    // and it is lifted 7 days after that delivery.
+    let record = sqlx::query!(
+        r#"
+        SELECT recorded_at + interval '7 days' AS "retry_at!"
+        FROM issue_delivery_receipts
+        WHERE subscriber_email = $1
+          AND status = 'delivered'
+          AND recorded_at > now() - interval '7 days'
+        ORDER BY recorded_at DESC
+        OFFSET $2::bigint - 1
+        LIMIT 1
+        "#,
+        email,
+        max_emails_per_week
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(record.map(|r| r.retry_at))
+}
+
+#[tracing::instrument(skip_all)]
+async fn record_last_emailed_at(pool: &PgPool, email: &str) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET last_emailed_at = now() WHERE email = $1",
+        email
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Whether the issue has already been sent to the email address.
+#[tracing::instrument(skip_all)]
+async fn is_delivered(pool: &PgPool, issue_id: Uuid, email: &str) -> Result<bool, anyhow::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM issue_delivery_receipts
+            WHERE newsletter_issue_id = $1 AND subscriber_email = $2 AND status = 'delivered'
+        ) AS "exists!"
+        "#,
+        issue_id,
+        email
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok(record.exists)
+}
+
+/// Records the outcome of a delivery, replacing the previous one.
+///
+/// This is written outside of the task's transaction: once an email has gone out,
+/// the receipt must survive even if deleting the task fails afterwards.
+#[tracing::instrument(skip_all)]
+async fn store_receipt(
+    pool: &PgPool,
+    issue_id: Uuid,
+    email: &str,
+    status: ReceiptStatus,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_receipts (
+            newsletter_issue_id, subscriber_email, status, recorded_at
+        )
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET status = EXCLUDED.status, recorded_at = EXCLUDED.recorded_at
+        "#,
+        issue_id,
+        email,
+        status.as_str()
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+/// Locks the next due task: the highest priority first, then the one enqueued the earliest.
 #[tracing::instrument(skip_all)]
 async fn dequeue_task(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String)>, anyhow::Error> {
+) -> Result<Option<(PgTransaction, Uuid, String, i32)>, anyhow::Error> {
     let mut tx = pool.begin().await?;
     let query = sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email
+        SELECT newsletter_issue_id, subscriber_email, n_retries
         FROM issue_delivery_queue
+        WHERE execute_after <= now()
+        ORDER BY priority DESC, enqueued_at
         FOR UPDATE SKIP LOCKED
         LIMIT 1
         "#,
     );
-    let record = tx.fetch_optional(query).await?;
+    let record = timed_query("dequeue_task", tx.fetch_optional(query)).await?;
     match record {
         Some(record) => Ok(Some((
             tx,
             record.try_get("newsletter_issue_id")?,
             record.try_get("subscriber_email")?,
+            record.try_get("n_retries")?,
         ))),
         None => Ok(None),
     }
@@ -135,16 +861,233 @@ async fn delete_task(
     Ok(())
 }
 
-struct NewsletterIssue {
-    title: String,
-    text_content: String,
-    html_content: String,
+/// Whether the issue has been cancelled since the task was enqueued.
+#[tracing::instrument(skip_all)]
+async fn is_cancelled(tx: &mut PgTransaction, issue_id: Uuid) -> Result<bool, anyhow::Error> {
+    let record = sqlx::query!(
+        "SELECT status FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        issue_id
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(record.status == CANCELLED)
+}
+
+/// When a task that has already failed `n_retries` times is attempted again.
+///
+/// The delay starts at [WorkerSettings::retry_base_delay_milliseconds], doubles on every
+/// failure, and never exceeds [WorkerSettings::max_retry_delay_milliseconds].
+fn next_attempt_at(settings: &WorkerSettings, n_retries: i32) -> DateTime<Utc> {
+    let exponent = n_retries.clamp(0, 16) as u32;
+    let delay = settings
+        .retry_base_delay_milliseconds
+        .saturating_mul(1 << exponent)
+        .min(settings.max_retry_delay_milliseconds);
+    let delay = chrono::Duration::milliseconds(i64::try_from(delay).unwrap_or(i64::MAX));
+    Utc::now()
+        .checked_add_signed(delay)
+        .unwrap_or(DateTime::<Utc>::MAX_UTC)
 }
 
+/// Counts a failed attempt at a task that is kept in the queue,
+/// and postpones it until `execute_after`.
 #[tracing::instrument(skip_all)]
-async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
-    let issue = sqlx::query_as!(
-        NewsletterIssue,
+async fn record_failed_attempt(
+    tx: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    execute_after: DateTime<Utc>,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET n_retries = n_retries + 1, execute_after = $3
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        issue_id,
+        email,
+        execute_after
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+/// Keeps a copy of a task the worker gave up on, so that it can be requeued later.
+#[tracing::instrument(skip(tx, email))]
+async fn dead_letter_task(
+    tx: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    n_retries: i32,
+    reason: &str,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO dead_letter (
+            id, newsletter_issue_id, subscriber_email, n_retries, reason, dead_lettered_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET n_retries = EXCLUDED.n_retries,
+            reason = EXCLUDED.reason,
+            dead_lettered_at = EXCLUDED.dead_lettered_at
+        "#,
+        Uuid::new_v4(),
+        issue_id,
+        email,
+        n_retries,
+        reason
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+/// Keeps the task in the queue, but out of reach of the worker until `execute_after`.
+#[tracing::instrument(skip_all)]
+async fn postpone_task(
+    tx: &mut PgTransaction,
+    issue_id: Uuid,
+    email: &str,
+    execute_after: DateTime<Utc>,
+) -> Result<(), anyhow::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET execute_after = $3
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        issue_id,
+        email,
+        execute_after
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+/// Updates the delivery counters of an issue and,
+/// once its last task is done, sets its final status.
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    tx: &mut PgTransaction,
+    issue_id: Uuid,
+    outcome: &DeliveryOutcome,
+    settings: &WorkerSettings,
+) -> Result<(), anyhow::Error> {
+    let (delivered, skipped) = match outcome {
+        DeliveryOutcome::Delivered => (1, 0),
+        DeliveryOutcome::Skipped | DeliveryOutcome::Rejected { .. } => (0, 1),
+        DeliveryOutcome::AlreadyDelivered
+        | DeliveryOutcome::DryRun
+        | DeliveryOutcome::NotConfirmed
+        | DeliveryOutcome::Throttled { .. }
+        | DeliveryOutcome::Deferred { .. } => (0, 0),
+    };
+    let counts = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET delivered_count = delivered_count + $2, skipped_count = skipped_count + $3
+        WHERE newsletter_issue_id = $1
+        RETURNING title, delivered_count, skipped_count
+        "#,
+        issue_id,
+        delivered,
+        skipped
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    let remaining = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+        ) AS "remaining!"
+        "#,
+        issue_id
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    if remaining.remaining {
+        return Ok(());
+    }
+
+    let status = final_issue_status(
+        counts.delivered_count,
+        counts.skipped_count,
+        settings.skipped_ratio_threshold,
+    );
+    if status == COMPLETED_WITH_ERRORS {
+        tracing::error!(
+            newsletter_issue_id = %issue_id,
+            title = %counts.title,
+            delivered = counts.delivered_count,
+            skipped = counts.skipped_count,
+            "A newsletter issue completed with errors."
+        );
+    }
+    let query = sqlx::query!(
+        "UPDATE newsletter_issues SET status = $2 WHERE newsletter_issue_id = $1",
+        issue_id,
+        status
+    );
+    tx.execute(query).await?;
+    Ok(())
+}
+
+const COMPLETED: &str = "completed";
+const COMPLETED_WITH_ERRORS: &str = "completed_with_errors";
+const CANCELLED: &str = "cancelled";
+
+/// The status of an issue whose delivery tasks have all been processed.
+fn final_issue_status(delivered: i32, skipped: i32, skipped_ratio_threshold: f64) -> &'static str {
+    let total = delivered + skipped;
+    if total > 0 && f64::from(skipped) / f64::from(total) >= skipped_ratio_threshold {
+        COMPLETED_WITH_ERRORS
+    } else {
+        COMPLETED
+    }
+}
+
+/// Loads an issue for delivery. Its size was checked when it was published,
+/// so only its content is validated again.
+///
+/// The translation closest to `locale` is picked, e.g. `fr` for `fr-CA`,
+/// and the default content when there is none.
+///
+/// A missing or invalid issue is a [WorkerError::Fatal] error, since no retry can fix it.
+#[tracing::instrument(skip_all)]
+async fn get_issue(
+    pool: &PgPool,
+    issue_id: Uuid,
+    locale: Option<SubscriberLocale>,
+) -> Result<NewsletterIssue, WorkerError> {
+    if let Some(locale) = locale {
+        let localization = sqlx::query!(
+            r#"
+            SELECT title, text_content, html_content
+            FROM newsletter_issue_localizations
+            WHERE newsletter_issue_id = $1 AND lower(locale) = ANY($2)
+            ORDER BY array_position($2, lower(locale))
+            LIMIT 1
+            "#,
+            issue_id,
+            &locale.fallbacks()[..]
+        )
+        .fetch_optional(pool)
+        .await
+        .context("Failed to load the translation of the newsletter issue.")?;
+        if let Some(record) = localization {
+            return NewsletterIssue::parse(
+                record.title,
+                record.html_content,
+                record.text_content,
+                &IssueLimits::UNBOUNDED,
+            )
+            .context("The stored translation of the newsletter issue is invalid.")
+            .map_err(WorkerError::Fatal);
+        }
+    }
+
+    let record = sqlx::query!(
         r#"
         SELECT title, text_content, html_content
         FROM newsletter_issues
@@ -152,8 +1095,204 @@ async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, any
         "#,
         issue_id
     )
-    .fetch_one(pool)
-    .await?;
+    .fetch_optional(pool)
+    .await
+    .context("Failed to load the newsletter issue.")?
+    .ok_or_else(|| WorkerError::Fatal(anyhow::anyhow!("The newsletter issue does not exist.")))?;
+
+    NewsletterIssue::parse(
+        record.title,
+        record.html_content,
+        record.text_content,
+        &IssueLimits::UNBOUNDED,
+    )
+    .context("The stored newsletter issue is invalid.")
+    .map_err(WorkerError::Fatal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_issue_where_every_recipient_was_skipped_completes_with_errors() {
+        assert_eq!(final_issue_status(0, 3, 1.0), COMPLETED_WITH_ERRORS);
+    }
+
+    #[test]
+    fn an_issue_with_some_deliveries_completes_below_the_threshold() {
+        assert_eq!(final_issue_status(1, 3, 1.0), COMPLETED);
+        assert_eq!(final_issue_status(3, 1, 0.5), COMPLETED);
+    }
+
+    #[test]
+    fn an_issue_completes_with_errors_at_the_threshold() {
+        assert_eq!(final_issue_status(1, 1, 0.5), COMPLETED_WITH_ERRORS);
+    }
+
+    fn worker_settings(max_emails_per_second: u32) -> WorkerSettings {
+        WorkerSettings {
+            dry_run: false,
+            skipped_ratio_threshold: 1.0,
+            stats_log_interval: 0,
+            max_emails_per_second,
+            max_emails_per_week: 0,
+            max_retries: 0,
+            retry_base_delay_milliseconds: 1000,
+            max_retry_delay_milliseconds: 10_000,
+            concurrency: 1,
+        }
+    }
+
+    #[test]
+    fn the_retry_delay_doubles_on_every_failure_up_to_the_cap() {
+        let settings = worker_settings(0);
+        let delay = |n_retries| {
+            let delay = next_attempt_at(&settings, n_retries) - Utc::now();
+            (delay.num_milliseconds() + 500) / 1000
+        };
+        assert_eq!(delay(0), 1);
+        assert_eq!(delay(1), 2);
+        assert_eq!(delay(3), 8);
+        assert_eq!(delay(4), 10);
+        assert_eq!(delay(i32::MAX), 10);
+    }
+
+    #[tokio::test]
+    async fn the_throttle_spreads_tasks_evenly_over_time() {
+        let throttle = DeliveryThrottle::new(&worker_settings(20));
+
+        let start = std::time::Instant::now();
+        for _ in 0..5 {
+            throttle.until_ready().await;
+        }
+
+        // The first task runs right away, the four others 50ms apart.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(190), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(500), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn a_zero_rate_disables_the_throttle() {
+        let throttle = DeliveryThrottle::new(&worker_settings(0));
+
+        let start = std::time::Instant::now();
+        for _ in 0..100 {
+            throttle.until_ready().await;
+        }
+
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn a_completed_task_is_counted_and_timestamped() {
+        let state = WorkerState::default();
+        state.record(&Ok(ExecutionOutcome::TaskCompleted));
+        state.record(&Ok(ExecutionOutcome::TaskCompleted));
+
+        let status = state.status();
+        assert_eq!(status.activity, WorkerActivity::Working);
+        assert_eq!(status.tasks_processed, 2);
+        assert!(status.last_task_at.is_some());
+    }
+
+    #[test]
+    fn an_empty_queue_makes_the_worker_idle() {
+        let state = WorkerState::default();
+        state.record(&Ok(ExecutionOutcome::TaskCompleted));
+        state.record(&Ok(ExecutionOutcome::EmptyQueue));
+
+        let status = state.status();
+        assert_eq!(status.activity, WorkerActivity::Idle);
+        assert_eq!(status.tasks_processed, 1);
+    }
+
+    /// Collects the numeric fields of every "Worker statistics." event.
+    #[derive(Clone, Default)]
+    struct StatsEvents(Arc<Mutex<Vec<std::collections::HashMap<String, u64>>>>);
 
-    Ok(issue)
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for StatsEvents {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            #[derive(Default)]
+            struct Visitor {
+                message: String,
+                fields: std::collections::HashMap<String, u64>,
+            }
+            impl tracing::field::Visit for Visitor {
+                fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+                    self.fields.insert(field.name().to_owned(), value);
+                }
+                fn record_debug(
+                    &mut self,
+                    field: &tracing::field::Field,
+                    value: &dyn std::fmt::Debug,
+                ) {
+                    if field.name() == "message" {
+                        self.message = format!("{:?}", value);
+                    }
+                }
+            }
+            let mut visitor = Visitor::default();
+            event.record(&mut visitor);
+            if visitor.message == "Worker statistics." {
+                self.0.lock().unwrap().push(visitor.fields);
+            }
+        }
+    }
+
+    #[test]
+    fn stats_are_logged_every_interval_with_cumulative_counts() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let events = StatsEvents::default();
+        let subscriber = tracing_subscriber::registry().with(events.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut stats = WorkerStats::default();
+            stats.record(&Ok(ExecutionOutcome::TaskCompleted), 3);
+            stats.record(&Ok(ExecutionOutcome::TaskCompleted), 3);
+            stats.record(&Ok(ExecutionOutcome::EmptyQueue), 3);
+            stats.record(&Err(anyhow::anyhow!("Connection refused.")), 3);
+            stats.record(&Ok(ExecutionOutcome::EmptyQueue), 3);
+            stats.record(&Ok(ExecutionOutcome::TaskCompleted), 3);
+        });
+
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        let last = &events[1];
+        assert_eq!(last["iterations"], 6);
+        assert_eq!(last["tasks_completed"], 3);
+        assert_eq!(last["empty_polls"], 2);
+        assert_eq!(last["errors"], 1);
+    }
+
+    #[test]
+    fn stats_are_not_logged_when_the_interval_is_zero() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let events = StatsEvents::default();
+        let subscriber = tracing_subscriber::registry().with(events.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut stats = WorkerStats::default();
+            for _ in 0..10 {
+                stats.record(&Ok(ExecutionOutcome::EmptyQueue), 0);
+            }
+        });
+
+        assert!(events.0.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn the_last_error_is_kept() {
+        let state = WorkerState::default();
+        state.record(&Err(anyhow::anyhow!("Connection refused.")));
+
+        let status = state.status();
+        assert_eq!(status.last_error.as_deref(), Some("Connection refused."));
+        assert_eq!(status.tasks_processed, 0);
+    }
 }
@@ -1,11 +1,24 @@
 pub mod authentication;
+pub mod client_ip;
 pub mod configuration;
+pub mod confirmation_outbox;
+pub mod disposable_domains;
 pub mod domain;
 pub mod email_client;
+pub mod email_templates;
+pub mod flash_cookies;
+pub mod https;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod maintenance;
+pub mod markdown;
+pub mod metrics;
+pub mod pending_sweeper;
+pub mod rate_limit;
+pub mod request_timeout;
 pub mod routes;
 pub mod session_state;
+pub mod session_store;
 pub mod startup;
 pub mod telemetry;
 pub mod utils;
@@ -1,5 +1,7 @@
 use newsletter_lib::configuration::get_configuration;
+use newsletter_lib::confirmation_outbox::run_outbox_until_stopped;
 use newsletter_lib::issue_delivery_worker::run_worker_until_stopped;
+use newsletter_lib::pending_sweeper::run_sweeper_until_stopped;
 use newsletter_lib::startup::Application;
 use newsletter_lib::telemetry::{get_subscriber, init_subscriber};
 use std::fmt::{Debug, Display};
@@ -7,18 +9,33 @@ use tokio::task::JoinError;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let subscriber = get_subscriber("newsletter".into(), "info".into(), std::io::stdout);
+    let configurations = get_configuration().expect("Failed to read configuration.");
+    let subscriber = get_subscriber(
+        "newsletter".into(),
+        "info".into(),
+        std::io::stdout,
+        configurations.application.otlp_endpoint.as_deref(),
+    );
     init_subscriber(subscriber);
 
-    let configurations = get_configuration().expect("Failed to read configuration.");
     let application = Application::build(&configurations.clone()).await?;
+    let worker_state = application.get_worker_state();
+    let email_client = application.get_email_client();
     let application_task = tokio::spawn(application.run_until_stopped());
-    let worker_task = tokio::spawn(run_worker_until_stopped(configurations));
+    let outbox_task = tokio::spawn(run_outbox_until_stopped(
+        configurations.clone(),
+        email_client,
+    ));
+    let sweeper_task = tokio::spawn(run_sweeper_until_stopped(configurations.clone()));
+    let worker_task = tokio::spawn(run_worker_until_stopped(configurations, worker_state));
 
     tokio::select! {
         result = application_task => report_exit("API", result),
         result = worker_task => report_exit("Worker", result),
+        result = outbox_task => report_exit("Confirmation outbox", result),
+        result = sweeper_task => report_exit("Pending subscriber sweeper", result),
     }
+    opentelemetry::global::shutdown_tracer_provider();
 
     Ok(())
 }
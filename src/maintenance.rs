@@ -0,0 +1,133 @@
+use crate::configuration::MaintenanceSettings;
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::{header, Method};
+use actix_web::{web, HttpResponse};
+use actix_web_lab::middleware::Next;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+/// The maintenance-mode flag, stored in Redis so that it can be toggled without a restart.
+#[derive(Clone)]
+pub struct MaintenanceMode {
+    connection: ConnectionManager,
+    redis_key: String,
+    retry_after_seconds: u64,
+}
+
+impl MaintenanceMode {
+    pub async fn new(
+        redis_url: &str,
+        settings: &MaintenanceSettings,
+    ) -> Result<Self, anyhow::Error> {
+        let connection = redis::Client::open(redis_url)?
+            .get_connection_manager()
+            .await?;
+        Ok(Self {
+            connection,
+            redis_key: settings.redis_key.clone(),
+            retry_after_seconds: settings.retry_after_seconds,
+        })
+    }
+
+    pub async fn is_enabled(&self) -> Result<bool, anyhow::Error> {
+        let value: Option<String> = self.connection.clone().get(&self.redis_key).await?;
+        Ok(value.as_deref().is_some_and(is_enabled_value))
+    }
+
+    pub async fn set_enabled(&self, enabled: bool) -> Result<(), anyhow::Error> {
+        let value = if enabled { "1" } else { "0" };
+        self.connection
+            .clone()
+            .set(&self.redis_key, value)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+fn is_enabled_value(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "on"
+    )
+}
+
+/// Whether a request changes state and has to be rejected during maintenance.
+///
+/// `/health_check` is never rejected, so that the load balancer keeps the instance in rotation.
+fn is_mutating_request(req: &ServiceRequest) -> bool {
+    let mutating = matches!(
+        *req.method(),
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    );
+    mutating && !req.path().ends_with("/health_check")
+}
+
+/// Rejects mutating requests with `503 Service Unavailable` while maintenance mode is on.
+///
+/// Reads keep being served. If the flag cannot be read from Redis,
+/// the request is let through rather than taking the whole application down.
+#[tracing::instrument(name = "Check maintenance mode", skip(req, next))]
+pub async fn reject_mutations_during_maintenance(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    if is_mutating_request(&req) {
+        if let Some(maintenance_mode) = req.app_data::<web::Data<MaintenanceMode>>() {
+            match maintenance_mode.is_enabled().await {
+                Ok(true) => {
+                    let response = HttpResponse::ServiceUnavailable()
+                        .insert_header((
+                            header::RETRY_AFTER,
+                            maintenance_mode.retry_after_seconds.to_string(),
+                        ))
+                        .body("The service is under maintenance. Try again later.");
+                    return Ok(req.into_response(response).map_into_right_body());
+                }
+                Ok(false) => {}
+                Err(e) => tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to read the maintenance-mode flag. Letting the request through."
+                ),
+            }
+        }
+    }
+    next.call(req)
+        .await
+        .map(ServiceResponse::map_into_left_body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn common_truthy_values_enable_maintenance_mode() {
+        for value in ["1", "true", "ON", " on\n"] {
+            assert!(is_enabled_value(value), "{:?}", value);
+        }
+        for value in ["0", "false", "off", ""] {
+            assert!(!is_enabled_value(value), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn only_mutating_methods_are_rejected() {
+        let get = TestRequest::get().uri("/subscriptions").to_srv_request();
+        let post = TestRequest::post().uri("/subscriptions").to_srv_request();
+        let delete = TestRequest::delete().uri("/admin/users").to_srv_request();
+        assert!(!is_mutating_request(&get));
+        assert!(is_mutating_request(&post));
+        assert!(is_mutating_request(&delete));
+    }
+
+    #[test]
+    fn the_health_check_is_never_rejected() {
+        let post = TestRequest::post()
+            .uri("/newsletter/health_check")
+            .to_srv_request();
+        assert!(!is_mutating_request(&post));
+    }
+}
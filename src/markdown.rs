@@ -0,0 +1,135 @@
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+
+/// The output of [render], holding both bodies of a newsletter issue.
+pub struct RenderedContent {
+    pub html: String,
+    pub text: String,
+}
+
+/// Renders Markdown into the HTML and plain-text bodies of a newsletter issue.
+///
+/// The HTML body is produced by `pulldown-cmark`.
+/// The plain-text body is derived from the same events by stripping the markup,
+/// keeping link destinations next to their text so that they survive in text-only clients.
+pub fn render(markdown: &str) -> RenderedContent {
+    let events: Vec<_> = Parser::new_ext(markdown, options()).collect();
+
+    let mut html = String::new();
+    html::push_html(&mut html, events.iter().cloned());
+    let text = strip_markup(events);
+
+    RenderedContent { html, text }
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH
+}
+
+fn strip_markup(events: Vec<Event<'_>>) -> String {
+    let mut text = String::new();
+    let mut links: Vec<(CowStr, usize)> = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => {
+                end_block(&mut text);
+                text.push_str("---");
+                end_block(&mut text);
+            }
+            Event::Start(Tag::Item) => text.push_str("- "),
+            Event::Start(Tag::Link { dest_url, .. }) => links.push((dest_url, text.len())),
+            Event::End(TagEnd::Link) => {
+                if let Some((dest_url, start)) = links.pop() {
+                    if text[start..] != *dest_url {
+                        text.push_str(&format!(" ({})", dest_url));
+                    }
+                }
+            }
+            Event::End(TagEnd::Item | TagEnd::TableRow | TagEnd::TableHead)
+                if !text.ends_with('\n') =>
+            {
+                text.push('\n')
+            }
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote
+                | TagEnd::List(_)
+                | TagEnd::Table,
+            ) => end_block(&mut text),
+            _ => {}
+        }
+    }
+
+    text.trim_end().to_string()
+}
+
+/// Separates blocks with a single blank line.
+fn end_block(text: &mut String) {
+    if text.is_empty() {
+        return;
+    }
+    let trimmed_len = text.trim_end_matches('\n').len();
+    text.truncate(trimmed_len);
+    text.push_str("\n\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paragraphs_and_emphasis_are_rendered_to_html() {
+        let rendered = render("Hello, *world*!\n\nSecond **paragraph**.");
+        assert_eq!(
+            rendered.html,
+            "<p>Hello, <em>world</em>!</p>\n<p>Second <strong>paragraph</strong>.</p>\n"
+        );
+        assert_eq!(rendered.text, "Hello, world!\n\nSecond paragraph.");
+    }
+
+    #[test]
+    fn fenced_code_blocks_are_rendered_to_html() {
+        let rendered = render("```rust\nfn main() {\n    println!(\"<hi>\");\n}\n```");
+        assert_eq!(
+            rendered.html,
+            "<pre><code class=\"language-rust\">fn main() {\n    println!(\"&lt;hi&gt;\");\n}\n</code></pre>\n"
+        );
+        assert_eq!(rendered.text, "fn main() {\n    println!(\"<hi>\");\n}");
+    }
+
+    #[test]
+    fn links_are_rendered_to_html() {
+        let rendered = render("Read [the docs](https://example.com/docs \"Docs\").");
+        assert_eq!(
+            rendered.html,
+            "<p>Read <a href=\"https://example.com/docs\" title=\"Docs\">the docs</a>.</p>\n"
+        );
+    }
+
+    #[test]
+    fn link_destinations_are_kept_in_plain_text() {
+        let rendered =
+            render("Read [the docs](https://example.com/docs) or <https://example.com>.");
+        assert_eq!(
+            rendered.text,
+            "Read the docs (https://example.com/docs) or https://example.com."
+        );
+    }
+
+    #[test]
+    fn lists_and_headings_are_stripped_to_plain_text() {
+        let rendered = render("# Title\n\n- first\n- second\n\nThe end.");
+        assert_eq!(rendered.text, "Title\n\n- first\n- second\n\nThe end.");
+    }
+
+    #[test]
+    fn raw_html_is_dropped_from_plain_text() {
+        let rendered = render("<div>ignored</div>\n\nKept.");
+        assert_eq!(rendered.text, "Kept.");
+    }
+}
@@ -0,0 +1,93 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::web;
+use actix_web_lab::middleware::Next;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use std::time::Instant;
+
+/// The label used for requests that do not match any route, so that scanners probing random
+/// paths cannot blow up the number of time series.
+const UNMATCHED_PATH: &str = "unmatched";
+
+/// The Prometheus registry of the application and the request metrics recorded into it.
+///
+/// Each [crate::startup::Application] owns its registry, rather than using the global default one,
+/// so that several instances in the same process (as in the tests) do not share their counters.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new();
+        let labels = ["method", "path", "status"];
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of HTTP requests handled."),
+            &labels,
+        )?;
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling HTTP requests, in seconds.",
+            ),
+            &labels,
+        )?;
+        registry.register(Box::new(http_requests_total.clone()))?;
+        registry.register(Box::new(http_request_duration_seconds.clone()))?;
+        Ok(Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+        })
+    }
+
+    fn observe(&self, method: &str, path: &str, status: u16, elapsed_seconds: f64) {
+        let status = status.to_string();
+        let labels = [method, path, status.as_str()];
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(elapsed_seconds);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+    }
+}
+
+/// Records the count and latency of every request, labelled with its method, status,
+/// and the pattern of the route it matched (`/admin/newsletters/{id}`, not the raw path).
+pub async fn record_request_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(metrics) = req.app_data::<web::Data<Metrics>>().cloned() else {
+        return next.call(req).await;
+    };
+    let method = req.method().to_string();
+    let path = req
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_PATH.to_owned());
+    let started_at = Instant::now();
+
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    metrics.observe(
+        &method,
+        &path,
+        status.as_u16(),
+        started_at.elapsed().as_secs_f64(),
+    );
+    result
+}
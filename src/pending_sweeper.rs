@@ -0,0 +1,135 @@
+use crate::configuration::Settings;
+use crate::domain::SubscriptionStatus;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub async fn run_sweeper_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = configuration.database.connection_pool();
+    let settings = configuration.subscriptions;
+    if settings.pending_expiry_seconds == 0 {
+        tracing::info!("Unconfirmed subscribers never expire. The sweeper is disabled.");
+        std::future::pending::<()>().await;
+    }
+
+    sweeper_loop(
+        connection_pool,
+        Duration::from_secs(settings.pending_expiry_seconds),
+        Duration::from_secs(settings.pending_sweep_interval_seconds.max(1)),
+    )
+    .await
+}
+
+async fn sweeper_loop(
+    pool: PgPool,
+    ttl: Duration,
+    interval: Duration,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match purge_stale_pending(&pool, ttl).await {
+            Ok(0) => {}
+            Ok(n_purged) => tracing::info!(n_purged, "Purged unconfirmed subscribers."),
+            Err(e) => tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to purge unconfirmed subscribers."
+            ),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Deletes the subscribers who have been waiting for confirmation for longer than `ttl`,
+/// together with their tokens, tags, and pending confirmation emails.
+///
+/// The age is measured from the last confirmation email, so that a subscriber who has just
+/// asked for the email to be sent again is not purged before they get a chance to click it.
+///
+/// Subscribers with an unconfirmed email change are never deleted: the change is
+/// reverted by [revert_expired_email_changes] instead, so that a mistyped new address
+/// does not cost them their subscription.
+///
+/// Returns the number of subscribers deleted.
+#[tracing::instrument(name = "Purge stale pending subscribers", skip(pool))]
+pub async fn purge_stale_pending(pool: &PgPool, ttl: Duration) -> Result<u64, anyhow::Error> {
+    let cutoff = Utc::now()
+        - chrono::Duration::from_std(ttl)
+            .context("The expiry of pending subscribers is too long.")?;
+    let n_reverted = revert_expired_email_changes(pool, cutoff).await?;
+    if n_reverted > 0 {
+        tracing::info!(n_reverted, "Reverted expired email changes.");
+    }
+    let result = sqlx::query!(
+        r#"
+        WITH stale AS (
+            SELECT id FROM subscriptions
+            WHERE status = 'pending_confirmation'
+              AND pending_email IS NULL
+              AND GREATEST(subscribed_at, last_confirmation_sent_at) < $1
+            FOR UPDATE SKIP LOCKED
+        ),
+        tokens AS (
+            DELETE FROM subscription_tokens WHERE subscriber_id IN (SELECT id FROM stale)
+        ),
+        rotations AS (
+            DELETE FROM subscription_token_rotations WHERE subscriber_id IN (SELECT id FROM stale)
+        ),
+        tags AS (
+            DELETE FROM subscriber_tags WHERE subscriber_id IN (SELECT id FROM stale)
+        ),
+        outbox AS (
+            DELETE FROM confirmation_email_outbox WHERE subscriber_id IN (SELECT id FROM stale)
+        )
+        DELETE FROM subscriptions WHERE id IN (SELECT id FROM stale)
+        "#,
+        cutoff
+    )
+    .execute(pool)
+    .await
+    .context("Failed to delete stale pending subscribers.")?;
+    Ok(result.rows_affected())
+}
+
+/// Drops the email changes that have not been confirmed since `cutoff`.
+///
+/// The subscriber keeps their current address, and goes back to `confirmed` if they had
+/// confirmed it before. The tokens sent to the new address are revoked.
+/// A subscriber who had never confirmed stays pending, and is purged once stale.
+///
+/// Returns the number of email changes reverted.
+#[tracing::instrument(name = "Revert expired email changes", skip(pool))]
+async fn revert_expired_email_changes(
+    pool: &PgPool,
+    cutoff: DateTime<Utc>,
+) -> Result<u64, anyhow::Error> {
+    let result = sqlx::query!(
+        r#"
+        WITH expired AS (
+            SELECT id FROM subscriptions
+            WHERE status = 'pending_confirmation'
+              AND pending_email IS NOT NULL
+              AND GREATEST(subscribed_at, last_confirmation_sent_at) < $1
+            FOR UPDATE SKIP LOCKED
+        ),
+        tokens AS (
+            DELETE FROM subscription_tokens WHERE subscriber_id IN (SELECT id FROM expired)
+        ),
+        outbox AS (
+            DELETE FROM confirmation_email_outbox WHERE subscriber_id IN (SELECT id FROM expired)
+        )
+        UPDATE subscriptions
+        SET pending_email = NULL,
+            status = CASE
+                WHEN confirmed_source IS NOT NULL THEN $2 ELSE status
+            END
+        WHERE id IN (SELECT id FROM expired)
+        "#,
+        cutoff,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+    )
+    .execute(pool)
+    .await
+    .context("Failed to revert expired email changes.")?;
+    Ok(result.rows_affected())
+}
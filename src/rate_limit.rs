@@ -0,0 +1,95 @@
+use actix_web::http::header::{self, HeaderName, HeaderValue};
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+
+const X_RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("x-ratelimit-limit");
+const X_RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("x-ratelimit-remaining");
+const X_RATELIMIT_RESET: HeaderName = HeaderName::from_static("x-ratelimit-reset");
+
+/// The quota of a client on a rate-limited endpoint, reported to it in the response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// How many requests are allowed in the window.
+    pub limit: i64,
+    /// How many requests are left in the current window.
+    pub remaining: i64,
+    /// When the window is over and the quota is restored.
+    pub reset_at: DateTime<Utc>,
+}
+
+impl RateLimitStatus {
+    /// Seconds until the quota is restored, rounded up so that clients never retry too early.
+    pub fn reset_after_seconds(&self) -> i64 {
+        let milliseconds = (self.reset_at - Utc::now()).num_milliseconds().max(0);
+        (milliseconds + 999) / 1000
+    }
+
+    /// Adds `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset` to the response,
+    /// and `Retry-After` if the quota has been used up.
+    ///
+    /// `X-RateLimit-Reset` is the number of seconds until the quota is restored.
+    pub fn insert_headers(&self, response: &mut HttpResponse) {
+        let reset_after_seconds = HeaderValue::from(self.reset_after_seconds());
+        let headers = response.headers_mut();
+        headers.insert(X_RATELIMIT_LIMIT, HeaderValue::from(self.limit));
+        headers.insert(
+            X_RATELIMIT_REMAINING,
+            HeaderValue::from(self.remaining.max(0)),
+        );
+        headers.insert(X_RATELIMIT_RESET, reset_after_seconds.clone());
+        if self.remaining <= 0 {
+            headers.insert(header::RETRY_AFTER, reset_after_seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn header<'a>(response: &'a HttpResponse, name: &str) -> Option<&'a str> {
+        response
+            .headers()
+            .get(name)
+            .map(|value| value.to_str().unwrap())
+    }
+
+    #[test]
+    fn the_quota_is_reported_in_the_headers() {
+        let status = RateLimitStatus {
+            limit: 3,
+            remaining: 2,
+            reset_at: Utc::now() + Duration::seconds(60),
+        };
+        let mut response = HttpResponse::Ok().finish();
+        status.insert_headers(&mut response);
+        assert_eq!(header(&response, "X-RateLimit-Limit"), Some("3"));
+        assert_eq!(header(&response, "X-RateLimit-Remaining"), Some("2"));
+        assert_eq!(header(&response, "X-RateLimit-Reset"), Some("60"));
+        assert_eq!(header(&response, "Retry-After"), None);
+    }
+
+    #[test]
+    fn retry_after_is_set_once_the_quota_is_used_up() {
+        let status = RateLimitStatus {
+            limit: 3,
+            remaining: 0,
+            reset_at: Utc::now() + Duration::seconds(30),
+        };
+        let mut response = HttpResponse::TooManyRequests().finish();
+        status.insert_headers(&mut response);
+        assert_eq!(header(&response, "X-RateLimit-Remaining"), Some("0"));
+        assert_eq!(header(&response, "Retry-After"), Some("30"));
+    }
+
+    #[test]
+    fn a_reset_in_the_past_is_reported_as_zero() {
+        let status = RateLimitStatus {
+            limit: 1,
+            remaining: 1,
+            reset_at: Utc::now() - Duration::seconds(5),
+        };
+        assert_eq!(status.reset_after_seconds(), 0);
+    }
+}
@@ -0,0 +1,38 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::ErrorGatewayTimeout;
+use actix_web::web;
+use actix_web_lab::middleware::Next;
+use std::time::Duration;
+
+/// How long a request may take before the client is answered with `504 Gateway Timeout`.
+pub struct RequestTimeout(pub Duration);
+
+/// Answers with `504 Gateway Timeout` when the rest of the pipeline takes longer than
+/// [RequestTimeout], so that clients are not left hanging on a slow database or email provider.
+///
+/// The handler is dropped when the timeout expires. Its open transactions are rolled back,
+/// but whatever it has already committed stays: work left to do after a commit must be
+/// recorded before it, as confirmation emails are through
+/// [crate::confirmation_outbox::schedule_confirmation_retry].
+pub async fn enforce_request_timeout(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let Some(timeout) = req
+        .app_data::<web::Data<RequestTimeout>>()
+        .map(|timeout| timeout.0)
+    else {
+        return next.call(req).await;
+    };
+    match tokio::time::timeout(timeout, next.call(req)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(
+                timeout_milliseconds = timeout.as_millis() as u64,
+                "The request timed out."
+            );
+            Err(ErrorGatewayTimeout("The request timed out."))
+        }
+    }
+}
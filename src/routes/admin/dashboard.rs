@@ -1,20 +1,54 @@
-use crate::authentication::UserId;
+use crate::authentication::{csrf_token, LastLogin, UserId};
+use crate::session_state::TypedSession;
+use crate::startup::{BasePath, ReadReplicaPool};
 use crate::utils;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{IncomingFlashMessages, Level};
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// The data shown on the dashboard.
+#[derive(serde::Serialize)]
+pub struct Dashboard {
+    username: String,
+    subscribers: SubscriberStats,
+    queue_depth: QueueDepth,
+    issues_with_errors: Vec<IssueWithErrors>,
+    last_login: Option<LastLogin>,
+}
+
+/// Render the admin dashboard, or return its data for clients that accept JSON.
+///
+/// # Response
+///
+/// - **200 OK**: The dashboard page, or a JSON [Dashboard].
+/// - **500 Internal Server Error**: An error occurred while processing the request.
 pub async fn admin_dashboard(
-    pool: web::Data<PgPool>,
+    req: HttpRequest,
+    pool: web::Data<ReadReplicaPool>,
     tmpl: web::Data<tera::Tera>,
     user_id: web::ReqData<UserId>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
-    let username = get_username(&pool, *user_id).await.map_err(utils::e500)?;
+    let dashboard = Dashboard {
+        username: get_username(&pool.0, *user_id).await.map_err(utils::e500)?,
+        subscribers: get_subscriber_stats(&pool.0).await.map_err(utils::e500)?,
+        queue_depth: get_queue_depth(&pool.0).await.map_err(utils::e500)?,
+        issues_with_errors: get_issues_with_errors(&pool.0).await.map_err(utils::e500)?,
+        last_login: session.get_previous_login().map_err(utils::e500)?,
+    };
+    if utils::accepts_json(&req) {
+        return Ok(HttpResponse::Ok().json(dashboard));
+    }
 
-    let mut context = tera::Context::new();
-    context.insert("username", &username);
+    let mut context = tera::Context::from_serialize(&dashboard).map_err(utils::e500)?;
+    context.insert("base_path", &base_path.0);
+    context.insert("csrf_token", &csrf_token(&session).map_err(utils::e500)?);
+    utils::set_flash_messages(&mut context, flash_messages, Level::Info);
     let rendered = tmpl
         .render("admin/dashboard.html", &context)
         .map_err(utils::e500)?;
@@ -31,3 +65,85 @@ pub async fn get_username(pool: &PgPool, user_id: Uuid) -> Result<String, anyhow
         .context("Failed to fetch username.")?;
     Ok(row.username)
 }
+
+/// A newsletter issue whose delivery finished with too many recipients skipped.
+#[derive(serde::Serialize)]
+pub struct IssueWithErrors {
+    title: String,
+    delivered_count: i32,
+    skipped_count: i32,
+}
+
+#[tracing::instrument(name = "Get newsletter issues completed with errors", skip(pool))]
+async fn get_issues_with_errors(pool: &PgPool) -> Result<Vec<IssueWithErrors>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        IssueWithErrors,
+        r#"
+        SELECT title, delivered_count, skipped_count
+        FROM newsletter_issues
+        WHERE status = 'completed_with_errors'
+        ORDER BY published_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to fetch newsletter issues completed with errors.")?;
+    Ok(issues)
+}
+
+/// The number of subscribers in each status.
+#[derive(serde::Serialize)]
+pub struct SubscriberStats {
+    pending_confirmation: i64,
+    confirmed: i64,
+    unsubscribed: i64,
+}
+
+#[tracing::instrument(name = "Get subscriber stats", skip(pool))]
+async fn get_subscriber_stats(pool: &PgPool) -> Result<SubscriberStats, anyhow::Error> {
+    let stats = sqlx::query_as!(
+        SubscriberStats,
+        r#"
+        SELECT
+            count(*) FILTER (WHERE status = 'pending_confirmation') AS "pending_confirmation!",
+            count(*) FILTER (WHERE status = 'confirmed') AS "confirmed!",
+            count(*) FILTER (WHERE status = 'unsubscribed') AS "unsubscribed!"
+        FROM subscriptions
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count subscribers.")?;
+    Ok(stats)
+}
+
+/// The work waiting for the delivery worker.
+///
+/// # Fields
+///
+/// - `ready`: Deliveries the worker can pick up right away.
+/// - `postponed`: Deliveries waiting for a frequency cap or a provider's `Retry-After`.
+/// - `pending_fan_out`: Published issues whose deliveries have not been enqueued yet.
+#[derive(serde::Serialize)]
+pub struct QueueDepth {
+    ready: i64,
+    postponed: i64,
+    pending_fan_out: i64,
+}
+
+#[tracing::instrument(name = "Get delivery queue depth", skip(pool))]
+async fn get_queue_depth(pool: &PgPool) -> Result<QueueDepth, anyhow::Error> {
+    let depth = sqlx::query_as!(
+        QueueDepth,
+        r#"
+        SELECT
+            (SELECT count(*) FROM issue_delivery_queue WHERE execute_after <= now()) AS "ready!",
+            (SELECT count(*) FROM issue_delivery_queue WHERE execute_after > now()) AS "postponed!",
+            (SELECT count(*) FROM issue_fanout_queue) AS "pending_fan_out!"
+        "#
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to measure the delivery queue.")?;
+    Ok(depth)
+}
@@ -0,0 +1,131 @@
+use crate::authentication::verify_csrf_header;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// A delivery the worker gave up on, as returned by the listing endpoint.
+#[derive(serde::Serialize)]
+pub struct DeadLetter {
+    id: Uuid,
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i32,
+    reason: String,
+    dead_lettered_at: DateTime<Utc>,
+}
+
+/// List the dead-lettered deliveries, most recent first.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON array of [DeadLetter].
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "List dead letters", skip(pool))]
+pub async fn list_dead_letters(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
+    let dead_letters = sqlx::query_as!(
+        DeadLetter,
+        r#"
+        SELECT id, newsletter_issue_id, subscriber_email, n_retries, reason, dead_lettered_at
+        FROM dead_letter
+        ORDER BY dead_lettered_at DESC
+        "#
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to fetch the dead letters.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(dead_letters))
+}
+
+/// Move a dead-lettered delivery back into the delivery queue, with its retry count reset.
+///
+/// This is meant for after the cause of the failure has been fixed, e.g. a provider outage.
+/// The issue is marked `in_progress` again until the task has been processed.
+///
+/// # Response
+///
+/// - **200 OK**: The delivery has been requeued.
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **404 Not Found**: No dead letter has this id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Requeue a dead letter", skip(pool, session, request))]
+pub async fn requeue_dead_letter(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let Some(dead_letter) = sqlx::query!(
+        r#"
+        DELETE FROM dead_letter
+        WHERE id = $1
+        RETURNING newsletter_issue_id, subscriber_email
+        "#,
+        id.into_inner()
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .context("Failed to remove the dead letter.")
+    .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    requeue_task(
+        &mut tx,
+        dead_letter.newsletter_issue_id,
+        &dead_letter.subscriber_email,
+    )
+    .await
+    .context("Failed to requeue the delivery task.")
+    .map_err(e500)?;
+    tx.commit()
+        .await
+        .context("Failed to commit SQL transaction to requeue a dead letter.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Enqueues the task again and moves it out of the issue's skipped count,
+/// so that the final status is computed again once it has been processed.
+#[tracing::instrument(skip(tx, email))]
+async fn requeue_task(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        VALUES ($1, $2)
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET n_retries = 0, execute_after = now()
+        "#,
+        newsletter_issue_id,
+        email
+    )
+    .execute(&mut **tx)
+    .await?;
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'in_progress', skipped_count = GREATEST(skipped_count - 1, 0)
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
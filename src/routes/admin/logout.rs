@@ -1,10 +1,32 @@
+use crate::authentication::{csrf_token, verify_csrf_token, CsrfForm};
 use crate::session_state::TypedSession;
-use crate::utils::see_other;
-use actix_web::HttpResponse;
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app, template_context};
+use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
+use tera::Tera;
 
-pub async fn log_out(session: TypedSession) -> Result<HttpResponse, actix_web::Error> {
+/// Ask the user to confirm that they want to log out.
+pub async fn log_out_form(
+    tmpl: web::Data<Tera>,
+    session: TypedSession,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut context = template_context(&base_path);
+    context.insert("csrf_token", &csrf_token(&session).map_err(e500)?);
+
+    tmpl.render("admin/logout.html", &context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(e500)
+}
+
+pub async fn log_out(
+    session: TypedSession,
+    form: web::Form<CsrfForm>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_token(&session, &form.csrf_token)?;
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
-    Ok(see_other("/login"))
+    Ok(see_other_in_app(&base_path, "/login"))
 }
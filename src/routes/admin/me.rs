@@ -0,0 +1,48 @@
+use super::dashboard::get_username;
+use crate::authentication::{csrf_token, get_totp_secret, LastLogin, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+/// The logged-in user, as returned by [current_user].
+#[derive(serde::Serialize)]
+pub struct CurrentUser {
+    user_id: UserId,
+    username: String,
+    two_factor_enabled: bool,
+    /// The login before the current one, if any.
+    last_login: Option<LastLogin>,
+    /// The CSRF token to send in the `X-CSRF-Token` header of admin requests.
+    csrf_token: String,
+}
+
+/// Return the logged-in user, so that a frontend can render the session state.
+///
+/// # Response
+///
+/// - **200 OK**: A [CurrentUser] JSON object.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Get the current user", skip_all, fields(user_id = %*user_id))]
+pub async fn current_user(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let username = get_username(&pool, *user_id).await.map_err(e500)?;
+    let two_factor_enabled = get_totp_secret(&pool, *user_id)
+        .await
+        .map_err(e500)?
+        .is_some();
+    let last_login = session.get_previous_login().map_err(e500)?;
+    let csrf_token = csrf_token(&session).map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(CurrentUser {
+        user_id,
+        username,
+        two_factor_enabled,
+        last_login,
+        csrf_token,
+    }))
+}
@@ -1,4 +1,11 @@
 pub mod dashboard;
+pub mod dead_letters;
 pub mod logout;
+pub mod me;
 pub mod newsletters;
 pub mod password;
+pub mod security;
+pub mod subscribers;
+pub mod system;
+pub mod two_factor;
+pub mod users;
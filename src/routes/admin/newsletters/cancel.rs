@@ -0,0 +1,121 @@
+use crate::authentication::verify_csrf_header;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The summary returned by the cancel endpoint.
+#[derive(serde::Serialize)]
+pub struct CancelSummary {
+    dropped: u64,
+}
+
+/// Stop the delivery of a newsletter issue that is still in progress.
+///
+/// The pending delivery tasks are dropped and the issue is marked `cancelled`.
+/// Emails that have already been sent are left as they are.
+///
+/// # Response
+///
+/// - **200 OK**: The issue has been cancelled. The body is a [CancelSummary]
+///   with the number of deliveries dropped.
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **404 Not Found**: No newsletter issue has this id.
+/// - **409 Conflict**: The issue is not in progress anymore.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Cancel a newsletter issue", skip(pool, session, request))]
+pub async fn cancel_newsletter_issue(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    newsletter_issue_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let Some(status) = get_issue_status(&mut tx, newsletter_issue_id)
+        .await
+        .context("Failed to fetch the newsletter issue.")
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if status != "in_progress" {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    let dropped = drop_pending_deliveries(&mut tx, newsletter_issue_id)
+        .await
+        .context("Failed to drop the pending deliveries.")
+        .map_err(e500)?;
+    // The worker may have completed the issue while its tasks were being deleted.
+    let cancelled = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled'
+        WHERE newsletter_issue_id = $1 AND status = 'in_progress'
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&mut *tx)
+    .await
+    .context("Failed to mark the newsletter issue as cancelled.")
+    .map_err(e500)?;
+    if cancelled.rows_affected() == 0 {
+        return Ok(HttpResponse::Conflict().finish());
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit SQL transaction to cancel a newsletter issue.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(CancelSummary { dropped }))
+}
+
+/// Returns `None` if the issue does not exist.
+///
+/// The issue row is not locked: the worker updates it while holding a task,
+/// so locking it before the tasks are deleted could deadlock with the worker.
+#[tracing::instrument(skip(tx))]
+async fn get_issue_status(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT status FROM newsletter_issues WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(record.map(|r| r.status))
+}
+
+/// Deletes the issue's fan-out and delivery tasks.
+///
+/// Deleting a task the worker is processing waits for the worker to be done with it,
+/// so once this returns, no email of the issue can be sent anymore.
+/// The fan-out task goes first, so that the tasks of a fan-out in progress are deleted as well.
+#[tracing::instrument(skip(tx))]
+async fn drop_pending_deliveries(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM issue_fanout_queue WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    let result = sqlx::query!(
+        "DELETE FROM issue_delivery_queue WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
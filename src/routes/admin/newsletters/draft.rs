@@ -0,0 +1,127 @@
+use crate::authentication::{verify_csrf_token, UserId};
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The form data passed to the save-draft endpoint. Missing fields are saved as empty.
+#[derive(serde::Deserialize)]
+pub struct DraftFormData {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    html_content: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    csrf_token: String,
+}
+
+/// The unpublished issue a user has been working on.
+#[derive(serde::Serialize)]
+pub struct NewsletterDraft {
+    pub title: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Save the content of the publish form as the user's draft, replacing the previous one.
+///
+/// # Response
+///
+/// - **303 See Other**: The draft has been saved. Redirects to the publish form.
+/// - **400 Bad Request**: The CSRF token is missing or invalid.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Save a newsletter draft", skip_all, fields(user_id = %*user_id))]
+pub async fn save_newsletter_draft(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    form: web::Form<DraftFormData>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_token(&session, &form.csrf_token)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_drafts (user_id, title, html_content, text_content, updated_at)
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (user_id) DO UPDATE
+        SET title = EXCLUDED.title,
+            html_content = EXCLUDED.html_content,
+            text_content = EXCLUDED.text_content,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        **user_id,
+        form.title,
+        form.html_content,
+        form.text_content,
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to save the newsletter draft.")
+    .map_err(e500)?;
+
+    FlashMessage::info("The draft has been saved.").send();
+    Ok(see_other_in_app(&base_path, "/admin/newsletters"))
+}
+
+/// Get the user's draft.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON [NewsletterDraft].
+/// - **404 Not Found**: The user has no draft.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Get the newsletter draft", skip_all, fields(user_id = %*user_id))]
+pub async fn get_newsletter_draft(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let draft = load_draft(&pool, **user_id)
+        .await
+        .context("Failed to fetch the newsletter draft.")
+        .map_err(e500)?;
+
+    match draft {
+        Some(draft) => Ok(HttpResponse::Ok().json(draft)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+#[tracing::instrument(name = "Load the newsletter draft", skip(pool))]
+pub(super) async fn load_draft(
+    pool: &PgPool,
+    user_id: Uuid,
+) -> Result<Option<NewsletterDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterDraft,
+        r#"
+        SELECT title, html_content, text_content, updated_at
+        FROM newsletter_drafts
+        WHERE user_id = $1
+        "#,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Deletes the user's draft once it has been published.
+#[tracing::instrument(name = "Clear the newsletter draft", skip(tx))]
+pub(super) async fn clear_draft(
+    tx: &mut Transaction<'_, Postgres>,
+    user_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    tx.execute(sqlx::query!(
+        "DELETE FROM newsletter_drafts WHERE user_id = $1",
+        user_id
+    ))
+    .await?;
+    Ok(())
+}
@@ -1,15 +1,36 @@
-use crate::utils::{e500, set_flash_messages};
-use actix_web::{web, HttpResponse};
+use super::draft::load_draft;
+use super::list_newsletter_issues;
+use crate::authentication::{csrf_token, UserId};
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{accepts_json, e500, set_flash_messages, template_context};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::{IncomingFlashMessages, Level};
+use sqlx::PgPool;
 use tera::Tera;
 
+/// Render the publish form, or list the published issues for clients that accept JSON.
+///
+/// The form is pre-filled with the user's draft, if any.
 pub async fn publish_newsletter_form(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
     tmpl: web::Data<Tera>,
+    user_id: web::ReqData<UserId>,
+    session: TypedSession,
     flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut context = tera::Context::new();
+    if accepts_json(&req) {
+        return list_newsletter_issues(pool).await;
+    }
+
+    let mut context = template_context(&base_path);
     set_flash_messages(&mut context, flash_messages, Level::Info);
+    context.insert("csrf_token", &csrf_token(&session).map_err(e500)?);
     context.insert("idempotency_key", &uuid::Uuid::new_v4().to_string());
+    let draft = load_draft(&pool, **user_id).await.map_err(e500)?;
+    context.insert("draft", &draft);
 
     tmpl.render("admin/newsletter.html", &context)
         .map(|body| HttpResponse::Ok().body(body))
@@ -0,0 +1,86 @@
+use crate::utils::e500;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A newsletter issue as returned by the listing endpoint.
+#[derive(serde::Serialize)]
+pub struct NewsletterIssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    status: String,
+}
+
+/// A stored newsletter issue with its content.
+#[derive(serde::Serialize)]
+pub struct NewsletterIssue {
+    newsletter_issue_id: Uuid,
+    title: String,
+    html_content: String,
+    text_content: String,
+    published_at: DateTime<Utc>,
+    status: String,
+}
+
+/// List the published newsletter issues, most recent first.
+///
+/// This is served from `GET /admin/newsletters` when the client accepts JSON.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON array of [NewsletterIssueSummary].
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "List newsletter issues", skip(pool))]
+pub async fn list_newsletter_issues(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issues = sqlx::query_as!(
+        NewsletterIssueSummary,
+        r#"
+        SELECT newsletter_issue_id, title, published_at, status
+        FROM newsletter_issues
+        ORDER BY published_at DESC
+        "#
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to fetch newsletter issues.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(issues))
+}
+
+/// Get a stored newsletter issue.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON [NewsletterIssue].
+/// - **404 Not Found**: No newsletter issue has this id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Get a newsletter issue", skip(pool))]
+pub async fn get_newsletter_issue(
+    pool: web::Data<PgPool>,
+    newsletter_issue_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT newsletter_issue_id, title, html_content, text_content, published_at, status
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id.into_inner()
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to fetch the newsletter issue.")
+    .map_err(e500)?;
+
+    match issue {
+        Some(issue) => Ok(HttpResponse::Ok().json(issue)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
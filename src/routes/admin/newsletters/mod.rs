@@ -1,5 +1,13 @@
+mod cancel;
+mod draft;
 mod get;
+mod issues;
 mod post;
+mod resend;
 
+pub use cancel::cancel_newsletter_issue;
+pub use draft::{get_newsletter_draft, save_newsletter_draft};
 pub use get::publish_newsletter_form;
+pub use issues::{get_newsletter_issue, list_newsletter_issues};
 pub use post::publish_newsletter;
+pub use resend::resend_failed_deliveries;
@@ -1,55 +1,190 @@
-use crate::authentication::UserId;
-use crate::idempotency::{save_response, try_processing, NextAction};
-use crate::utils::{e400, e500, see_other};
+use super::draft::clear_draft;
+use crate::authentication::{verify_csrf_token, UserId};
+use crate::configuration::NewsletterSettings;
+use crate::domain::{
+    DeliveryPriority, IssueLimits, NewsletterIssue, SubscriberLocale, SubscriberTag,
+};
+use crate::idempotency::{
+    request_fingerprint, save_response, try_processing, IdempotencyKey, NextAction,
+};
+use crate::markdown;
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e400, e500, see_other_in_app};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
+    title: String,
+    #[serde(default)]
+    content_type: ContentType,
+    html_content: Option<String>,
+    text_content: Option<String>,
+    content_markdown: Option<String>,
+    /// Translations of the issue as a JSON object, e.g.
+    /// `{"fr": {"title": "...", "html_content": "...", "text_content": "..."}}`.
+    #[serde(default)]
+    localizations: String,
+    /// When set, the issue is only delivered to subscribers with this tag.
+    #[serde(default)]
+    segment: String,
+    /// Transactional issues are delivered ahead of the bulk ones already queued.
+    #[serde(default)]
+    priority: DeliveryPriority,
+    idempotency_key: String,
+    #[serde(default)]
+    csrf_token: String,
+}
+
+/// The format in which the newsletter content has been submitted.
+#[derive(serde::Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentType {
+    /// `html_content` and `text_content` are stored as they are.
+    #[default]
+    Html,
+    /// `content_markdown` is rendered into both HTML and plain-text bodies.
+    Markdown,
+}
+
+/// A translation of the issue, keyed by its locale in [FormData::localizations].
+#[derive(serde::Deserialize)]
+struct LocalizedContent {
     title: String,
     html_content: String,
     text_content: String,
-    idempotency_key: String,
 }
 
+/// Store a newsletter issue and schedule its delivery.
+///
+/// Subscribers whose locale matches one of the translations receive it instead of the
+/// default content.
+///
+/// The issue, its fan-out marker, and the saved response of the idempotency key are written
+/// in the transaction opened by [try_processing], so they are committed together or not at all:
+/// a failure midway leaves no issue without deliveries, and the key can be retried.
+///
+/// While more than [NewsletterSettings::max_queue_depth] deliveries are queued,
+/// the worker is falling behind and the issue is refused with a `503 Service Unavailable`.
 #[tracing::instrument(name = "Publish a newsletter", skip_all, fields(user_id = %*user_id))]
 pub async fn publish_newsletter(
     pool: web::Data<PgPool>,
     user_id: web::ReqData<UserId>,
+    limits: web::Data<NewsletterSettings>,
+    session: TypedSession,
     form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_token(&session, &form.csrf_token)?;
     let FormData {
         title,
+        content_type,
         text_content,
         html_content,
+        content_markdown,
+        localizations,
+        segment,
+        priority,
         idempotency_key,
+        csrf_token: _,
     } = form.0;
 
-    let idempotency_key = idempotency_key.try_into().map_err(e400)?;
-    let mut tx = match try_processing(&pool, &idempotency_key, &user_id)
-        .await
-        .map_err(e500)?
-    {
+    let (html_content, text_content) = match content_type {
+        ContentType::Html => match (html_content, text_content) {
+            (Some(html_content), Some(text_content)) => (html_content, text_content),
+            _ => {
+                return Err(e400(
+                    "Both `html_content` and `text_content` are required for HTML content.",
+                ))
+            }
+        },
+        ContentType::Markdown => {
+            let content_markdown = content_markdown
+                .ok_or_else(|| e400("`content_markdown` is required for Markdown content."))?;
+            if content_markdown.len() > limits.max_content_length {
+                return Err(e400(format!(
+                    "`content_markdown` must be at most {} bytes long.",
+                    limits.max_content_length
+                )));
+            }
+            let rendered = markdown::render(&content_markdown);
+            (rendered.html, rendered.text)
+        }
+    };
+    let issue = NewsletterIssue::parse(title, html_content, text_content, &limits.issue_limits())
+        .map_err(e400)?;
+    let localizations = parse_localizations(&localizations, &limits.issue_limits())?;
+    let segment = match segment.trim() {
+        "" => None,
+        segment => Some(SubscriberTag::parse(segment.to_owned()).map_err(e400)?),
+    };
+
+    let idempotency_key =
+        IdempotencyKey::parse(idempotency_key, limits.max_idempotency_key_length).map_err(e400)?;
+    let mut fingerprint = vec![
+        issue.title(),
+        issue.html_content(),
+        issue.text_content(),
+        segment.as_ref().map_or("", |s| s.as_ref()),
+        priority.as_str(),
+    ];
+    for (locale, issue) in &localizations {
+        fingerprint.extend([
+            locale.as_ref(),
+            issue.title(),
+            issue.html_content(),
+            issue.text_content(),
+        ]);
+    }
+    let fingerprint = request_fingerprint(&fingerprint);
+    let mut tx = match try_processing(&pool, &idempotency_key, &user_id, &fingerprint).await? {
         NextAction::StartProcessing(tx) => tx,
         NextAction::ReturnSavedResponse(response) => {
             success_message().send();
             return Ok(response);
         }
     };
+    if limits.max_queue_depth > 0 {
+        let depth = queue_depth(&mut tx, limits.max_queue_depth)
+            .await
+            .context("Failed to measure the depth of the delivery queue.")
+            .map_err(e500)?;
+        if depth > limits.max_queue_depth {
+            tracing::warn!(
+                depth,
+                max_queue_depth = limits.max_queue_depth,
+                "The delivery queue is too deep. Refusing to publish."
+            );
+            let message = "Too many emails are waiting to be delivered. \
+                Try publishing the issue again later.";
+            FlashMessage::error(message).send();
+            return Ok(HttpResponse::ServiceUnavailable().body(message));
+        }
+    }
 
-    let issue_id = insert_newsletter_issue(&mut tx, &title, &text_content, &html_content)
+    let issue_id = insert_newsletter_issue(&mut tx, &issue)
         .await
         .context("Failed to store newsletter issue details.")
         .map_err(e500)?;
-    enqueue_delivery_tasks(&mut tx, issue_id)
+    insert_localizations(&mut tx, issue_id, &localizations)
+        .await
+        .context("Failed to store the translations of the newsletter issue.")
+        .map_err(e500)?;
+    schedule_fan_out(&mut tx, issue_id, segment.as_ref(), priority)
+        .await
+        .context("Failed to schedule the fan-out of the newsletter issue.")
+        .map_err(e500)?;
+    clear_draft(&mut tx, **user_id)
         .await
-        .context("Failed to enqueue delivery tasks.")
+        .context("Failed to clear the newsletter draft.")
         .map_err(e500)?;
 
-    let response = see_other("/admin/newsletters");
+    let response = see_other_in_app(&base_path, "/admin/newsletters");
     let response = save_response(tx, &idempotency_key, &user_id, response)
         .await
         .map_err(e500)?;
@@ -61,12 +196,64 @@ fn success_message() -> FlashMessage {
     FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
 }
 
+/// Validates the translations of the issue like the issue itself.
+/// Two locales differing only by case are the same locale and are refused.
+fn parse_localizations(
+    localizations: &str,
+    limits: &IssueLimits,
+) -> Result<Vec<(SubscriberLocale, NewsletterIssue)>, actix_web::Error> {
+    if localizations.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let localizations: BTreeMap<String, LocalizedContent> = serde_json::from_str(localizations)
+        .map_err(|e| e400(format!("`localizations` is not a valid JSON object: {}", e)))?;
+    let mut parsed: Vec<(SubscriberLocale, NewsletterIssue)> = Vec::new();
+    for (locale, content) in localizations {
+        let locale = SubscriberLocale::parse(locale).map_err(e400)?;
+        if parsed
+            .iter()
+            .any(|(other, _)| other.as_ref().eq_ignore_ascii_case(locale.as_ref()))
+        {
+            return Err(e400(format!(
+                "The `{}` translation is given more than once.",
+                locale.as_ref()
+            )));
+        }
+        let issue = NewsletterIssue::parse(
+            content.title,
+            content.html_content,
+            content.text_content,
+            limits,
+        )
+        .map_err(e400)?;
+        parsed.push((locale, issue));
+    }
+    Ok(parsed)
+}
+
+/// Counts the queued deliveries, stopping past `max_queue_depth` so that a deep queue
+/// is not scanned in full.
+#[tracing::instrument(name = "Measure the delivery queue depth", skip(tx))]
+async fn queue_depth(
+    tx: &mut Transaction<'_, Postgres>,
+    max_queue_depth: i64,
+) -> Result<i64, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT count(*) AS "depth!"
+        FROM (SELECT 1 FROM issue_delivery_queue LIMIT $1::bigint + 1) AS queued
+        "#,
+        max_queue_depth
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    Ok(record.depth)
+}
+
 #[tracing::instrument(name = "Store newsletter issue", skip_all)]
 async fn insert_newsletter_issue(
     tx: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    issue: &NewsletterIssue,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
     let query = sqlx::query!(
@@ -76,36 +263,68 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            published_at,
+            status
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, now(), 'in_progress')
         "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        issue.title(),
+        issue.text_content(),
+        issue.html_content()
     );
     tx.execute(query).await?;
 
     Ok(newsletter_issue_id)
 }
 
-#[tracing::instrument(name = "Enqueue delivery tasks", skip_all)]
-async fn enqueue_delivery_tasks(
+#[tracing::instrument(name = "Store newsletter issue translations", skip_all)]
+async fn insert_localizations(
     tx: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
+    localizations: &[(SubscriberLocale, NewsletterIssue)],
+) -> Result<(), sqlx::Error> {
+    for (locale, issue) in localizations {
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issue_localizations (
+                newsletter_issue_id,
+                locale,
+                title,
+                text_content,
+                html_content
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            newsletter_issue_id,
+            locale.as_ref(),
+            issue.title(),
+            issue.text_content(),
+            issue.html_content()
+        );
+        tx.execute(query).await?;
+    }
+
+    Ok(())
+}
+
+/// Leaves the selection of the recipients to the delivery worker,
+/// so that publishing does not depend on the number of subscribers.
+#[tracing::instrument(name = "Schedule newsletter issue fan-out", skip_all)]
+async fn schedule_fan_out(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    segment: Option<&SubscriberTag>,
+    priority: DeliveryPriority,
 ) -> Result<(), sqlx::Error> {
     let query = sqlx::query!(
         r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email
-        )
-        SELECT $1, email
-        FROM subscriptions
-        WHERE status = 'confirmed'
+        INSERT INTO issue_fanout_queue (newsletter_issue_id, segment, priority)
+        VALUES ($1, $2, $3)
         "#,
         newsletter_issue_id,
+        segment.map(AsRef::as_ref),
+        priority as DeliveryPriority,
     );
     tx.execute(query).await?;
 
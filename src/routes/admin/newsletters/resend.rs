@@ -0,0 +1,137 @@
+use crate::authentication::verify_csrf_header;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The summary returned by the resend-failed endpoint.
+#[derive(serde::Serialize)]
+pub struct ResendSummary {
+    enqueued: u64,
+}
+
+/// Re-enqueue the deliveries of a newsletter issue whose last receipt is `failed`.
+///
+/// The new delivery tasks reuse the same `newsletter_issue_id`,
+/// and subscribers who have already received the issue are left untouched.
+/// The issue is marked `in_progress` again until the new tasks have been processed,
+/// and its dead letters are cleared.
+///
+/// # Response
+///
+/// - **200 OK**: The failed deliveries have been enqueued. The body is a [ResendSummary].
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **404 Not Found**: No newsletter issue has this id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(
+    name = "Resend failed newsletter deliveries",
+    skip(pool, session, request)
+)]
+pub async fn resend_failed_deliveries(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    newsletter_issue_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    if !lock_issue(&mut tx, newsletter_issue_id)
+        .await
+        .context("Failed to fetch the newsletter issue.")
+        .map_err(e500)?
+    {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let enqueued = enqueue_failed_deliveries(&mut tx, newsletter_issue_id)
+        .await
+        .context("Failed to enqueue the failed deliveries.")
+        .map_err(e500)?;
+    if enqueued > 0 {
+        reopen_issue(&mut tx, newsletter_issue_id, enqueued)
+            .await
+            .context("Failed to reopen the newsletter issue.")
+            .map_err(e500)?;
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit SQL transaction to resend failed deliveries.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(ResendSummary { enqueued }))
+}
+
+/// Returns `false` if the issue does not exist.
+#[tracing::instrument(skip(tx))]
+async fn lock_issue(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    Ok(record.is_some())
+}
+
+#[tracing::instrument(skip(tx))]
+async fn enqueue_failed_deliveries(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        SELECT newsletter_issue_id, subscriber_email
+        FROM issue_delivery_receipts
+        WHERE newsletter_issue_id = $1 AND status = 'failed'
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO NOTHING
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    // Dead-lettered deliveries have a `failed` receipt, so they have just been enqueued again.
+    sqlx::query!(
+        "DELETE FROM dead_letter WHERE newsletter_issue_id = $1",
+        newsletter_issue_id
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Moves the re-enqueued deliveries out of the skipped count,
+/// so that the final status is computed again once they have been processed.
+#[tracing::instrument(skip(tx))]
+async fn reopen_issue(
+    tx: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    enqueued: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'in_progress', skipped_count = GREATEST(skipped_count - $2, 0)
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        enqueued as i32
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
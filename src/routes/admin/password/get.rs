@@ -1,14 +1,20 @@
-use crate::utils::{e500, set_flash_messages};
+use crate::authentication::csrf_token;
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e500, set_flash_messages, template_context};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::{IncomingFlashMessages, Level};
-use tera::{Context, Tera};
+use tera::Tera;
 
 pub async fn change_password_form(
     tmpl: web::Data<Tera>,
+    session: TypedSession,
     flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut context = Context::new();
+    let mut context = template_context(&base_path);
     set_flash_messages(&mut context, flash_messages, Level::Error);
+    context.insert("csrf_token", &csrf_token(&session).map_err(e500)?);
 
     tmpl.render("admin/password.html", &context)
         .map(|body| HttpResponse::Ok().body(body))
@@ -1,7 +1,11 @@
-use crate::authentication::{validate_credentials, AuthError, Credentials, UserId};
+use crate::authentication::{
+    validate_credentials, validate_new_password, verify_csrf_token, AuthError, Credentials, UserId,
+};
+use crate::configuration::PasswordPolicySettings;
 use crate::routes::admin::dashboard::get_username;
 use crate::session_state::TypedSession;
-use crate::utils::{e500, see_other};
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::{ExposeSecret, Secret};
@@ -12,27 +16,31 @@ pub struct FormData {
     current_password: Secret<String>,
     new_password: Secret<String>,
     new_password_confirm: Secret<String>,
+    #[serde(default)]
+    csrf_token: String,
 }
 
 pub async fn change_password(
     pool: web::Data<PgPool>,
     session: TypedSession,
+    password_policy: web::Data<PasswordPolicySettings>,
     user_id: web::ReqData<UserId>,
     form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_token(&session, &form.csrf_token)?;
     let user_id = user_id.into_inner();
 
+    // Every failure is reported in a single flash message,
+    // so that the cookie holding the flash messages stays small.
+    let mut errors = Vec::new();
     if form.new_password.expose_secret() != form.new_password_confirm.expose_secret() {
-        FlashMessage::error(
-            "You entered two different new passwords - the field values must match.",
-        )
-        .send();
-        return Ok(see_other("/admin/password"));
+        errors.push(
+            "You entered two different new passwords - the field values must match.".to_owned(),
+        );
     }
-
-    if let Err(e) = validate_new_password(form.new_password.clone()) {
-        FlashMessage::error(e.to_string()).send();
-        return Ok(see_other("/admin/password"));
+    if let Err(e) = validate_new_password(&password_policy, form.new_password.clone()) {
+        errors.push(e.to_string());
     }
 
     let username = get_username(&pool, *user_id).await.map_err(e500)?;
@@ -40,14 +48,17 @@ pub async fn change_password(
         username,
         password: form.current_password.clone(),
     };
-    if let Err(e) = validate_credentials(&pool, credentials).await {
-        return match e {
-            AuthError::InvalidCredentials(_) => {
-                FlashMessage::error("The current password is incorrect.").send();
-                Ok(see_other("/admin/password"))
-            }
-            AuthError::UnexpectedError(_) => Err(e500(e)),
-        };
+    match validate_credentials(&pool, credentials).await {
+        Ok(_) => {}
+        Err(AuthError::InvalidCredentials(_)) => {
+            errors.push("The current password is incorrect.".to_owned())
+        }
+        Err(e @ AuthError::UnexpectedError(_)) => return Err(e500(e)),
+    }
+
+    if !errors.is_empty() {
+        FlashMessage::error(errors.join(" ")).send();
+        return Ok(see_other_in_app(&base_path, "/admin/password"));
     }
 
     crate::authentication::change_password(&pool, *user_id, form.new_password.clone())
@@ -56,19 +67,5 @@ pub async fn change_password(
 
     session.log_out();
     FlashMessage::info("Your password has been changed successfully.").send();
-    Ok(see_other("/login"))
-}
-
-fn validate_new_password(new_password: Secret<String>) -> Result<(), anyhow::Error> {
-    if new_password.expose_secret().len() < 12 {
-        return Err(anyhow::anyhow!(
-            "The new password must be at least 12 characters long."
-        ));
-    }
-    if new_password.expose_secret().len() > 128 {
-        return Err(anyhow::anyhow!(
-            "The new password must be at most 128 characters long."
-        ));
-    }
-    Ok(())
+    Ok(see_other_in_app(&base_path, "/login"))
 }
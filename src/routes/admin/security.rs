@@ -0,0 +1,44 @@
+use crate::authentication::verify_csrf_header;
+use crate::session_state::TypedSession;
+use crate::utils::e500;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+/// The report returned by the rehash endpoint.
+#[derive(serde::Serialize)]
+pub struct RehashReport {
+    /// How many users will have their password hashed again on their next login.
+    flagged: u64,
+}
+
+/// Flag every user for a forced re-hash of their password on their next login.
+///
+/// Passwords are not available outside of a login, so hashes cannot be upgraded right away
+/// after the hashing parameters have changed. Each one is replaced once its user logs in.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON [RehashReport].
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(
+    name = "Flag all users for a password re-hash",
+    skip(pool, session, request)
+)]
+pub async fn rehash_all_passwords(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let result = sqlx::query!("UPDATE users SET needs_rehash = true")
+        .execute(pool.as_ref())
+        .await
+        .context("Failed to flag the users for a password re-hash.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(RehashReport {
+        flagged: result.rows_affected(),
+    }))
+}
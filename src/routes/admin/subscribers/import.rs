@@ -0,0 +1,194 @@
+use crate::authentication::verify_csrf_header;
+use crate::configuration::SubscriptionSettings;
+use crate::confirmation_outbox::enqueue_confirmation_email;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::routes::subscriptions::issue_confirmation_token;
+use crate::session_state::TypedSession;
+use crate::startup::HmacSecret;
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// The JSON body passed to the import endpoint.
+#[derive(serde::Deserialize)]
+pub struct ImportData {
+    subscribers: Vec<ImportedSubscriber>,
+}
+
+/// A subscriber to be imported.
+///
+/// # Fields
+///
+/// - `email`: The email address of the subscriber.
+/// - `name`: The name of the subscriber.
+/// - `pre_verified`: Whether the subscriber has already opted in elsewhere.
+/// - `consented_at`: When the subscriber originally gave consent.
+///   Required for pre-verified subscribers.
+#[derive(serde::Deserialize)]
+pub struct ImportedSubscriber {
+    email: String,
+    name: String,
+    #[serde(default)]
+    pre_verified: bool,
+    consented_at: Option<DateTime<Utc>>,
+}
+
+/// How an imported subscriber enters the subscriptions table.
+pub(super) enum Provenance {
+    /// Confirmed right away, keeping the time of the original consent.
+    PreVerified { consented_at: DateTime<Utc> },
+    /// Left pending until the subscriber follows the confirmation link.
+    NeedsConfirmation,
+}
+
+/// The summary returned by the import endpoint.
+#[derive(serde::Serialize)]
+pub struct ImportSummary {
+    confirmed: usize,
+    pending_confirmation: usize,
+    skipped: usize,
+}
+
+/// Import a list of subscribers.
+///
+/// Pre-verified subscribers are stored as `confirmed` with `confirmed_source = 'import'`
+/// and their original consent timestamp, without sending a confirmation email.
+/// Every other subscriber goes through the usual confirmation flow: their confirmation
+/// emails are handed over to the outbox, so that a large import does not wait on them.
+/// Email addresses that are already subscribed are skipped.
+///
+/// # Response
+///
+/// - **200 OK**: The subscribers have been imported. The body is an [ImportSummary].
+/// - **400 Bad Request**: An entry is malformed, or a pre-verified entry lacks `consented_at`.
+///   Also returned when the `X-CSRF-Token` header is missing or invalid.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(
+    name = "Import subscribers",
+    skip_all,
+    fields(count = body.subscribers.len())
+)]
+pub async fn import_subscribers(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    settings: web::Data<SubscriptionSettings>,
+    hmac_secret: web::Data<HmacSecret>,
+    body: web::Json<ImportData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let entries = body
+        .0
+        .subscribers
+        .into_iter()
+        .map(parse_entry)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    let mut summary = ImportSummary {
+        confirmed: 0,
+        pending_confirmation: 0,
+        skipped: 0,
+    };
+    for (new_subscriber, provenance) in entries {
+        let Some(subscriber_id) = insert_imported_subscriber(&mut tx, &new_subscriber, &provenance)
+            .await
+            .context("Failed to insert an imported subscriber into the database.")
+            .map_err(e500)?
+        else {
+            summary.skipped += 1;
+            continue;
+        };
+        match provenance {
+            Provenance::PreVerified { .. } => summary.confirmed += 1,
+            Provenance::NeedsConfirmation => {
+                let subscription_token =
+                    issue_confirmation_token(&mut tx, &subscriber_id, &settings, &hmac_secret.0)
+                        .await
+                        .context(
+                            "Failed to store the confirmation token for an imported subscriber.",
+                        )
+                        .map_err(e500)?;
+                enqueue_confirmation_email(&mut tx, subscriber_id, &subscription_token)
+                    .await
+                    .context("Failed to enqueue the confirmation email of an imported subscriber.")
+                    .map_err(e500)?;
+                summary.pending_confirmation += 1;
+            }
+        }
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit SQL transaction to store imported subscribers.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(summary))
+}
+
+fn parse_entry(entry: ImportedSubscriber) -> Result<(NewSubscriber, Provenance), actix_web::Error> {
+    let email = SubscriberEmail::parse(entry.email).map_err(e400)?;
+    let name = SubscriberName::parse(entry.name).map_err(e400)?;
+    let provenance = match (entry.pre_verified, entry.consented_at) {
+        (true, Some(consented_at)) => Provenance::PreVerified { consented_at },
+        (true, None) => {
+            return Err(e400(format!(
+                "`consented_at` is required for the pre-verified subscriber {}.",
+                email.as_ref()
+            )))
+        }
+        (false, _) => Provenance::NeedsConfirmation,
+    };
+    Ok((
+        NewSubscriber {
+            email,
+            name,
+            tags: Vec::new(),
+            locale: None,
+            timezone: None,
+        },
+        provenance,
+    ))
+}
+
+/// Returns `None` if the email address is already subscribed.
+#[tracing::instrument(name = "Saving imported subscriber details in the database", skip_all)]
+pub(super) async fn insert_imported_subscriber(
+    tx: &mut Transaction<'_, Postgres>,
+    new_subscriber: &NewSubscriber,
+    provenance: &Provenance,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let (status, confirmed_source, consented_at) = match provenance {
+        Provenance::PreVerified { consented_at } => (
+            SubscriptionStatus::Confirmed,
+            Some("import"),
+            Some(*consented_at),
+        ),
+        Provenance::NeedsConfirmation => (SubscriptionStatus::PendingConfirmation, None, None),
+    };
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (
+            id, email, name, subscribed_at, status, confirmed_source, consented_at
+        )
+        VALUES ($1, $2, $3, now(), $4, $5, $6)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        new_subscriber.email.as_ref(),
+        new_subscriber.name.as_ref(),
+        status as SubscriptionStatus,
+        confirmed_source,
+        consented_at,
+    );
+    let record = query.fetch_optional(&mut **tx).await?;
+
+    Ok(record.map(|r| r.id))
+}
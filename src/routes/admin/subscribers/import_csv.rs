@@ -0,0 +1,217 @@
+use super::import::{insert_imported_subscriber, Provenance};
+use crate::authentication::verify_csrf_header;
+use crate::configuration::SubscriptionSettings;
+use crate::confirmation_outbox::enqueue_confirmation_email;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::routes::subscriptions::issue_confirmation_token;
+use crate::session_state::TypedSession;
+use crate::startup::HmacSecret;
+use crate::utils::e500;
+use actix_web::guard::GuardContext;
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::PgPool;
+
+/// The query parameters for the CSV import endpoint.
+///
+/// # Fields
+///
+/// - `pending`: Leave the subscribers pending and send them a confirmation email
+///   through the outbox, instead of confirming them right away.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    #[serde(default)]
+    pending: bool,
+}
+
+/// The report returned by the CSV import endpoint.
+#[derive(serde::Serialize, Default)]
+pub struct CsvImportReport {
+    inserted: usize,
+    skipped: usize,
+    invalid: usize,
+    errors: Vec<RowError>,
+}
+
+/// Why a row of the CSV file was not imported.
+#[derive(serde::Serialize, Debug)]
+pub struct RowError {
+    line: u64,
+    reason: String,
+}
+
+/// Whether the request body is a CSV file, so that the import is routed to [import_subscribers_csv].
+pub fn is_csv(ctx: &GuardContext) -> bool {
+    ctx.header::<ContentType>()
+        .is_some_and(|content_type| content_type.essence_str() == "text/csv")
+}
+
+/// Import subscribers from a CSV file with `email,name` rows. A header row is optional.
+///
+/// Subscribers are confirmed with `confirmed_source = 'import'`, taking the import time
+/// as the time of consent, unless `pending` is set. Invalid rows are reported
+/// and do not prevent the other rows from being imported.
+/// Email addresses that are already subscribed are skipped.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a [CsvImportReport].
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Import subscribers from CSV", skip_all)]
+pub async fn import_subscribers_csv(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    settings: web::Data<SubscriptionSettings>,
+    hmac_secret: web::Data<HmacSecret>,
+    parameters: web::Query<Parameters>,
+    body: web::Bytes,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let mut report = CsvImportReport::default();
+    let mut new_subscribers = Vec::new();
+    for row in parse_rows(&body) {
+        match row {
+            Ok(new_subscriber) => new_subscribers.push(new_subscriber),
+            Err(error) => {
+                report.invalid += 1;
+                report.errors.push(error);
+            }
+        }
+    }
+    let provenance = if parameters.pending {
+        Provenance::NeedsConfirmation
+    } else {
+        Provenance::PreVerified {
+            consented_at: Utc::now(),
+        }
+    };
+
+    let mut tx = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")
+        .map_err(e500)?;
+    for new_subscriber in new_subscribers {
+        let Some(subscriber_id) = insert_imported_subscriber(&mut tx, &new_subscriber, &provenance)
+            .await
+            .context("Failed to insert an imported subscriber into the database.")
+            .map_err(e500)?
+        else {
+            report.skipped += 1;
+            continue;
+        };
+        report.inserted += 1;
+        if let Provenance::NeedsConfirmation = provenance {
+            let subscription_token =
+                issue_confirmation_token(&mut tx, &subscriber_id, &settings, &hmac_secret.0)
+                    .await
+                    .context("Failed to store the confirmation token for an imported subscriber.")
+                    .map_err(e500)?;
+            enqueue_confirmation_email(&mut tx, subscriber_id, &subscription_token)
+                .await
+                .context("Failed to enqueue the confirmation email of an imported subscriber.")
+                .map_err(e500)?;
+        }
+    }
+    tx.commit()
+        .await
+        .context("Failed to commit SQL transaction to store imported subscribers.")
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Parses every row of the file, skipping blank lines and an `email,name` header row.
+fn parse_rows(body: &[u8]) -> Vec<Result<NewSubscriber, RowError>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(body);
+    reader
+        .records()
+        .enumerate()
+        .filter_map(|(index, record)| {
+            let line = record
+                .as_ref()
+                .ok()
+                .and_then(|r| r.position())
+                .map_or(index as u64 + 1, |p| p.line());
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    return Some(Err(RowError {
+                        line,
+                        reason: e.to_string(),
+                    }))
+                }
+            };
+            if index == 0 && is_header(&record) {
+                return None;
+            }
+            Some(parse_record(&record).map_err(|reason| RowError { line, reason }))
+        })
+        .collect()
+}
+
+fn is_header(record: &csv::StringRecord) -> bool {
+    record.len() == 2
+        && record[0].eq_ignore_ascii_case("email")
+        && record[1].eq_ignore_ascii_case("name")
+}
+
+fn parse_record(record: &csv::StringRecord) -> Result<NewSubscriber, String> {
+    if record.len() != 2 {
+        return Err(format!(
+            "Expected 2 columns (`email,name`), found {}.",
+            record.len()
+        ));
+    }
+    let email = SubscriberEmail::parse(record[0].to_owned()).map_err(|e| e.to_string())?;
+    let name = SubscriberName::parse(record[1].to_owned()).map_err(|e| e.to_string())?;
+    Ok(NewSubscriber {
+        email,
+        name,
+        tags: Vec::new(),
+        locale: None,
+        timezone: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_header_row_is_skipped() {
+        let rows = parse_rows(b"email,name\nursula@example.com,Ursula Le Guin\n");
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].as_ref().unwrap().email.as_ref(),
+            "ursula@example.com"
+        );
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_with_their_line() {
+        let rows = parse_rows(
+            b"ursula@example.com,Ursula Le Guin\nnot-an-email,Someone\nonly-one-column\n",
+        );
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].is_ok());
+        let errors: Vec<_> = rows.iter().filter_map(|r| r.as_ref().err()).collect();
+        assert_eq!(errors[0].line, 2);
+        assert_eq!(errors[1].line, 3);
+        assert!(errors[1].reason.contains("2 columns"));
+    }
+
+    #[test]
+    fn quoted_names_can_contain_commas() {
+        let rows = parse_rows(b"tolkien@example.com,\"Tolkien, John\"\n");
+        assert_eq!(rows[0].as_ref().unwrap().name.as_ref(), "Tolkien, John");
+    }
+}
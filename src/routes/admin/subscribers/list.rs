@@ -0,0 +1,149 @@
+use crate::domain::SubscriptionStatus;
+use crate::startup::ReadReplicaPool;
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 100;
+
+/// The query parameters for the subscriber listing endpoint.
+///
+/// # Fields
+///
+/// - `search`: Only list subscribers whose email or name contains this text, ignoring case.
+/// - `page`: The page to return, starting from 1.
+/// - `per_page`: The number of subscribers per page, at most 100.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    #[serde(default)]
+    search: String,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+/// A subscriber as returned by the listing endpoint.
+#[derive(serde::Serialize)]
+pub struct SubscriberSummary {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: SubscriptionStatus,
+    subscribed_at: DateTime<Utc>,
+}
+
+/// A page of subscribers.
+#[derive(serde::Serialize)]
+pub struct SubscriberPage {
+    subscribers: Vec<SubscriberSummary>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+/// List subscribers, most recent first, optionally filtered by a search term.
+///
+/// An empty search term lists every subscriber.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a [SubscriberPage].
+/// - **400 Bad Request**: `page` or `per_page` is out of range.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "List subscribers", skip(pool, parameters))]
+pub async fn list_subscribers(
+    pool: web::Data<ReadReplicaPool>,
+    parameters: web::Query<Parameters>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let page = parameters.page.unwrap_or(1);
+    if page < 1 {
+        return Err(e400("`page` must be at least 1."));
+    }
+    let per_page = parameters.per_page.unwrap_or(DEFAULT_PER_PAGE);
+    if !(1..=MAX_PER_PAGE).contains(&per_page) {
+        return Err(e400(format!(
+            "`per_page` must be between 1 and {}.",
+            MAX_PER_PAGE
+        )));
+    }
+    let pattern = search_pattern(&parameters.search);
+
+    let total = sqlx::query!(
+        r#"
+        SELECT count(*) AS "count!"
+        FROM subscriptions
+        WHERE $1::text IS NULL OR email ILIKE $1 OR name ILIKE $1
+        "#,
+        pattern
+    )
+    .fetch_one(&pool.0)
+    .await
+    .context("Failed to count subscribers.")
+    .map_err(e500)?
+    .count;
+    let subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"
+        SELECT id, email, name, status AS "status: SubscriptionStatus", subscribed_at
+        FROM subscriptions
+        WHERE $1::text IS NULL OR email ILIKE $1 OR name ILIKE $1
+        ORDER BY subscribed_at DESC, id
+        LIMIT $2 OFFSET $3
+        "#,
+        pattern,
+        per_page,
+        (page - 1) * per_page
+    )
+    .fetch_all(&pool.0)
+    .await
+    .context("Failed to fetch subscribers.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(SubscriberPage {
+        subscribers,
+        page,
+        per_page,
+        total,
+    }))
+}
+
+/// The `ILIKE` pattern matching values that contain `search`, or `None` to match everything.
+///
+/// `%`, `_` and `\` are escaped, so that they only match themselves.
+fn search_pattern(search: &str) -> Option<String> {
+    let search = search.trim();
+    if search.is_empty() {
+        return None;
+    }
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    Some(format!("%{}%", escaped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::search_pattern;
+
+    #[test]
+    fn an_empty_search_matches_everything() {
+        assert_eq!(search_pattern(""), None);
+        assert_eq!(search_pattern("   "), None);
+    }
+
+    #[test]
+    fn the_search_term_can_appear_anywhere() {
+        assert_eq!(search_pattern(" ursula "), Some("%ursula%".into()));
+    }
+
+    #[test]
+    fn wildcards_are_escaped() {
+        assert_eq!(
+            search_pattern("50%_off\\"),
+            Some("%50\\%\\_off\\\\%".into())
+        );
+    }
+}
@@ -0,0 +1,11 @@
+mod import;
+mod import_csv;
+mod list;
+mod status;
+mod update;
+
+pub use import::import_subscribers;
+pub use import_csv::{import_subscribers_csv, is_csv};
+pub use list::list_subscribers;
+pub use status::subscriber_status;
+pub use update::update_subscriber;
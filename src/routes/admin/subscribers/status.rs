@@ -0,0 +1,55 @@
+use crate::domain::{SubscriberEmail, SubscriptionStatus};
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+/// The query parameters for the subscriber status endpoint.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    email: String,
+}
+
+/// The JSON body returned by the subscriber status endpoint.
+#[derive(serde::Serialize)]
+pub struct SubscriberStatus {
+    email: String,
+    status: SubscriptionStatus,
+}
+
+/// Look up the status of a subscriber by email address.
+///
+/// The lookup is case-insensitive, so `Ursula@Example.com` finds `ursula@example.com`.
+///
+/// # Response
+///
+/// - **200 OK**: The subscriber exists. The body is a [SubscriberStatus].
+/// - **400 Bad Request**: The email address is invalid.
+/// - **404 Not Found**: No subscriber has this email address.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Get subscriber status", skip(pool, parameters))]
+pub async fn subscriber_status(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let email = SubscriberEmail::parse(parameters.0.email.trim().to_owned()).map_err(e400)?;
+
+    let record = sqlx::query_as!(
+        SubscriberStatus,
+        r#"
+        SELECT email, status AS "status: SubscriptionStatus"
+        FROM subscriptions
+        WHERE lower(email) = lower($1)
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to look up the subscriber.")
+    .map_err(e500)?;
+
+    match record {
+        Some(status) => Ok(HttpResponse::Ok().json(status)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
@@ -0,0 +1,103 @@
+use crate::authentication::verify_csrf_header;
+use crate::domain::{SubscriberName, SubscriptionStatus};
+use crate::session_state::TypedSession;
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The statuses an admin can set on a subscriber.
+const ALLOWED_STATUSES: [SubscriptionStatus; 2] = [
+    SubscriptionStatus::PendingConfirmation,
+    SubscriptionStatus::Confirmed,
+];
+
+/// The JSON body passed to the update endpoint. Omitted fields are left unchanged.
+///
+/// # Fields
+///
+/// - `name`: The corrected name of the subscriber.
+/// - `status`: Either `pending_confirmation` or `confirmed`.
+#[derive(serde::Deserialize)]
+pub struct UpdateData {
+    name: Option<String>,
+    status: Option<String>,
+}
+
+/// The JSON body returned by the update endpoint.
+#[derive(serde::Serialize)]
+pub struct UpdatedSubscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    status: SubscriptionStatus,
+}
+
+/// Edit the name or status of a subscriber.
+///
+/// Confirming a subscriber this way records `confirmed_source = 'admin'`.
+/// Moving a subscriber back to `pending_confirmation` restarts the pending sweeper's TTL.
+///
+/// # Response
+///
+/// - **200 OK**: The subscriber has been updated. The body is an [UpdatedSubscriber].
+/// - **400 Bad Request**: The name or the status is invalid.
+///   Also returned when the `X-CSRF-Token` header is missing or invalid.
+/// - **404 Not Found**: No subscriber has this id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Update a subscriber", skip(pool, session, request, body))]
+pub async fn update_subscriber(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    subscriber_id: web::Path<Uuid>,
+    body: web::Json<UpdateData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let UpdateData { name, status } = body.0;
+    let name = name.map(SubscriberName::parse).transpose().map_err(e400)?;
+    let status = status.map(parse_allowed_status).transpose()?;
+
+    let record = sqlx::query_as!(
+        UpdatedSubscriber,
+        r#"
+        UPDATE subscriptions
+        SET name = COALESCE($2, name),
+            confirmed_source = CASE
+                WHEN $3::subscription_status = 'confirmed' AND status <> 'confirmed' THEN 'admin'
+                WHEN $3::subscription_status = 'pending_confirmation' THEN NULL
+                ELSE confirmed_source
+            END,
+            last_confirmation_sent_at = CASE
+                WHEN $3::subscription_status = 'pending_confirmation' THEN now()
+                ELSE last_confirmation_sent_at
+            END,
+            status = COALESCE($3, status)
+        WHERE id = $1
+        RETURNING id, email, name, status AS "status: SubscriptionStatus"
+        "#,
+        *subscriber_id,
+        name.as_ref().map(AsRef::<str>::as_ref),
+        status as Option<SubscriptionStatus>,
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to update the subscriber.")
+    .map_err(e500)?;
+
+    match record {
+        Some(subscriber) => Ok(HttpResponse::Ok().json(subscriber)),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+fn parse_allowed_status(status: String) -> Result<SubscriptionStatus, actix_web::Error> {
+    ALLOWED_STATUSES
+        .into_iter()
+        .find(|allowed| allowed.as_str() == status)
+        .ok_or_else(|| {
+            let allowed: Vec<&str> = ALLOWED_STATUSES.iter().map(|s| s.as_str()).collect();
+            e400(format!("`status` must be one of {}.", allowed.join(", ")))
+        })
+}
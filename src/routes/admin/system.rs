@@ -0,0 +1,65 @@
+use crate::authentication::verify_csrf_header;
+use crate::configuration::WorkerSettings;
+use crate::issue_delivery_worker::{drain_queue, WorkerState};
+use crate::routes::UnsubscribeLinks;
+use crate::session_state::TypedSession;
+use crate::startup::NewsletterEmailClient;
+use crate::utils::e500;
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+/// Reports the current state of the issue delivery worker.
+///
+/// # Response
+///
+/// - **200 OK**: A JSON object with the worker's activity (`idle` or `working`),
+///   the time of the last completed task, the last error and the number of tasks
+///   processed since boot.
+pub async fn worker_status(state: web::Data<WorkerState>) -> HttpResponse {
+    HttpResponse::Ok().json(state.status())
+}
+
+/// The summary returned by the dispatch endpoint.
+#[derive(serde::Serialize)]
+pub struct DispatchSummary {
+    processed: u64,
+}
+
+/// Processes the delivery queue until it is empty, as the background worker would.
+///
+/// This is meant for when the worker is down. The queue is drained by
+/// [WorkerSettings::concurrency] loops, deliveries are paced with
+/// [WorkerSettings::max_emails_per_second], and every task is reported to the worker state.
+///
+/// # Response
+///
+/// - **200 OK**: The queue is empty. The body is a [DispatchSummary] with the number of
+///   tasks processed, including the fan-out of published issues to their recipients.
+/// - **400 Bad Request**: The `X-CSRF-Token` header is missing or invalid.
+/// - **500 Internal Server Error**: A task failed. The tasks processed before it are kept.
+#[tracing::instrument(name = "Dispatch pending tasks", skip_all, fields(processed))]
+pub async fn dispatch_pending_tasks(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    email_client: web::Data<NewsletterEmailClient>,
+    settings: web::Data<WorkerSettings>,
+    unsubscribe_links: web::Data<UnsubscribeLinks>,
+    state: web::Data<WorkerState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let processed = drain_queue(
+        &pool,
+        &email_client.0,
+        &settings,
+        &unsubscribe_links,
+        &state,
+    )
+    .await
+    .context("Failed to execute a delivery task.")
+    .map_err(e500)?;
+    tracing::Span::current().record("processed", processed);
+
+    Ok(HttpResponse::Ok().json(DispatchSummary { processed }))
+}
@@ -0,0 +1,45 @@
+use crate::authentication::{csrf_token, generate_totp_secret, totp_uri, UserId};
+use crate::routes::admin::dashboard::get_username;
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e500, set_flash_messages, template_context};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{IncomingFlashMessages, Level};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use tera::Tera;
+
+pub async fn two_factor_setup_form(
+    pool: web::Data<PgPool>,
+    tmpl: web::Data<Tera>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let username = get_username(&pool, *user_id).await.map_err(e500)?;
+
+    // Keep showing the same secret until the setup is confirmed with a valid code.
+    let secret = match session.get_totp_setup_secret().map_err(e500)? {
+        Some(secret) => Secret::new(secret),
+        None => {
+            let secret = generate_totp_secret();
+            session
+                .insert_totp_setup_secret(secret.expose_secret())
+                .map_err(e500)?;
+            secret
+        }
+    };
+    let otpauth_uri = totp_uri(&secret, &username).map_err(e500)?;
+
+    let mut context = template_context(&base_path);
+    set_flash_messages(&mut context, flash_messages, Level::Info);
+    context.insert("secret", secret.expose_secret());
+    context.insert("otpauth_uri", &otpauth_uri);
+    context.insert("csrf_token", &csrf_token(&session).map_err(e500)?);
+
+    tmpl.render("admin/two_factor_setup.html", &context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(e500)
+}
@@ -0,0 +1,44 @@
+use crate::authentication::{enable_totp, verify_csrf_token, verify_totp_code, UserId};
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app};
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::Secret;
+use sqlx::PgPool;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+    #[serde(default)]
+    csrf_token: String,
+}
+
+#[tracing::instrument(name = "Enable two-factor authentication", skip_all, fields(user_id = %*user_id))]
+pub async fn enable_two_factor(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    user_id: web::ReqData<UserId>,
+    form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_token(&session, &form.csrf_token)?;
+    let user_id = user_id.into_inner();
+
+    let Some(secret) = session.get_totp_setup_secret().map_err(e500)? else {
+        FlashMessage::error("The two-factor setup has expired. Please try again.").send();
+        return Ok(see_other_in_app(&base_path, "/admin/2fa/setup"));
+    };
+    let secret = Secret::new(secret);
+
+    if !verify_totp_code(&secret, &form.code).map_err(e500)? {
+        FlashMessage::error("The authentication code is invalid.").send();
+        return Ok(see_other_in_app(&base_path, "/admin/2fa/setup"));
+    }
+
+    enable_totp(&pool, *user_id, secret).await.map_err(e500)?;
+    session.remove_totp_setup_secret();
+
+    FlashMessage::info("Two-factor authentication has been enabled.").send();
+    Ok(see_other_in_app(&base_path, "/admin/dashboard"))
+}
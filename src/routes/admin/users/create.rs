@@ -0,0 +1,62 @@
+use crate::authentication::{validate_new_password, verify_csrf_header};
+use crate::configuration::PasswordPolicySettings;
+use crate::session_state::TypedSession;
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpRequest, HttpResponse};
+use secrecy::Secret;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const MAX_USERNAME_LENGTH: usize = 64;
+
+/// The JSON body passed to the user creation endpoint.
+#[derive(serde::Deserialize)]
+pub struct NewUserData {
+    username: String,
+    password: Secret<String>,
+}
+
+/// The JSON body returned by the user creation endpoint.
+#[derive(serde::Serialize)]
+pub struct CreatedUser {
+    user_id: Uuid,
+    username: String,
+}
+
+/// Create a new admin user.
+///
+/// # Response
+///
+/// - **201 Created**: The user has been created. The body is a [CreatedUser].
+/// - **400 Bad Request**: The username is empty or too long, or the password breaks the password policy.
+///   Also returned when the `X-CSRF-Token` header is missing or invalid.
+/// - **409 Conflict**: The username is already taken.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Create a user", skip(pool, session, request, password_policy, body), fields(username = %body.username))]
+pub async fn create_user(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    password_policy: web::Data<PasswordPolicySettings>,
+    body: web::Json<NewUserData>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let NewUserData { username, password } = body.0;
+    let username = username.trim().to_owned();
+    if username.is_empty() || username.chars().count() > MAX_USERNAME_LENGTH {
+        return Err(e400(format!(
+            "The username must be between 1 and {} characters long.",
+            MAX_USERNAME_LENGTH
+        )));
+    }
+    validate_new_password(&password_policy, password.clone()).map_err(e400)?;
+
+    let user_id = crate::authentication::create_user(&pool, &username, password)
+        .await
+        .map_err(e500)?;
+
+    match user_id {
+        Some(user_id) => Ok(HttpResponse::Created().json(CreatedUser { user_id, username })),
+        None => Ok(HttpResponse::Conflict().finish()),
+    }
+}
@@ -0,0 +1,45 @@
+use crate::authentication::{verify_csrf_header, UserId};
+use crate::session_state::TypedSession;
+use crate::utils::{e400, e500};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Deactivate a user, so that they can no longer log in.
+///
+/// # Response
+///
+/// - **200 OK**: The user has been deactivated.
+/// - **400 Bad Request**: The user tried to deactivate themselves.
+///   Also returned when the `X-CSRF-Token` header is missing or invalid.
+/// - **404 Not Found**: No user has this id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Deactivate a user", skip(pool, session, request))]
+pub async fn deactivate_user(
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    request: HttpRequest,
+    current_user_id: web::ReqData<UserId>,
+    user_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    verify_csrf_header(&session, &request)?;
+    let user_id = user_id.into_inner();
+    if user_id == **current_user_id {
+        return Err(e400("You cannot deactivate your own account."));
+    }
+
+    let result = sqlx::query!(
+        "UPDATE users SET is_active = false WHERE user_id = $1",
+        user_id
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to deactivate the user.")
+    .map_err(e500)?;
+
+    if result.rows_affected() == 0 {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    Ok(HttpResponse::Ok().finish())
+}
@@ -0,0 +1,33 @@
+use crate::utils::e500;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A user as returned by the user listing endpoint.
+#[derive(serde::Serialize)]
+pub struct User {
+    user_id: Uuid,
+    username: String,
+    is_active: bool,
+}
+
+/// List every user, including deactivated ones.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a JSON array of [User], ordered by username.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "List users", skip(pool))]
+pub async fn list_users(pool: web::Data<PgPool>) -> Result<HttpResponse, actix_web::Error> {
+    let users = sqlx::query_as!(
+        User,
+        "SELECT user_id, username, is_active FROM users ORDER BY username"
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .context("Failed to fetch users.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(users))
+}
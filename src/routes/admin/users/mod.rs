@@ -0,0 +1,7 @@
+mod create;
+mod deactivate;
+mod list;
+
+pub use create::create_user;
+pub use deactivate::deactivate_user;
+pub use list::list_users;
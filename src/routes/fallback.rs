@@ -0,0 +1,39 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header;
+use actix_web::middleware::ErrorHandlerResponse;
+use actix_web::HttpResponse;
+
+/// The JSON body returned when no route handles the request.
+#[derive(serde::Serialize)]
+pub struct RoutingErrorResponse {
+    error: &'static str,
+}
+
+/// Answers requests to unknown paths.
+///
+/// # Response
+///
+/// - **404 Not Found**: `{"error":"not_found"}`.
+pub async fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(RoutingErrorResponse { error: "not_found" })
+}
+
+/// Replaces the empty body of the `405 Method Not Allowed` response, returned when a path
+/// is known but none of its routes accepts the method, with `{"error":"method_not_allowed"}`.
+///
+/// The `Allow` header listing the accepted methods is kept.
+pub fn method_not_allowed<B>(
+    res: ServiceResponse<B>,
+) -> Result<ErrorHandlerResponse<B>, actix_web::Error> {
+    let (req, res) = res.into_parts();
+    let mut response = HttpResponse::MethodNotAllowed();
+    if let Some(allow) = res.headers().get(header::ALLOW) {
+        response.insert_header((header::ALLOW, allow.clone()));
+    }
+    let response = response.json(RoutingErrorResponse {
+        error: "method_not_allowed",
+    });
+    Ok(ErrorHandlerResponse::Response(
+        ServiceResponse::new(req, response).map_into_right_body(),
+    ))
+}
@@ -1,4 +1,14 @@
-use actix_web::{HttpResponse, Responder};
+use crate::email_client::EmailClient;
+use crate::utils::e500;
+use actix_web::{web, HttpResponse, Responder};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a dependency may take to answer a readiness check before it is reported as failing.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
 
 /// Check if the server is running.
 /// This always returns a 200 OK status code.
@@ -9,3 +19,154 @@ use actix_web::{HttpResponse, Responder};
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok()
 }
+
+/// Build and migration details of the running server.
+#[derive(serde::Serialize)]
+pub struct HealthDetails {
+    version: &'static str,
+    git_sha: &'static str,
+    migration_version: Option<i64>,
+}
+
+/// Report the version of the running server and the latest applied migration.
+///
+/// # Response
+///
+/// - **200 OK**: A [HealthDetails] JSON object.
+///   `migration_version` is `null` if no migration has been applied.
+/// - **500 Internal Server Error**: The database could not be queried.
+pub async fn health_check_details(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let migration_version = sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT max(version) FROM _sqlx_migrations WHERE success",
+    )
+    .fetch_one(pool.as_ref())
+    .await
+    .context("Failed to read the latest applied migration.")
+    .map_err(e500)?;
+
+    Ok(HttpResponse::Ok().json(HealthDetails {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: env!("GIT_SHA"),
+        migration_version,
+    }))
+}
+
+/// The dependencies checked by [ready], other than the database pool.
+pub struct DependencyChecks {
+    redis_client: redis::Client,
+    email_client: EmailClient,
+    email_provider_check_interval: Duration,
+    /// When the email provider was last pinged, and whether it answered.
+    last_email_provider_check: Mutex<Option<(Instant, bool)>>,
+}
+
+impl DependencyChecks {
+    /// Connecting to Redis is left to the checks, so that building them never fails
+    /// because Redis is down.
+    pub fn new(
+        redis_url: &str,
+        email_client: EmailClient,
+        email_provider_check_interval: Duration,
+    ) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            redis_client: redis::Client::open(redis_url)?,
+            email_client,
+            email_provider_check_interval,
+            last_email_provider_check: Mutex::new(None),
+        })
+    }
+
+    async fn check_redis(&self) -> Result<(), anyhow::Error> {
+        let mut connection = self.redis_client.get_multiplexed_tokio_connection().await?;
+        redis::cmd("PING")
+            .query_async::<_, String>(&mut connection)
+            .await?;
+        Ok(())
+    }
+
+    /// Pinging the provider is a request to a third party, so its result is reused
+    /// for `email_provider_check_interval` rather than repeated for every probe.
+    async fn check_email_provider(&self) -> bool {
+        let last_check = *self.last_email_provider_check.lock().unwrap();
+        if let Some((checked_at, healthy)) = last_check {
+            if checked_at.elapsed() < self.email_provider_check_interval {
+                return healthy;
+            }
+        }
+        let healthy = report(
+            "email_provider",
+            with_timeout(self.email_client.check_connection()),
+        )
+        .await;
+        *self.last_email_provider_check.lock().unwrap() = Some((Instant::now(), healthy));
+        healthy
+    }
+}
+
+/// The status of each dependency, either `"ok"` or `"error"`.
+#[derive(serde::Serialize)]
+pub struct ReadinessReport {
+    database: &'static str,
+    redis: &'static str,
+    email_provider: &'static str,
+}
+
+/// Check whether the server can handle requests, i.e. whether its dependencies are reachable.
+///
+/// # Response
+///
+/// - **200 OK**: A [ReadinessReport] JSON object, with every dependency `"ok"`.
+/// - **503 Service Unavailable**: A [ReadinessReport] JSON object,
+///   with the failing dependencies marked as `"error"`.
+pub async fn ready(pool: web::Data<PgPool>, checks: web::Data<DependencyChecks>) -> HttpResponse {
+    let database = async {
+        sqlx::query("SELECT 1").execute(pool.as_ref()).await?;
+        Ok(())
+    };
+    let (database, redis, email_provider) = tokio::join!(
+        report("database", with_timeout(database)),
+        report("redis", with_timeout(checks.check_redis())),
+        checks.check_email_provider(),
+    );
+
+    let status = |healthy| if healthy { "ok" } else { "error" };
+    let body = ReadinessReport {
+        database: status(database),
+        redis: status(redis),
+        email_provider: status(email_provider),
+    };
+    if database && redis && email_provider {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+async fn with_timeout(
+    check: impl Future<Output = Result<(), anyhow::Error>>,
+) -> Result<(), anyhow::Error> {
+    tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .context("The check timed out.")?
+}
+
+/// Logs the failure of a check, and tells whether it succeeded.
+async fn report(
+    dependency: &'static str,
+    check: impl Future<Output = Result<(), anyhow::Error>>,
+) -> bool {
+    match check.await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                dependency,
+                error.cause_chain = ?e,
+                error.message = %e,
+                "A readiness check failed."
+            );
+            false
+        }
+    }
+}
@@ -1,12 +1,18 @@
+use crate::startup::BasePath;
+use crate::utils::{e500, template_context};
 use actix_web::http::header::ContentType;
 use actix_web::{web, HttpResponse};
 use tera::Tera;
 
-pub async fn home(tmpl: web::Data<Tera>) -> HttpResponse {
-    let rendered = tmpl
-        .render("home.html", &tera::Context::new())
-        .expect("Failed to render template.");
-    HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(rendered)
+pub async fn home(
+    tmpl: web::Data<Tera>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    tmpl.render("home.html", &template_context(&base_path))
+        .map(|body| {
+            HttpResponse::Ok()
+                .content_type(ContentType::html())
+                .body(body)
+        })
+        .map_err(e500)
 }
@@ -1,18 +1,85 @@
 pub mod post;
+pub mod two_factor;
 
-use crate::utils::{e500, set_flash_messages};
+use crate::startup::BasePath;
+use crate::utils::{e500, set_flash_messages, template_context};
 use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::{IncomingFlashMessages, Level};
 use tera::Tera;
 
+/// The query parameters for the login form.
+///
+/// # Fields
+///
+/// - `next`: The page to go to after logging in. Ignored unless it is an admin page.
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    next: Option<String>,
+}
+
 pub async fn login_form(
     tmpl: web::Data<Tera>,
+    query: web::Query<QueryParams>,
     flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, actix_web::Error> {
-    let mut context = tera::Context::new();
+    let mut context = template_context(&base_path);
     set_flash_messages(&mut context, flash_messages, Level::Info);
+    if let Some(next) = query.next.as_deref().and_then(safe_next_path) {
+        context.insert("next", next);
+    }
 
     tmpl.render("login.html", &context)
         .map(|body| HttpResponse::Ok().body(body))
         .map_err(e500)
 }
+
+/// The page to go to after logging in when no valid `next` has been given.
+pub(crate) const DEFAULT_LANDING_PATH: &str = "/admin/dashboard";
+
+/// Returns `next` if it is safe to redirect to after logging in.
+///
+/// Only relative paths under `/admin` are accepted, so that the login form
+/// cannot be used to redirect users to another site.
+pub(crate) fn safe_next_path(next: &str) -> Option<&str> {
+    let under_admin = next == "/admin"
+        || ["/admin/", "/admin?"]
+            .iter()
+            .any(|prefix| next.starts_with(prefix));
+    let is_safe = under_admin && !next.contains('\\') && !next.chars().any(char::is_control);
+    is_safe.then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::safe_next_path;
+
+    #[test]
+    fn admin_pages_are_accepted() {
+        for next in [
+            "/admin",
+            "/admin/password",
+            "/admin/newsletters?page=2",
+            "/admin?tab=users",
+        ] {
+            assert_eq!(safe_next_path(next), Some(next));
+        }
+    }
+
+    #[test]
+    fn other_destinations_are_rejected() {
+        for next in [
+            "",
+            "/",
+            "/login",
+            "/administrator",
+            "admin/password",
+            "//evil.example.com/admin",
+            "https://evil.example.com/admin",
+            "/admin\\@evil.example.com",
+            "/admin/\r\nSet-Cookie: x=y",
+        ] {
+            assert_eq!(safe_next_path(next), None, "{:?} was accepted", next);
+        }
+    }
+}
@@ -1,11 +1,17 @@
-use crate::authentication::{validate_credentials, AuthError, Credentials};
+use crate::authentication::{
+    csrf_token, get_totp_secret, record_login, validate_credentials, AuthError, Credentials,
+};
+use crate::client_ip::client_ip;
+use crate::routes::login::{safe_next_path, DEFAULT_LANDING_PATH};
 use crate::session_state::TypedSession;
-use crate::utils::{error_chain_fmt, see_other};
+use crate::startup::BasePath;
+use crate::utils::{error_chain_fmt, see_other_in_app};
 use actix_web::error::InternalError;
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use secrecy::Secret;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 #[derive(thiserror::Error)]
 pub enum LoginError {
@@ -34,40 +40,79 @@ impl From<AuthError> for LoginError {
 pub struct FormData {
     username: String,
     password: Secret<String>,
+    /// The page to go to after logging in. Ignored unless it is an admin page.
+    next: Option<String>,
 }
 
 #[tracing::instrument(
-    skip(pool, session, form),
+    skip(req, pool, session, form, base_path),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
+    req: HttpRequest,
     pool: web::Data<PgPool>,
     session: TypedSession,
     form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
-    let credentials = Credentials {
-        username: form.0.username,
-        password: form.0.password,
-    };
-    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+    let FormData {
+        username,
+        password,
+        next,
+    } = form.0;
+    let destination = next
+        .as_deref()
+        .and_then(safe_next_path)
+        .unwrap_or(DEFAULT_LANDING_PATH);
+    let credentials = Credentials { username, password };
+    tracing::Span::current().record("username", tracing::field::display(&credentials.username));
     match validate_credentials(&pool, credentials).await {
         Ok(user_id) => {
-            tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            let totp_secret = get_totp_secret(&pool, user_id)
+                .await
+                .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e)))?;
             session.renew();
-            session
-                .insert_user_id(user_id)
-                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
-            Ok(see_other("/admin/dashboard"))
+            if totp_secret.is_some() {
+                session
+                    .insert_pending_two_factor_user_id(user_id)
+                    .and_then(|_| session.insert_next_path(destination))
+                    .map_err(|e| {
+                        login_redirect(&base_path, LoginError::UnexpectedError(e.into()))
+                    })?;
+                return Ok(see_other_in_app(&base_path, "/login/2fa"));
+            }
+            complete_login(&req, &pool, &session, user_id)
+                .await
+                .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e)))?;
+            Ok(see_other_in_app(&base_path, destination))
         }
         Err(e) => {
             let e = LoginError::from(e);
-            Err(login_redirect(e))
+            Err(login_redirect(&base_path, e))
         }
     }
 }
 
-fn login_redirect(e: LoginError) -> InternalError<LoginError> {
+/// Marks the session as authenticated and records the login,
+/// keeping the previous one around for the dashboard.
+pub(super) async fn complete_login(
+    req: &HttpRequest,
+    pool: &PgPool,
+    session: &TypedSession,
+    user_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    session.insert_user_id(user_id)?;
+    csrf_token(session)?;
+    let ip = client_ip(req).to_string();
+    if let Some(previous_login) = record_login(pool, user_id, Some(&ip)).await? {
+        session.insert_previous_login(&previous_login)?;
+    }
+    Ok(())
+}
+
+pub(super) fn login_redirect(base_path: &BasePath, e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
-    let response = see_other("/login");
+    let response = see_other_in_app(base_path, "/login");
     InternalError::from_response(e, response)
 }
@@ -0,0 +1,108 @@
+use crate::authentication::{get_totp_secret, verify_totp_code};
+use crate::routes::login::post::{complete_login, login_redirect, LoginError};
+use crate::routes::login::{safe_next_path, DEFAULT_LANDING_PATH};
+use crate::session_state::TypedSession;
+use crate::startup::BasePath;
+use crate::utils::{e500, see_other_in_app, set_flash_messages, template_context};
+use actix_web::error::InternalError;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages, Level};
+use sqlx::PgPool;
+use tera::Tera;
+
+pub async fn login_two_factor_form(
+    tmpl: web::Data<Tera>,
+    session: TypedSession,
+    flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, actix_web::Error> {
+    if session
+        .get_pending_two_factor_user_id()
+        .map_err(e500)?
+        .is_none()
+    {
+        return Ok(see_other_in_app(&base_path, "/login"));
+    }
+
+    let mut context = template_context(&base_path);
+    set_flash_messages(&mut context, flash_messages, Level::Info);
+
+    tmpl.render("login_two_factor.html", &context)
+        .map(|body| HttpResponse::Ok().body(body))
+        .map_err(e500)
+}
+
+/// The number of invalid codes after which the pending login is dropped,
+/// so that the code cannot be brute-forced once the password is known.
+const MAX_TWO_FACTOR_ATTEMPTS: u32 = 5;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    code: String,
+}
+
+#[tracing::instrument(skip_all, fields(user_id = tracing::field::Empty))]
+pub async fn login_two_factor(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    session: TypedSession,
+    form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, InternalError<LoginError>> {
+    let user_id = session
+        .get_pending_two_factor_user_id()
+        .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e.into())))?;
+    let Some(user_id) = user_id else {
+        return Ok(see_other_in_app(&base_path, "/login"));
+    };
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+
+    let secret = get_totp_secret(&pool, user_id)
+        .await
+        .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e)))?
+        .ok_or_else(|| {
+            login_redirect(
+                &base_path,
+                LoginError::UnexpectedError(anyhow::anyhow!(
+                    "Two-factor authentication is not enabled for the user."
+                )),
+            )
+        })?;
+    let is_valid = verify_totp_code(&secret, &form.code)
+        .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e)))?;
+    if !is_valid {
+        let failures = session
+            .get_two_factor_failures()
+            .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e.into())))?
+            .unwrap_or(0)
+            + 1;
+        if failures >= MAX_TWO_FACTOR_ATTEMPTS {
+            tracing::warn!("Too many invalid authentication codes. Dropping the pending login.");
+            session.remove_pending_two_factor_user_id();
+            session.remove_next_path();
+            FlashMessage::error("Too many invalid authentication codes. Please log in again.")
+                .send();
+            return Ok(see_other_in_app(&base_path, "/login"));
+        }
+        session
+            .insert_two_factor_failures(failures)
+            .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e.into())))?;
+        FlashMessage::error("The authentication code is invalid.").send();
+        return Ok(see_other_in_app(&base_path, "/login/2fa"));
+    }
+
+    let next = session
+        .get_next_path()
+        .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e.into())))?;
+    session.renew();
+    session.remove_pending_two_factor_user_id();
+    session.remove_next_path();
+    complete_login(&req, &pool, &session, user_id)
+        .await
+        .map_err(|e| login_redirect(&base_path, LoginError::UnexpectedError(e)))?;
+    let destination = next
+        .as_deref()
+        .and_then(safe_next_path)
+        .unwrap_or(DEFAULT_LANDING_PATH);
+    Ok(see_other_in_app(&base_path, destination))
+}
@@ -0,0 +1,17 @@
+use crate::metrics::Metrics;
+use crate::utils::e500;
+use actix_web::{web, HttpResponse};
+use prometheus::{Encoder, TextEncoder};
+
+/// Expose the metrics of the application to Prometheus.
+///
+/// # Response
+///
+/// - **200 OK**: The metrics in the Prometheus text exposition format.
+/// - **500 Internal Server Error**: The metrics could not be encoded.
+pub async fn metrics(metrics: web::Data<Metrics>) -> Result<HttpResponse, actix_web::Error> {
+    let body = metrics.render().map_err(e500)?;
+    Ok(HttpResponse::Ok()
+        .content_type(TextEncoder::new().format_type())
+        .body(body))
+}
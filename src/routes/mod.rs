@@ -1,19 +1,55 @@
 mod admin;
+mod fallback;
 mod health_check;
 mod home;
 mod login;
+mod metrics;
+mod preference_center;
+mod preferences;
 mod subscriptions;
+mod subscriptions_change_email;
 mod subscriptions_confirm;
+mod subscriptions_resend;
+mod subscriptions_status;
+mod subscriptions_unsubscribe;
 
 pub use admin::dashboard::admin_dashboard;
-pub use admin::logout::log_out;
+pub use admin::dead_letters::{list_dead_letters, requeue_dead_letter};
+pub use admin::logout::{log_out, log_out_form};
+pub use admin::me::current_user;
+pub use admin::newsletters::cancel_newsletter_issue;
 pub use admin::newsletters::publish_newsletter;
 pub use admin::newsletters::publish_newsletter_form;
+pub use admin::newsletters::resend_failed_deliveries;
+pub use admin::newsletters::{get_newsletter_draft, save_newsletter_draft};
+pub use admin::newsletters::{get_newsletter_issue, list_newsletter_issues};
 pub use admin::password::change_password;
 pub use admin::password::change_password_form;
-pub use health_check::health_check;
+pub use admin::security::rehash_all_passwords;
+pub use admin::subscribers::import_subscribers;
+pub use admin::subscribers::list_subscribers;
+pub use admin::subscribers::subscriber_status;
+pub use admin::subscribers::update_subscriber;
+pub use admin::subscribers::{import_subscribers_csv, is_csv};
+pub use admin::system::{dispatch_pending_tasks, worker_status};
+pub use admin::two_factor::enable_two_factor;
+pub use admin::two_factor::two_factor_setup_form;
+pub use admin::users::{create_user, deactivate_user, list_users};
+pub use fallback::{method_not_allowed, not_found};
+pub use health_check::{health_check, health_check_details, ready, DependencyChecks};
 pub use home::home;
 pub use login::login_form;
 pub use login::post::login;
+pub use login::two_factor::login_two_factor;
+pub use login::two_factor::login_two_factor_form;
+pub use metrics::metrics;
+pub use preference_center::{preference_center, unsubscribe_from_preferences, update_preferences};
+pub use preferences::rotate_token;
+pub(crate) use subscriptions::send_confirmation_email;
 pub use subscriptions::subscribe;
-pub use subscriptions_confirm::confirm;
+pub use subscriptions::{generate_subscription_token_with_rng, store_token_with_rng};
+pub use subscriptions_change_email::change_email;
+pub use subscriptions_confirm::{confirm, confirm_via_post};
+pub use subscriptions_resend::resend_confirmation;
+pub use subscriptions_status::{subscription_by_id, subscription_status};
+pub use subscriptions_unsubscribe::{unsubscribe, UnsubscribeLinks};
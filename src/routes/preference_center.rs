@@ -0,0 +1,211 @@
+use crate::domain::{ContentFormat, DeliveryFrequency, SubscriberName};
+use crate::startup::BasePath;
+use crate::utils::{error_chain_fmt, see_other_in_app, set_flash_messages, template_context};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages, Level};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::{Debug, Formatter};
+use tera::Tera;
+use PreferenceCenterError::*;
+
+/// The query parameters of the preference center.
+///
+/// # Fields
+///
+/// - `token`: The subscription token of the subscriber.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+/// The form data passed to the preference center.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    name: String,
+    content_format: ContentFormat,
+    frequency: DeliveryFrequency,
+}
+
+/// The preferences of a subscriber, as shown in the preference center.
+struct Preferences {
+    name: String,
+    content_format: String,
+    frequency: String,
+}
+
+/// Show the preferences of a confirmed subscriber, with a form to update them or unsubscribe.
+///
+/// The subscription token authorizes access to the preferences of its subscriber only.
+///
+/// # Response
+///
+/// - **200 OK**: The body is the preference center page.
+/// - **401 Unauthorized**: The token does not belong to a confirmed subscriber.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Show the preference center", skip_all)]
+pub async fn preference_center(
+    tmpl: web::Data<Tera>,
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    flash_messages: IncomingFlashMessages,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, PreferenceCenterError> {
+    let preferences = sqlx::query_as!(
+        Preferences,
+        r#"
+        SELECT s.name, s.content_format, s.frequency
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1 AND s.status = 'confirmed'
+        "#,
+        parameters.token
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to fetch the subscriber's preferences.")?
+    .ok_or(InvalidTokenError)?;
+
+    let mut context = template_context(&base_path);
+    set_flash_messages(&mut context, flash_messages, Level::Info);
+    context.insert("token", &parameters.token);
+    context.insert("name", &preferences.name);
+    context.insert("content_format", &preferences.content_format);
+    context.insert("frequency", &preferences.frequency);
+    render_page(&tmpl, "preferences/center.html", &context)
+}
+
+/// Update the name, content format and delivery frequency of a confirmed subscriber.
+///
+/// # Response
+///
+/// - **303 See Other**: Redirects to the preference center, with a message saying
+///   whether the preferences have been saved.
+/// - **400 Bad Request**: A content format or frequency is missing or unknown.
+/// - **401 Unauthorized**: The token does not belong to a confirmed subscriber.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Update subscriber preferences", skip_all)]
+pub async fn update_preferences(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    form: web::Form<FormData>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, PreferenceCenterError> {
+    let location = format!("/preferences?token={}", parameters.token);
+    let form = form.into_inner();
+    let name = match SubscriberName::parse(form.name) {
+        Ok(name) => name,
+        Err(e) => {
+            FlashMessage::error(e.to_string()).send();
+            return Ok(see_other_in_app(&base_path, &location));
+        }
+    };
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions s
+        SET name = $2, content_format = $3, frequency = $4
+        FROM subscription_tokens t
+        WHERE t.subscription_token = $1 AND s.id = t.subscriber_id AND s.status = 'confirmed'
+        "#,
+        parameters.token,
+        name.as_ref(),
+        form.content_format.as_str(),
+        form.frequency.as_str(),
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to update the subscriber's preferences.")?;
+    if result.rows_affected() == 0 {
+        return Err(InvalidTokenError);
+    }
+
+    FlashMessage::info("Your preferences have been saved.").send();
+    Ok(see_other_in_app(&base_path, &location))
+}
+
+/// Unsubscribe a confirmed subscriber from the preference center.
+///
+/// Every token of the subscriber is revoked, including the one used here.
+///
+/// # Response
+///
+/// - **200 OK**: The subscriber has been unsubscribed. The body is a page saying so.
+/// - **401 Unauthorized**: The token does not belong to a confirmed subscriber.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Unsubscribe from the preference center", skip_all)]
+pub async fn unsubscribe_from_preferences(
+    tmpl: web::Data<Tera>,
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+    base_path: web::Data<BasePath>,
+) -> Result<HttpResponse, PreferenceCenterError> {
+    let unsubscribed = sqlx::query_scalar!(
+        r#"
+        WITH unsubscribed AS (
+            UPDATE subscriptions s
+            SET status = 'unsubscribed'
+            FROM subscription_tokens t
+            WHERE t.subscription_token = $1 AND s.id = t.subscriber_id AND s.status = 'confirmed'
+            RETURNING s.id
+        ), revoked AS (
+            DELETE FROM subscription_tokens
+            WHERE subscriber_id IN (SELECT id FROM unsubscribed)
+        )
+        SELECT count(*) AS "count!" FROM unsubscribed
+        "#,
+        parameters.token,
+    )
+    .fetch_one(pool.as_ref())
+    .await
+    .context("Failed to set status `unsubscribed` in the database.")?;
+    if unsubscribed == 0 {
+        return Err(InvalidTokenError);
+    }
+
+    render_page(
+        &tmpl,
+        "preferences/unsubscribed.html",
+        &template_context(&base_path),
+    )
+}
+
+fn render_page(
+    tmpl: &Tera,
+    template: &str,
+    context: &tera::Context,
+) -> Result<HttpResponse, PreferenceCenterError> {
+    let body = tmpl
+        .render(template, context)
+        .with_context(|| format!("Failed to render `{}`.", template))?;
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+/// The error type for the preference center.
+#[derive(thiserror::Error)]
+pub enum PreferenceCenterError {
+    /// The token does not belong to a confirmed subscriber.
+    #[error("The link to your preferences is invalid.")]
+    InvalidTokenError,
+    /// An error occurred while processing the request.
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for PreferenceCenterError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            InvalidTokenError => StatusCode::UNAUTHORIZED,
+            UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Debug for PreferenceCenterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
@@ -0,0 +1,268 @@
+use crate::configuration::PreferencesSettings;
+use crate::domain::{SubscriberEmail, SubscriptionStatus};
+use crate::email_client::EmailClient;
+use crate::rate_limit::RateLimitStatus;
+use crate::routes::subscriptions::{confirmation_link, store_token};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::fmt::{Debug, Formatter};
+use url::Url;
+use uuid::Uuid;
+use RotateTokenError::*;
+
+/// The query parameters for the rotate-token endpoint.
+///
+/// # Fields
+///
+/// - `subscription_token`: The token to be replaced.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    subscription_token: String,
+}
+
+/// Replace a subscription token with a new one.
+///
+/// The old token stops working immediately,
+/// and a link with the new token is sent to the subscriber's email address:
+/// a link to the preference center, or a confirmation link if the subscriber is still pending.
+/// The new token is always stored, whatever the
+/// [crate::configuration::ConfirmationTokenScheme], so that it works with the preference center.
+/// If the link cannot be sent, nothing is changed and the old token keeps working.
+/// The number of rotations per subscriber is limited by
+/// [PreferencesSettings::max_token_rotations_per_hour], and the remaining quota is reported
+/// in the `X-RateLimit-*` headers of the response.
+///
+/// # Request
+///
+/// ### Query Parameters
+///
+/// Field                | Description
+/// ---------------------|----------------------------
+/// `subscription_token` | The token to be replaced.
+///
+/// # Response
+///
+/// - **200 OK**: The token has been rotated.
+/// - **401 Unauthorized**: The token is invalid.
+/// - **429 Too Many Requests**: The token has been rotated too many times in the last hour.
+///   `Retry-After` says when the next rotation will be allowed.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(
+    name = "Rotate a subscription token",
+    skip(pool, email_client, base_url, settings, parameters)
+)]
+pub async fn rotate_token(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<PreferencesSettings>,
+    parameters: web::Query<Parameters>,
+) -> Result<HttpResponse, RotateTokenError> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let (subscriber_id, email, status) =
+        lock_subscriber_from_token(&mut transaction, &parameters.subscription_token)
+            .await
+            .context("Failed to get subscriber from the database.")?
+            .ok_or(TokenNotFoundError)?;
+
+    let recent = recent_rotations(&mut transaction, subscriber_id)
+        .await
+        .context("Failed to count recent token rotations.")?;
+    let limit = settings.max_token_rotations_per_hour;
+    if recent.count >= limit {
+        return Err(TooManyRotations(RateLimitStatus {
+            limit,
+            remaining: 0,
+            reset_at: recent.reset_at.unwrap_or_else(Utc::now),
+        }));
+    }
+    let rate_limit = RateLimitStatus {
+        limit,
+        remaining: limit - recent.count - 1,
+        reset_at: recent
+            .reset_at
+            .unwrap_or_else(|| Utc::now() + ROTATION_WINDOW),
+    };
+
+    replace_token(
+        &mut transaction,
+        subscriber_id,
+        &parameters.subscription_token,
+    )
+    .await
+    .context("Failed to record the token rotation.")?;
+    let subscription_token = store_token(&mut transaction, &subscriber_id)
+        .await
+        .context("Failed to store the new subscription token.")?;
+
+    // The link is sent before committing, so that the old token is only revoked
+    // once the subscriber has been given a new one.
+    let email = SubscriberEmail::parse(email)
+        .context("The subscriber's stored email address is invalid.")?;
+    let link = if status == SubscriptionStatus::Confirmed {
+        preference_center_link(&base_url.0, &subscription_token)
+    } else {
+        confirmation_link(&base_url.0, &subscription_token)
+    }?;
+    send_new_link_email(&email_client, &email, &link)
+        .await
+        .context("Failed to send the new subscription link.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to rotate a subscription token.")?;
+
+    let mut response = HttpResponse::Ok().finish();
+    rate_limit.insert_headers(&mut response);
+    Ok(response)
+}
+
+/// The error type for the rotate-token endpoint.
+#[derive(thiserror::Error)]
+pub enum RotateTokenError {
+    /// The subscription token is invalid.
+    #[error("Failed to find subscriber. The token is invalid.")]
+    TokenNotFoundError,
+    /// The subscriber has reached the rotation limit.
+    #[error("The subscription token has been rotated too many times. Try again later.")]
+    TooManyRotations(RateLimitStatus),
+    /// An error occurred while processing the request.
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for RotateTokenError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            TokenNotFoundError => StatusCode::UNAUTHORIZED,
+            TooManyRotations(_) => StatusCode::TOO_MANY_REQUESTS,
+            UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code()).body(self.to_string());
+        if let TooManyRotations(rate_limit) = self {
+            rate_limit.insert_headers(&mut response);
+        }
+        response
+    }
+}
+
+impl Debug for RotateTokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[tracing::instrument(name = "Lock subscriber from token", skip_all)]
+async fn lock_subscriber_from_token(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<Option<(Uuid, String, SubscriptionStatus)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT s.id, s.email, s.status AS "status: SubscriptionStatus"
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+        FOR UPDATE OF s
+        "#,
+        subscription_token
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(record.map(|r| (r.id, r.email, r.status)))
+}
+
+/// The window over which token rotations are counted.
+const ROTATION_WINDOW: Duration = Duration::hours(1);
+
+/// The rotations of a subscriber's token within the last [ROTATION_WINDOW].
+struct RecentRotations {
+    count: i64,
+    /// When the oldest of them leaves the window, if there are any.
+    reset_at: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Count recent token rotations", skip(tx))]
+async fn recent_rotations(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<RecentRotations, sqlx::Error> {
+    sqlx::query_as!(
+        RecentRotations,
+        r#"
+        SELECT count(*) AS "count!", min(rotated_at) + interval '1 hour' AS reset_at
+        FROM subscription_token_rotations
+        WHERE subscriber_id = $1 AND rotated_at > now() - interval '1 hour'
+        "#,
+        subscriber_id
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Deletes the old token and records the rotation.
+#[tracing::instrument(name = "Replace subscription token", skip(tx, subscription_token))]
+async fn replace_token(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    tx.execute(sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscription_token = $1",
+        subscription_token
+    ))
+    .await?;
+    tx.execute(sqlx::query!(
+        r#"
+        INSERT INTO subscription_token_rotations (subscriber_id, rotated_at)
+        VALUES ($1, now())
+        "#,
+        subscriber_id
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Builds the link to the preference center of the subscriber owning `subscription_token`.
+fn preference_center_link(base_url: &Url, subscription_token: &str) -> Result<Url, anyhow::Error> {
+    let mut link = base_url
+        .join("preferences")
+        .context("Failed to build the preference center link.")?;
+    link.query_pairs_mut()
+        .append_pair("token", subscription_token);
+    Ok(link)
+}
+
+#[tracing::instrument(name = "Send the new subscription link", skip_all)]
+async fn send_new_link_email(
+    email_client: &EmailClient,
+    email: &SubscriberEmail,
+    link: &Url,
+) -> Result<(), anyhow::Error> {
+    let html_body = format!(
+        "Your subscription link has been renewed.<br />\
+                Use <a href=\"{}\">this link</a> from now on; the previous one no longer works.",
+        link
+    );
+    let plain_body = format!(
+        "Your subscription link has been renewed.\nUse {} from now on; the previous one no longer works.",
+        link
+    );
+    email_client
+        .send_email(email, "Your new subscription link", &html_body, &plain_body)
+        .await?;
+    Ok(())
+}
@@ -1,17 +1,42 @@
 use self::SubscribeError::*;
+use crate::authentication::{api_token_source, bearer_token};
+use crate::configuration::{
+    ConfirmationRetrySettings, ConfirmationTokenScheme, FeatureFlags, SubscriptionSettings,
+};
+use crate::confirmation_outbox::{cancel_confirmation_retry, schedule_confirmation_retry};
+use crate::disposable_domains::DisposableDomains;
 use crate::domain::SubscriberName;
-use crate::domain::{NewSubscriber, SubscriberEmail};
+use crate::domain::{
+    NewSubscriber, NewSubscriberError, SubscriberEmail, SubscriberLocale, SubscriberTag,
+    SubscriberTimezone, SubscriptionStatus,
+};
 use crate::email_client::EmailClient;
-use crate::startup::ApplicationBaseUrl;
-use crate::utils::{error_chain_fmt, ParsingError};
+use crate::email_templates::EmailTemplates;
+use crate::routes::subscriptions_confirm::{confirmation_address, sign_confirmation_token};
+use crate::startup::{ApplicationBaseUrl, BasePath, HmacSecret};
+use crate::telemetry::timed_query;
+use crate::utils::{accepts_html, accepts_json, error_chain_fmt, template_context};
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
-use actix_web::{web, HttpResponse, ResponseError};
+use actix_web::mime;
+use actix_web::{web, FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
 use anyhow::Context;
 use chrono::Utc;
 use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng, RngCore};
+use secrecy::Secret;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
-use std::fmt::{Debug, Display, Formatter};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use tera::Tera;
+use tracing::field::display;
+use tracing::Span;
+use url::Url;
 use uuid::Uuid;
 
 /// The form data passed to the subscribe endpoint.
@@ -21,45 +46,142 @@ use uuid::Uuid;
 ///
 /// - `email`: The email address of the new subscriber.
 /// - `name`: The name of the new subscriber.
+/// - `tags`: An optional comma-separated list of tags.
+/// - `locale`: An optional BCP-47 language tag, e.g. `en-US`.
+/// - `timezone`: An optional IANA time zone name, e.g. `America/New_York`.
 #[derive(serde::Deserialize)]
 pub struct FormData {
     email: String,
     name: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    locale: String,
+    #[serde(default)]
+    timezone: String,
 }
 
-/// This struct implements the [TryInto] trait,
-/// which allows it to be converted into a [NewSubscriber].
-/// Wrong formats of the email address or name will be caught and returned as an error.
-impl TryInto<NewSubscriber> for FormData {
-    type Error = Box<dyn ParsingError>;
+/// Every field is validated, and all of the invalid ones are reported together
+/// in a [NewSubscriberError], which tells which field failed and why.
+impl TryFrom<FormData> for NewSubscriber {
+    type Error = NewSubscriberError;
 
-    fn try_into(self) -> Result<NewSubscriber, Self::Error> {
-        let email = SubscriberEmail::parse(self.email).map_err(Box::new)?;
-        let name = SubscriberName::parse(self.name).map_err(Box::new)?;
-        Ok(NewSubscriber { email, name })
+    fn try_from(form: FormData) -> Result<Self, Self::Error> {
+        let mut error = NewSubscriberError::default();
+        let email = SubscriberEmail::parse(form.email)
+            .map_err(|e| error.email = Some(e))
+            .ok();
+        let name = SubscriberName::parse(form.name)
+            .map_err(|e| error.name = Some(e))
+            .ok();
+        let tags = SubscriberTag::parse_list(&form.tags)
+            .map_err(|e| error.tags = Some(e))
+            .ok();
+        let locale = parse_optional(form.locale, SubscriberLocale::parse)
+            .map_err(|e| error.locale = Some(e))
+            .ok();
+        let timezone = parse_optional(form.timezone, SubscriberTimezone::parse)
+            .map_err(|e| error.timezone = Some(e))
+            .ok();
+        match (email, name, tags, locale, timezone) {
+            (Some(email), Some(name), Some(tags), Some(locale), Some(timezone)) => {
+                Ok(NewSubscriber {
+                    email,
+                    name,
+                    tags,
+                    locale,
+                    timezone,
+                })
+            }
+            _ => Err(error),
+        }
+    }
+}
+
+/// The body of a subscribe request: a JSON object when `Content-Type` says so,
+/// URL-encoded form data otherwise.
+pub struct SubscribeBody(FormData);
+
+impl FromRequest for SubscribeBody {
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        if is_json(req) {
+            let json = web::Json::<FormData>::from_request(req, payload);
+            Box::pin(async move { Ok(Self(json.await?.into_inner())) })
+        } else {
+            let form = web::Form::<FormData>::from_request(req, payload);
+            Box::pin(async move { Ok(Self(form.await?.into_inner())) })
+        }
+    }
+}
+
+/// Whether the request body is JSON: `application/json` or a `+json` media type.
+fn is_json(req: &HttpRequest) -> bool {
+    match req.mime_type() {
+        Ok(Some(mime)) => mime.subtype() == mime::JSON || mime.suffix() == Some(mime::JSON),
+        _ => false,
+    }
+}
+
+/// Parses an optional form field, where an empty value means that it was not provided.
+fn parse_optional<T, E>(
+    value: String,
+    parse: impl FnOnce(String) -> Result<T, E>,
+) -> Result<Option<T>, E> {
+    if value.trim().is_empty() {
+        Ok(None)
+    } else {
+        parse(value).map(Some)
     }
 }
 
 /// Add a new subscriber to the database.
 ///
+/// When [FeatureFlags::double_opt_in] is disabled, the subscriber is confirmed right away
+/// and no confirmation email is sent.
+/// Otherwise, the confirmation email is retried a few times while the client waits.
+/// If it still cannot be sent, it is handed over to the confirmation outbox
+/// and the request succeeds anyway.
+///
 /// # Request
 ///
-/// ### URL-encoded Form Data
+/// ### URL-encoded Form Data or JSON
 ///
-/// The URL-encoded form data will be passed as `form`, an instance of [FormData].
-/// Every field is required.
+/// The body is read as URL-encoded form data or as a JSON object, depending on `Content-Type`,
+/// into an instance of [FormData]. `email` and `name` are required.
 ///
-/// Field   | Description
-/// --------|-----------------------------------------
-/// `email` | The email address of the new subscriber.
-/// `name`  | The name of the new subscriber.
+/// Field      | Description
+/// -----------|-----------------------------------------
+/// `email`    | The email address of the new subscriber.
+/// `name`     | The name of the new subscriber.
+/// `tags`     | A comma-separated list of tags.
+/// `locale`   | A BCP-47 language tag, e.g. `en-US`.
+/// `timezone` | An IANA time zone name, e.g. `America/New_York`.
 ///
 /// See [FormData] for more information.
 ///
+/// ### Headers
+///
+/// - `Authorization: Bearer <token>`: An API token, whose `source` is recorded on the subscriber.
+///   Required when [SubscriptionSettings::require_api_token] is set.
+///
 /// # Response
 ///
+/// - **201 Created** - The subscriber has been successfully added and the client accepts JSON.
+///   The `Location` header points to the subscriber's status (`/subscriptions/{id}`),
+///   and the body is a JSON object with the new subscriber's `id` and `status`.
 /// - **200 OK** - The subscriber has been successfully added.
-/// - **400 Bad Request** - The request is malformed.
+///   If the client accepts HTML, the body is a page asking the subscriber to check their email,
+///   or thanking them when no confirmation is needed.
+///   Otherwise, the body is empty.
+/// - **400 Bad Request** - The request is malformed, or the email domain is blocked.
+///   When the form data is invalid, the body is a JSON object whose `errors` field
+///   lists a message for every invalid field.
+///   If the client accepts HTML, the subscribe form is rendered again with the errors instead.
+/// - **401 Unauthorized** - The API token is invalid, or missing while it is required.
+/// - **403 Forbidden** - The [SubscriptionSettings::max_subscribers] limit has been reached.
 /// - **500 Internal Server Error** - An error occurred while processing the request.
 ///
 /// # Errors
@@ -67,60 +189,242 @@ impl TryInto<NewSubscriber> for FormData {
 /// This function can return [SubscribeError] which has the following variants:
 ///
 /// - [ValidationError]: The form data is invalid.
+/// - [InvalidApiToken]: The API token is invalid or missing.
+/// - [DisposableEmailDomain]: The email domain is in [DisposableDomains].
+/// - [SubscriberLimitReached]: There is no room left for a new subscriber.
 /// - [UnexpectedError]: An error occurred while processing the request.
 ///
 /// See [SubscribeError::status_code] for more information
 /// about mapping between the error and status codes.
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(pool, email_client, base_url, form),
-    fields(email = %form.email, name = %form.name)
+    skip(
+        req,
+        tmpl,
+        pool,
+        email_client,
+        email_templates,
+        disposable_domains,
+        base_url,
+        retry_settings,
+        subscription_settings,
+        feature_flags,
+        hmac_secret,
+        base_path,
+        body
+    ),
+    fields(email = tracing::field::Empty, name = tracing::field::Empty)
 )]
 pub async fn subscribe(
+    req: HttpRequest,
+    tmpl: web::Data<Tera>,
     pool: web::Data<PgPool>,
     email_client: web::Data<EmailClient>,
+    email_templates: web::Data<EmailTemplates>,
+    disposable_domains: web::Data<DisposableDomains>,
     base_url: web::Data<ApplicationBaseUrl>,
-    form: web::Form<FormData>,
+    retry_settings: web::Data<ConfirmationRetrySettings>,
+    subscription_settings: web::Data<SubscriptionSettings>,
+    feature_flags: web::Data<FeatureFlags>,
+    hmac_secret: web::Data<HmacSecret>,
+    base_path: web::Data<BasePath>,
+    body: SubscribeBody,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(ValidationError)?;
+    let source = authorize_api_token(&req, &pool, subscription_settings.require_api_token).await?;
+    let form = body.0;
+    Span::current()
+        .record("email", display(&form.email))
+        .record("name", display(&form.name));
+    let render_html = !accepts_json(&req) && accepts_html(&req);
+    let mut context = template_context(&base_path);
+    context.insert("email", &form.email);
+    context.insert("name", &form.name);
+    let new_subscriber = match NewSubscriber::try_from(form) {
+        Ok(new_subscriber) => new_subscriber,
+        Err(error) if render_html => {
+            let errors: Vec<_> = error
+                .field_errors()
+                .into_iter()
+                .map(|(_, message)| message)
+                .collect();
+            context.insert("errors", &errors);
+            return render_page(
+                &tmpl,
+                "subscriptions/form.html",
+                &context,
+                StatusCode::BAD_REQUEST,
+            );
+        }
+        Err(error) => return Err(error.into()),
+    };
+    if disposable_domains.is_blocked(&new_subscriber.email) {
+        if render_html {
+            context.insert("errors", &[DisposableEmailDomain.to_string()]);
+            return render_page(
+                &tmpl,
+                "subscriptions/form.html",
+                &context,
+                StatusCode::BAD_REQUEST,
+            );
+        }
+        return Err(DisposableEmailDomain);
+    }
 
     // Transaction start
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool.")?;
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    if subscription_settings.max_subscribers > 0 {
+        let subscribers = lock_and_count_subscribers(&mut transaction)
+            .await
+            .context("Failed to count the current subscribers.")?;
+        if subscribers >= subscription_settings.max_subscribers {
+            return Err(SubscriberLimitReached);
+        }
+    }
+    let status = if feature_flags.double_opt_in {
+        SubscriptionStatus::PendingConfirmation
+    } else {
+        SubscriptionStatus::Confirmed
+    };
+    let subscriber_id =
+        insert_subscriber(&mut transaction, &new_subscriber, status, source.as_deref())
+            .await
+            .context("Failed to insert a new subscriber into the database.")?;
+    insert_tags(&mut transaction, &subscriber_id, &new_subscriber.tags)
         .await
-        .context("Failed to insert a new subscriber into the database.")?;
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, &subscriber_id, &subscription_token)
+        .context("Failed to store the tags of a new subscriber.")?;
+    let subscription_token = if status == SubscriptionStatus::PendingConfirmation {
+        let subscription_token = issue_confirmation_token(
+            &mut transaction,
+            &subscriber_id,
+            &subscription_settings,
+            &hmac_secret.0,
+        )
         .await
         .context("Failed to store the confirmation token for a new subscriber.")?;
+        schedule_confirmation_retry(
+            &mut transaction,
+            &retry_settings,
+            subscriber_id,
+            &subscription_token,
+        )
+        .await
+        .context("Failed to schedule a retry of the confirmation email.")?;
+        Some(subscription_token)
+    } else {
+        None
+    };
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
-    send_confirmation_email(
-        &email_client,
-        new_subscriber,
-        &base_url.0,
-        &subscription_token,
-    )
-    .await
-    .context("Failed to send the confirmation email.")?;
+    if let Some(subscription_token) = subscription_token {
+        match send_confirmation_email_with_retry(
+            &email_client,
+            &email_templates,
+            &retry_settings,
+            &new_subscriber.email,
+            &base_url.0,
+            &subscription_token,
+        )
+        .await
+        {
+            Ok(()) => cancel_confirmation_retry(&pool, subscriber_id).await,
+            Err(e) => tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to send the confirmation email. Leaving it to the outbox."
+            ),
+        }
+    }
 
+    if accepts_json(&req) {
+        let location = base_url
+            .0
+            .join(&format!("subscriptions/{subscriber_id}"))
+            .context("Failed to build the location of the new subscriber.")?;
+        return Ok(HttpResponse::Created()
+            .insert_header((header::LOCATION, location.as_str()))
+            .json(SubscribeResponse {
+                id: subscriber_id,
+                status,
+            }));
+    }
+    if render_html {
+        context.insert("confirmed", &(status == SubscriptionStatus::Confirmed));
+        return render_page(
+            &tmpl,
+            "subscriptions/check_your_email.html",
+            &context,
+            StatusCode::OK,
+        );
+    }
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Checks the API token of the request, and returns its `source`.
+/// `None` when no token is given and none is required.
+async fn authorize_api_token(
+    req: &HttpRequest,
+    pool: &PgPool,
+    required: bool,
+) -> Result<Option<String>, SubscribeError> {
+    let token = bearer_token(req.headers()).map_err(InvalidApiToken)?;
+    match token {
+        Some(token) => api_token_source(pool, token)
+            .await
+            .context("Failed to look up the API token.")?
+            .map(Some)
+            .ok_or_else(|| InvalidApiToken(anyhow::anyhow!("The API token is unknown."))),
+        None if required => Err(InvalidApiToken(anyhow::anyhow!(
+            "No API token has been given."
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn render_page(
+    tmpl: &Tera,
+    template: &str,
+    context: &tera::Context,
+    status: StatusCode,
+) -> Result<HttpResponse, SubscribeError> {
+    let body = tmpl
+        .render(template, context)
+        .with_context(|| format!("Failed to render `{}`.", template))?;
+    Ok(HttpResponse::build(status)
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+/// The JSON body returned to clients that accept JSON.
+#[derive(serde::Serialize)]
+pub struct SubscribeResponse {
+    id: Uuid,
+    status: SubscriptionStatus,
+}
+
 /// Errors that can occur when adding a new subscriber.
 /// This is a custom error type that wraps the various errors that can occur
 /// when adding a new subscriber.
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
-    /// The form data is invalid.
+    /// The form data is invalid. Every invalid field is listed.
     #[error(transparent)]
-    ValidationError(#[from] Box<dyn ParsingError>),
+    ValidationError(#[from] NewSubscriberError),
+    /// The API token is unknown, malformed, or missing while it is required.
+    #[error("Missing or invalid API token.")]
+    InvalidApiToken(#[source] anyhow::Error),
+    /// The email address belongs to a blocked disposable email provider.
+    #[error("Email addresses from this domain are not accepted. Please use another address.")]
+    DisposableEmailDomain,
+    /// The deployment already has as many subscribers as it allows.
+    #[error("The subscriber limit has been reached. No new subscriptions are accepted.")]
+    SubscriberLimitReached,
     /// An unexpected error occurred.
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
@@ -134,13 +438,54 @@ impl ResponseError for SubscribeError {
     /// # Status Codes
     ///
     /// - [ValidationError]: 400 Bad Request
+    /// - [DisposableEmailDomain]: 400 Bad Request
+    /// - [InvalidApiToken]: 401 Unauthorized
+    /// - [SubscriberLimitReached]: 403 Forbidden
     /// - [UnexpectedError]: 500 Internal Server Error
     fn status_code(&self) -> StatusCode {
         match self {
-            ValidationError(_) => StatusCode::BAD_REQUEST,
+            ValidationError(_) | DisposableEmailDomain => StatusCode::BAD_REQUEST,
+            InvalidApiToken(_) => StatusCode::UNAUTHORIZED,
+            SubscriberLimitReached => StatusCode::FORBIDDEN,
             UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
+
+    /// Lists the validation errors as JSON, and falls back to the plain-text message otherwise.
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            ValidationError(error) => {
+                let field_errors = error.field_errors();
+                HttpResponse::build(self.status_code()).json(ValidationErrorResponse {
+                    errors: field_errors.iter().map(|(_, m)| m.clone()).collect(),
+                    fields: field_errors.into_iter().collect(),
+                })
+            }
+            DisposableEmailDomain => {
+                let message = self.to_string();
+                HttpResponse::build(self.status_code()).json(ValidationErrorResponse {
+                    errors: vec![message.clone()],
+                    fields: BTreeMap::from([("email", message)]),
+                })
+            }
+            InvalidApiToken(_) => HttpResponse::build(self.status_code())
+                .insert_header((header::WWW_AUTHENTICATE, "Bearer"))
+                .body(self.to_string()),
+            SubscriberLimitReached | UnexpectedError(_) => {
+                HttpResponse::build(self.status_code()).body(self.to_string())
+            }
+        }
+    }
+}
+
+/// The JSON body returned when the form data is invalid.
+///
+/// `errors` lists the messages in the order of the form,
+/// and `fields` maps each invalid field to its message.
+#[derive(serde::Serialize)]
+pub struct ValidationErrorResponse {
+    errors: Vec<String>,
+    fields: BTreeMap<&'static str, String>,
 }
 
 impl Debug for SubscribeError {
@@ -149,9 +494,14 @@ impl Debug for SubscribeError {
     }
 }
 
-/// Wrapper around a [sqlx::Error] to provide a more descriptive error message.
-/// This error type is used when storing the subscription token fails.
-pub struct StoreTokenError(sqlx::Error);
+/// The error type for storing a new subscription token.
+#[derive(thiserror::Error)]
+pub enum StoreTokenError {
+    #[error("A database error occurred when storing the subscription token.")]
+    DatabaseError(#[source] sqlx::Error),
+    #[error("Every generated subscription token was already taken.")]
+    CollisionsExhausted,
+}
 
 impl Debug for StoreTokenError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -159,19 +509,30 @@ impl Debug for StoreTokenError {
     }
 }
 
-impl Display for StoreTokenError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "A database error occurred when storing the subscription token."
-        )
-    }
-}
+/// Counts the pending and confirmed subscribers.
+///
+/// A transaction-scoped advisory lock serializes concurrent subscriptions,
+/// so that two requests cannot both take the last free slot.
+#[tracing::instrument(name = "Count subscribers against the limit", skip(tx))]
+async fn lock_and_count_subscribers(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<i64, sqlx::Error> {
+    tx.execute(sqlx::query!(
+        "SELECT pg_advisory_xact_lock(hashtext('subscriptions_quota'))"
+    ))
+    .await?;
+    let record = sqlx::query!(
+        r#"
+        SELECT count(*) AS "count!"
+        FROM subscriptions
+        WHERE status <> $1
+        "#,
+        SubscriptionStatus::Unsubscribed as SubscriptionStatus,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
 
-impl std::error::Error for StoreTokenError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        Some(&self.0)
-    }
+    Ok(record.count)
 }
 
 #[tracing::instrument(
@@ -181,76 +542,227 @@ impl std::error::Error for StoreTokenError {
 async fn insert_subscriber(
     tx: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    status: SubscriptionStatus,
+    source: Option<&str>,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
+    // The confirmation email is sent right after the subscriber has been stored.
+    let (confirmed_source, consented_at, last_confirmation_sent_at) =
+        if status == SubscriptionStatus::Confirmed {
+            (Some("subscribe"), Some(Utc::now()), None)
+        } else {
+            (None, None, Some(Utc::now()))
+        };
 
     let query = sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (
+            id, email, name, subscribed_at, status, locale, timezone,
+            confirmed_source, consented_at, last_confirmation_sent_at, source
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
         "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        status as SubscriptionStatus,
+        new_subscriber.locale.as_ref().map(AsRef::as_ref),
+        new_subscriber.timezone.as_ref().map(AsRef::as_ref),
+        confirmed_source,
+        consented_at,
+        last_confirmation_sent_at,
+        source,
     );
-    tx.execute(query).await?;
+    timed_query("insert_subscriber", tx.execute(query)).await?;
 
     Ok(subscriber_id)
 }
 
-#[tracing::instrument(
-    name = "Store subscription token in the database",
-    skip(tx, subscription_token)
-)]
-async fn store_token(
+#[tracing::instrument(name = "Saving subscriber tags in the database", skip(tx, tags))]
+async fn insert_tags(
     tx: &mut Transaction<'_, Postgres>,
     subscriber_id: &Uuid,
-    subscription_token: &str,
-) -> Result<(), StoreTokenError> {
+    tags: &[SubscriberTag],
+) -> Result<(), sqlx::Error> {
+    let tags: Vec<_> = tags.iter().map(|t| t.as_ref().to_owned()).collect();
     let query = sqlx::query!(
         r#"
-        INSERT INTO subscription_tokens (subscriber_id, subscription_token)
-        values ($1, $2)
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT $1, tag FROM UNNEST($2::text[]) AS tag
+        ON CONFLICT DO NOTHING
         "#,
         subscriber_id,
-        subscription_token
+        &tags,
     );
-    tx.execute(query).await.map_err(StoreTokenError)?;
+    tx.execute(query).await?;
 
     Ok(())
 }
 
+/// How many tokens are generated before giving up on storing one.
+const MAX_TOKEN_ATTEMPTS: usize = 5;
+
+/// Stores a new random subscription token for the subscriber and returns it.
+pub(crate) async fn store_token(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+) -> Result<String, StoreTokenError> {
+    store_token_with_rng(tx, subscriber_id, &mut OsRng).await
+}
+
+/// Stores a new subscription token drawn from `rng` and returns it.
+///
+/// Tokens are unique, so a token that is already taken is replaced by a freshly generated one,
+/// up to [MAX_TOKEN_ATTEMPTS] times, rather than failing the request.
+#[tracing::instrument(name = "Store subscription token in the database", skip(tx, rng))]
+pub async fn store_token_with_rng<R: CryptoRng + RngCore>(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    rng: &mut R,
+) -> Result<String, StoreTokenError> {
+    for _ in 0..MAX_TOKEN_ATTEMPTS {
+        let subscription_token = generate_subscription_token_with_rng(rng);
+        let query = sqlx::query!(
+            r#"
+            INSERT INTO subscription_tokens (subscriber_id, subscription_token)
+            VALUES ($1, $2)
+            ON CONFLICT (subscription_token) DO NOTHING
+            "#,
+            subscriber_id,
+            subscription_token
+        );
+        let result = tx
+            .execute(query)
+            .await
+            .map_err(StoreTokenError::DatabaseError)?;
+        if result.rows_affected() == 1 {
+            return Ok(subscription_token);
+        }
+        tracing::warn!("The generated subscription token is already taken. Generating a new one.");
+    }
+    Err(StoreTokenError::CollisionsExhausted)
+}
+
+/// Creates the token of a confirmation link according to
+/// [SubscriptionSettings::confirmation_token_scheme]: a random token stored for the subscriber,
+/// or a signed token that is not stored.
+///
+/// A signed token is bound to the address it confirms, so it has to be issued
+/// after the subscriber, or their pending email change, has been written.
+pub(crate) async fn issue_confirmation_token(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    settings: &SubscriptionSettings,
+    hmac_secret: &Secret<String>,
+) -> Result<String, StoreTokenError> {
+    match settings.confirmation_token_scheme {
+        ConfirmationTokenScheme::Stored => store_token(tx, subscriber_id).await,
+        ConfirmationTokenScheme::Signed => {
+            let email = confirmation_address(&mut **tx, *subscriber_id)
+                .await
+                .map_err(StoreTokenError::DatabaseError)?
+                .ok_or(StoreTokenError::DatabaseError(sqlx::Error::RowNotFound))?;
+            let expires_at =
+                Utc::now() + chrono::Duration::seconds(settings.signed_token_ttl_seconds as i64);
+            Ok(sign_confirmation_token(
+                hmac_secret,
+                *subscriber_id,
+                &email,
+                expires_at,
+            ))
+        }
+    }
+}
+
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber)
+    skip(email_client, email_templates, recipient)
 )]
-async fn send_confirmation_email(
+pub(crate) async fn send_confirmation_email(
     email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
-    base_url: &str,
+    email_templates: &EmailTemplates,
+    recipient: &SubscriberEmail,
+    base_url: &Url,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
-    let confirmation_link = format!(
-        "{}/subscriptions/confirm?subscription_token={}",
-        base_url, subscription_token
-    );
-    let html_body = format!(
-        "Welcome to our newsletter!<br />\
-                Click <a href=\"{}\">here</a> to confirm your subscription.",
-        confirmation_link
-    );
-    let plain_body = format!(
-        "Welcome to our newsletter!\nvisit {} to confirm your subscription.",
-        confirmation_link
-    );
+) -> Result<(), anyhow::Error> {
+    let confirmation_link = confirmation_link(base_url, subscription_token)?;
+    let email = email_templates
+        .confirmation(confirmation_link.as_str())
+        .context("Failed to render the confirmation email.")?;
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+        .send_email(
+            recipient,
+            &email.subject,
+            &email.html_body,
+            &email.text_body,
+        )
+        .await?;
+    Ok(())
+}
+
+/// The link a subscriber follows to confirm their subscription.
+///
+/// `base_url` must end with a slash, as [crate::configuration::ApplicationSettings::public_url] does.
+pub(crate) fn confirmation_link(
+    base_url: &Url,
+    subscription_token: &str,
+) -> Result<Url, anyhow::Error> {
+    let mut link = base_url
+        .join("subscriptions/confirm")
+        .context("Failed to build the confirmation link.")?;
+    link.query_pairs_mut()
+        .append_pair("subscription_token", subscription_token);
+    Ok(link)
+}
+
+/// Sends a confirmation email, retrying with a short backoff
+/// up to [ConfirmationRetrySettings::immediate_attempts] times.
+#[tracing::instrument(
+    name = "Send a confirmation email with retries",
+    skip(email_client, email_templates, settings, recipient, subscription_token)
+)]
+pub(crate) async fn send_confirmation_email_with_retry(
+    email_client: &EmailClient,
+    email_templates: &EmailTemplates,
+    settings: &ConfirmationRetrySettings,
+    recipient: &SubscriberEmail,
+    base_url: &Url,
+    subscription_token: &str,
+) -> Result<(), anyhow::Error> {
+    let mut attempt = 1;
+    loop {
+        let outcome = send_confirmation_email(
+            email_client,
+            email_templates,
+            recipient,
+            base_url,
+            subscription_token,
+        )
+        .await;
+        match outcome {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= settings.immediate_attempts => return Err(e),
+            Err(e) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    attempt,
+                    "Failed to send a confirmation email. Retrying."
+                );
+                let backoff = settings
+                    .immediate_backoff_milliseconds
+                    .saturating_mul(1 << (attempt - 1).min(16));
+                tokio::time::sleep(std::time::Duration::from_millis(backoff)).await;
+                attempt += 1;
+            }
+        }
+    }
 }
 
-fn generate_subscription_token() -> String {
-    let mut rng = thread_rng();
+/// Generates a 25-character alphanumeric token. `rng` must be cryptographically secure,
+/// since the token is all it takes to confirm or manage a subscription.
+pub fn generate_subscription_token_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> String {
     std::iter::repeat_with(|| rng.sample(Alphanumeric))
         .map(char::from)
         .take(25)
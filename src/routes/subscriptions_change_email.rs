@@ -0,0 +1,238 @@
+use crate::configuration::{ConfirmationRetrySettings, SubscriptionSettings};
+use crate::confirmation_outbox::{cancel_confirmation_retry, schedule_confirmation_retry};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::email_templates::EmailTemplates;
+use crate::routes::subscriptions::{issue_confirmation_token, send_confirmation_email_with_retry};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use std::fmt::{Debug, Formatter};
+use uuid::Uuid;
+use ChangeEmailError::*;
+
+/// The form data passed to the change-email endpoint.
+///
+/// # Fields
+///
+/// - `subscription_token`: A token that was sent to the current email address.
+/// - `email`: The new email address.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    subscription_token: String,
+    email: String,
+}
+
+/// Request a change of a subscriber's email address.
+///
+/// The subscriber goes back to `pending_confirmation`, so neither address receives
+/// newsletter issues until the change is confirmed. Every existing token is revoked,
+/// and a confirmation link is sent to the new address.
+/// The stored address is only replaced once that link is followed.
+///
+/// # Request
+///
+/// ### URL-encoded Form Data
+///
+/// Field                | Description
+/// ---------------------|--------------------------------------------------
+/// `subscription_token` | A token that was sent to the current email address.
+/// `email`              | The new email address.
+///
+/// # Response
+///
+/// - **200 OK**: The confirmation email has been sent to the new address,
+///   or scheduled for a later attempt if the email provider is failing.
+/// - **400 Bad Request**: The new email address is invalid.
+/// - **401 Unauthorized**: The token is invalid.
+/// - **409 Conflict**: The new email address is already subscribed.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "Request a change of email address",
+    skip(
+        pool,
+        email_client,
+        email_templates,
+        base_url,
+        retry_settings,
+        settings,
+        hmac_secret,
+        form
+    )
+)]
+pub async fn change_email(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    email_templates: web::Data<EmailTemplates>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    retry_settings: web::Data<ConfirmationRetrySettings>,
+    settings: web::Data<SubscriptionSettings>,
+    hmac_secret: web::Data<HmacSecret>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, ChangeEmailError> {
+    let FormData {
+        subscription_token,
+        email,
+    } = form.0;
+    let new_email = SubscriberEmail::parse(email).map_err(|e| ValidationError(e.to_string()))?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let subscriber_id = lock_subscriber_from_token(&mut transaction, &subscription_token)
+        .await
+        .context("Failed to get subscriber from the database.")?
+        .ok_or(TokenNotFoundError)?;
+    if is_email_taken(&mut transaction, subscriber_id, &new_email)
+        .await
+        .context("Failed to check whether the new email address is subscribed.")?
+    {
+        return Err(EmailAlreadySubscribed);
+    }
+    request_email_change(&mut transaction, subscriber_id, &new_email)
+        .await
+        .context("Failed to record the email change.")?;
+    let new_token =
+        issue_confirmation_token(&mut transaction, &subscriber_id, &settings, &hmac_secret.0)
+            .await
+            .context("Failed to store the confirmation token for the new email address.")?;
+    schedule_confirmation_retry(&mut transaction, &retry_settings, subscriber_id, &new_token)
+        .await
+        .context("Failed to schedule a retry of the confirmation email.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to change an email address.")?;
+
+    match send_confirmation_email_with_retry(
+        &email_client,
+        &email_templates,
+        &retry_settings,
+        &new_email,
+        &base_url.0,
+        &new_token,
+    )
+    .await
+    {
+        Ok(()) => cancel_confirmation_retry(&pool, subscriber_id).await,
+        Err(e) => tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to send the confirmation email. Leaving it to the outbox."
+        ),
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The error type for the change-email endpoint.
+#[derive(thiserror::Error)]
+pub enum ChangeEmailError {
+    /// The new email address is invalid.
+    #[error("{0}")]
+    ValidationError(String),
+    /// The subscription token is invalid.
+    #[error("Failed to find subscriber. The token is invalid.")]
+    TokenNotFoundError,
+    /// Another subscriber already uses the new email address.
+    #[error("The new email address is already subscribed.")]
+    EmailAlreadySubscribed,
+    /// An error occurred while processing the request.
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for ChangeEmailError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ValidationError(_) => StatusCode::BAD_REQUEST,
+            TokenNotFoundError => StatusCode::UNAUTHORIZED,
+            EmailAlreadySubscribed => StatusCode::CONFLICT,
+            UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Debug for ChangeEmailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[tracing::instrument(name = "Lock subscriber from token", skip_all)]
+async fn lock_subscriber_from_token(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT s.id
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1
+        FOR UPDATE OF s
+        "#,
+        subscription_token
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(record.map(|r| r.id))
+}
+
+#[tracing::instrument(name = "Check whether an email address is subscribed", skip(tx, email))]
+async fn is_email_taken(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+) -> Result<bool, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM subscriptions WHERE email = $2 AND id <> $1
+        ) AS "taken!"
+        "#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(record.taken)
+}
+
+/// Stores the new address as pending and revokes every existing token.
+///
+/// `last_confirmation_sent_at` is reset so that the pending sweeper
+/// gives the subscriber a full TTL to confirm the new address.
+#[tracing::instrument(name = "Record an email change", skip(tx, new_email))]
+async fn request_email_change(
+    tx: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    new_email: &SubscriberEmail,
+) -> Result<(), sqlx::Error> {
+    tx.execute(sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = 'pending_confirmation',
+            pending_email = $2,
+            last_confirmation_sent_at = now()
+        WHERE id = $1
+        "#,
+        subscriber_id,
+        new_email.as_ref()
+    ))
+    .await?;
+    tx.execute(sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+        subscriber_id
+    ))
+    .await?;
+
+    Ok(())
+}
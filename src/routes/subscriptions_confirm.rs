@@ -1,13 +1,22 @@
-use crate::utils::error_chain_fmt;
+use crate::configuration::SubscriptionSettings;
+use crate::domain::SubscriptionStatus;
+use crate::startup::{BasePath, HmacSecret};
+use crate::utils::{error_chain_fmt, template_context};
+use actix_web::http::header::ContentType;
 use actix_web::http::StatusCode;
 use actix_web::{web, HttpResponse, ResponseError};
 use anyhow::Context;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use sqlx::{PgExecutor, PgPool};
 use std::fmt::{Debug, Formatter};
+use tera::Tera;
 use uuid::Uuid;
 use SubscribeConfirmError::*;
 
-/// The query parameters for the confirm endpoint.
+/// The query parameters for the confirm endpoint, also accepted as form data.
 ///
 /// # Fields
 ///
@@ -17,7 +26,17 @@ pub struct Parameters {
     subscription_token: String,
 }
 
-/// Confirm a pending subscriber.
+/// Confirm a pending subscriber by following the link of the confirmation email.
+///
+/// If [SubscriptionSettings::require_post_confirmation] is set, nothing is changed and
+/// a page with a button submitting the token to [confirm_via_post] is rendered instead,
+/// so that email scanners and link prefetchers cannot confirm the subscription.
+///
+/// Both stored tokens and signed tokens are accepted, whatever the configured
+/// [crate::configuration::ConfirmationTokenScheme], so that switching schemes
+/// does not invalidate the links that have already been sent.
+/// A signed token is verified without looking it up, against the address it confirms:
+/// requesting an email change invalidates the signed links sent before it.
 ///
 /// # Request
 ///
@@ -34,40 +53,97 @@ pub struct Parameters {
 ///
 /// # Response
 ///
-/// - **200 OK**: The subscriber has been confirmed.
+/// - **200 OK**: The subscriber has been confirmed, or the body is the confirm page.
 /// - **401 Unauthorized**: The token is invalid.
+/// - **410 Gone**: The token is a signed token that has expired.
 /// - **500 Internal Server Error**: An error occurred while processing the request.
 ///
 /// # Errors
 ///
-/// This function can return two types of errors:
+/// This function can return three types of errors:
 ///
 /// 1. [TokenNotFoundError]
 ///
 ///    The token is invalid. It will be converted into a 401 Unauthorized response.
 ///
-/// 2. [UnexpectedError]:
+/// 2. [TokenExpired]
+///
+///    The signed token has expired. It will be converted into a 410 Gone response.
+///
+/// 3. [UnexpectedError]:
 ///
 ///    An error occurred while processing the request.
 ///    It will be converted into a 500 Internal Server Error response.
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(pool, parameters))]
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(pool, hmac_secret, settings, tmpl, parameters, base_path)
+)]
 pub async fn confirm(
     pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    settings: web::Data<SubscriptionSettings>,
+    tmpl: web::Data<Tera>,
     parameters: web::Query<Parameters>,
+    base_path: web::Data<BasePath>,
 ) -> Result<HttpResponse, SubscribeConfirmError> {
-    let subscriber_id = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
-        .await
-        .context("Failed to get subscriber ID from the database.")?;
-
-    match subscriber_id {
-        Some(id) => {
-            confirm_subscriber(&pool, id)
-                .await
-                .context("Failed to set status `confirmed` in the database")?;
-            Ok(HttpResponse::Ok().finish())
-        }
-        None => Err(TokenNotFoundError),
+    if settings.require_post_confirmation {
+        let mut context = template_context(&base_path);
+        context.insert("subscription_token", &parameters.subscription_token);
+        let body = tmpl
+            .render("subscriptions/confirm.html", &context)
+            .context("Failed to render the confirm page.")?;
+        return Ok(HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body));
     }
+    confirm_token(&pool, &hmac_secret, &parameters.subscription_token).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// Confirm a pending subscriber with the token submitted from the confirm page.
+///
+/// The token itself authorizes the request, so it cannot be forged from another site
+/// without knowing it. Tokens are checked like in [confirm].
+///
+/// # Response
+///
+/// - **200 OK**: The subscriber has been confirmed.
+/// - **401 Unauthorized**: The token is invalid.
+/// - **410 Gone**: The token is a signed token that has expired.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber via POST",
+    skip(pool, hmac_secret, form)
+)]
+pub async fn confirm_via_post(
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    form: web::Form<Parameters>,
+) -> Result<HttpResponse, SubscribeConfirmError> {
+    confirm_token(&pool, &hmac_secret, &form.subscription_token).await?;
+    Ok(HttpResponse::Ok().finish())
+}
+
+async fn confirm_token(
+    pool: &PgPool,
+    hmac_secret: &HmacSecret,
+    token: &str,
+) -> Result<(), SubscribeConfirmError> {
+    let confirmed = if is_signed_token(token) {
+        let subscriber_id = signed_token_subscriber_id(token)?;
+        let email = confirmation_address(pool, subscriber_id)
+            .await
+            .context("Failed to get the address to confirm from the database.")?
+            .ok_or(TokenNotFoundError)?;
+        verify_signed_token(&hmac_secret.0, token, &email, Utc::now())?;
+        confirm_subscriber_by_id(pool, subscriber_id, &email).await
+    } else {
+        confirm_subscriber_by_token(pool, token).await
+    };
+    confirmed
+        .context("Failed to set status `confirmed` in the database")?
+        .ok_or(TokenNotFoundError)?;
+    Ok(())
 }
 
 /// The error type for the confirm endpoint.
@@ -76,6 +152,9 @@ pub enum SubscribeConfirmError {
     /// The subscription token is invalid.
     #[error("Failed to find subscriber. The token is invalid.")]
     TokenNotFoundError,
+    /// The signed token has expired.
+    #[error("The confirmation link has expired.")]
+    TokenExpired,
     /// An error occurred while processing the request.
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
@@ -85,6 +164,7 @@ impl ResponseError for SubscribeConfirmError {
     fn status_code(&self) -> StatusCode {
         match self {
             TokenNotFoundError => StatusCode::UNAUTHORIZED,
+            TokenExpired => StatusCode::GONE,
             UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -96,33 +176,247 @@ impl Debug for SubscribeConfirmError {
     }
 }
 
-#[tracing::instrument(name = "Mark subscriber as confirmed", skip(pool, subscriber_id))]
-async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"
-        UPDATE subscriptions SET status = 'confirmed' WHERE id = $1
-        "#,
+/// Signs a confirmation token for [crate::configuration::ConfirmationTokenScheme::Signed],
+/// formatted as `<subscriber id>.<expiry as a Unix timestamp>.<signature>`.
+///
+/// The signature also covers `email`, the address the link confirms, without it being
+/// part of the token: see [confirmation_address].
+pub(crate) fn sign_confirmation_token(
+    hmac_secret: &Secret<String>,
+    subscriber_id: Uuid,
+    email: &str,
+    expires_at: DateTime<Utc>,
+) -> String {
+    let payload = format!("{}.{}", subscriber_id, expires_at.timestamp());
+    let signature = hex::encode(mac(hmac_secret, &payload, email).finalize().into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+fn mac(hmac_secret: &Secret<String>, payload: &str, email: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length.");
+    mac.update(b"confirm:");
+    mac.update(payload.as_bytes());
+    // The payload never contains a colon, so the address cannot be shifted into it.
+    mac.update(b":");
+    mac.update(email.as_bytes());
+    mac
+}
+
+/// Returns the address that confirming the subscriber would confirm:
+/// the pending new address during an email change, their current address otherwise.
+#[tracing::instrument(name = "Get the address to confirm", skip(executor))]
+pub(crate) async fn confirmation_address(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT COALESCE(pending_email, email) AS "email!" FROM subscriptions WHERE id = $1"#,
         subscriber_id
     )
-    .execute(pool)
+    .fetch_optional(executor)
     .await?;
 
-    Ok(())
+    Ok(record.map(|r| r.email))
 }
 
-#[tracing::instrument(name = "Get subscriber_id from token", skip(pool, subscription_token))]
-async fn get_subscriber_id_from_token(
+/// Stored tokens are alphanumeric, so only signed tokens contain a dot.
+fn is_signed_token(token: &str) -> bool {
+    token.contains('.')
+}
+
+/// Returns the subscriber id a signed token claims to be for, before it is verified.
+fn signed_token_subscriber_id(token: &str) -> Result<Uuid, SubscribeConfirmError> {
+    let (subscriber_id, _) = token.split_once('.').ok_or(TokenNotFoundError)?;
+    Uuid::parse_str(subscriber_id).map_err(|_| TokenNotFoundError)
+}
+
+/// Returns the subscriber id of a signed token after checking its signature over `email`,
+/// then its expiry.
+fn verify_signed_token(
+    hmac_secret: &Secret<String>,
+    token: &str,
+    email: &str,
+    now: DateTime<Utc>,
+) -> Result<Uuid, SubscribeConfirmError> {
+    let (payload, signature) = token.rsplit_once('.').ok_or(TokenNotFoundError)?;
+    let signature = hex::decode(signature).map_err(|_| TokenNotFoundError)?;
+    mac(hmac_secret, payload, email)
+        .verify_slice(&signature)
+        .map_err(|_| TokenNotFoundError)?;
+
+    let (subscriber_id, expires_at) = payload.split_once('.').ok_or(TokenNotFoundError)?;
+    let subscriber_id = Uuid::parse_str(subscriber_id).map_err(|_| TokenNotFoundError)?;
+    let expires_at: i64 = expires_at.parse().map_err(|_| TokenNotFoundError)?;
+    if now.timestamp() >= expires_at {
+        return Err(TokenExpired);
+    }
+    Ok(subscriber_id)
+}
+
+/// Confirms the subscriber owning the token in a single statement.
+///
+/// Returns `None` if the token is unknown or the subscriber has unsubscribed since,
+/// so that an old link cannot subscribe them again. Clicking the link again keeps
+/// the source and consent time of the first confirmation.
+/// A pending email change is applied at the same time.
+#[tracing::instrument(name = "Mark subscriber as confirmed", skip(pool, subscription_token))]
+async fn confirm_subscriber_by_token(
     pool: &PgPool,
     subscription_token: &str,
 ) -> Result<Option<Uuid>, sqlx::Error> {
     let record = sqlx::query!(
         r#"
-        SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1
+        UPDATE subscriptions s
+        SET status = $2,
+            email = COALESCE(s.pending_email, s.email),
+            pending_email = NULL,
+            confirmed_source = CASE
+                WHEN s.status = $2 THEN s.confirmed_source ELSE 'email'
+            END,
+            consented_at = CASE
+                WHEN s.status = $2 THEN s.consented_at ELSE now()
+            END
+        FROM subscription_tokens t
+        WHERE t.subscriber_id = s.id AND t.subscription_token = $1 AND s.status <> $3
+        RETURNING s.id
+        "#,
+        subscription_token,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+        SubscriptionStatus::Unsubscribed as SubscriptionStatus,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|r| r.id))
+}
+
+/// Confirms the subscriber of a verified signed token, like [confirm_subscriber_by_token].
+///
+/// Returns `None` if the subscriber no longer exists or has unsubscribed since:
+/// a signed token cannot be revoked before it expires.
+/// Returns `None` as well if the address to confirm is no longer `email`,
+/// the one the token was verified against.
+#[tracing::instrument(name = "Mark subscriber as confirmed by id", skip(pool, email))]
+async fn confirm_subscriber_by_id(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+    email: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        UPDATE subscriptions s
+        SET status = $2,
+            email = COALESCE(s.pending_email, s.email),
+            pending_email = NULL,
+            confirmed_source = CASE
+                WHEN s.status = $2 THEN s.confirmed_source ELSE 'email'
+            END,
+            consented_at = CASE
+                WHEN s.status = $2 THEN s.consented_at ELSE now()
+            END
+        WHERE s.id = $1 AND s.status <> $3 AND COALESCE(s.pending_email, s.email) = $4
+        RETURNING s.id
         "#,
-        subscription_token
+        subscriber_id,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+        SubscriptionStatus::Unsubscribed as SubscriptionStatus,
+        email,
     )
     .fetch_optional(pool)
     .await?;
 
-    Ok(record.map(|r| r.subscriber_id))
+    Ok(record.map(|r| r.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+    fn secret() -> Secret<String> {
+        Secret::new("secret".into())
+    }
+
+    #[test]
+    fn a_signed_token_yields_its_subscriber_until_it_expires() {
+        let subscriber_id = Uuid::new_v4();
+        let now = Utc::now();
+        let token =
+            sign_confirmation_token(&secret(), subscriber_id, EMAIL, now + Duration::hours(1));
+
+        assert!(is_signed_token(&token));
+        assert_eq!(signed_token_subscriber_id(&token).unwrap(), subscriber_id);
+        assert_eq!(
+            verify_signed_token(&secret(), &token, EMAIL, now).unwrap(),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn a_signed_token_is_rejected_for_another_address() {
+        let now = Utc::now();
+        let token =
+            sign_confirmation_token(&secret(), Uuid::new_v4(), EMAIL, now + Duration::hours(1));
+
+        assert!(matches!(
+            verify_signed_token(&secret(), &token, "ursula.new@example.com", now),
+            Err(TokenNotFoundError)
+        ));
+    }
+
+    #[test]
+    fn an_expired_signed_token_is_rejected_as_expired() {
+        let now = Utc::now();
+        let token = sign_confirmation_token(&secret(), Uuid::new_v4(), EMAIL, now);
+
+        assert!(matches!(
+            verify_signed_token(&secret(), &token, EMAIL, now),
+            Err(TokenExpired)
+        ));
+    }
+
+    #[test]
+    fn a_tampered_signed_token_is_rejected_as_invalid() {
+        let now = Utc::now();
+        let token = sign_confirmation_token(&secret(), Uuid::new_v4(), EMAIL, now);
+        // Pushing the expiry back invalidates the signature.
+        let (subscriber_id, rest) = token.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let extended = format!(
+            "{}.{}.{}",
+            subscriber_id,
+            (now + Duration::days(365)).timestamp(),
+            signature
+        );
+
+        assert!(matches!(
+            verify_signed_token(&secret(), &extended, EMAIL, now),
+            Err(TokenNotFoundError)
+        ));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let now = Utc::now();
+        let token = sign_confirmation_token(
+            &Secret::new("another secret".into()),
+            Uuid::new_v4(),
+            EMAIL,
+            now + Duration::hours(1),
+        );
+
+        assert!(matches!(
+            verify_signed_token(&secret(), &token, EMAIL, now),
+            Err(TokenNotFoundError)
+        ));
+    }
+
+    #[test]
+    fn stored_tokens_are_not_mistaken_for_signed_ones() {
+        let token = crate::routes::generate_subscription_token_with_rng(&mut rand::rngs::OsRng);
+        assert!(!is_signed_token(&token));
+    }
 }
@@ -0,0 +1,189 @@
+use crate::configuration::{ConfirmationRetrySettings, SubscriptionSettings};
+use crate::confirmation_outbox::{cancel_confirmation_retry, schedule_confirmation_retry};
+use crate::domain::SubscriberEmail;
+use crate::email_client::EmailClient;
+use crate::email_templates::EmailTemplates;
+use crate::routes::subscriptions::{issue_confirmation_token, send_confirmation_email_with_retry};
+use crate::startup::{ApplicationBaseUrl, HmacSecret};
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::fmt::{Debug, Formatter};
+use uuid::Uuid;
+use ResendConfirmationError::*;
+
+/// The form data passed to the resend-confirmation endpoint.
+///
+/// # Fields
+///
+/// - `email`: The email address of the pending subscriber.
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    email: String,
+}
+
+/// Send a new confirmation link to a pending subscriber.
+///
+/// At most one confirmation email is sent per
+/// [SubscriptionSettings::confirmation_resend_cooldown_seconds], counting the one sent on
+/// subscription, so that the endpoint cannot be used to flood someone's inbox.
+/// Requests within the cooldown, and requests for addresses that are not pending,
+/// succeed without sending anything, so that the response does not reveal
+/// whether an address is subscribed.
+///
+/// # Request
+///
+/// ### URL-encoded Form Data
+///
+/// Field   | Description
+/// --------|---------------------------------------------
+/// `email` | The email address of the pending subscriber.
+///
+/// # Response
+///
+/// - **200 OK**: The request has been accepted.
+/// - **400 Bad Request**: The email address is invalid.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[allow(clippy::too_many_arguments)]
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(
+        pool,
+        email_client,
+        email_templates,
+        base_url,
+        retry_settings,
+        settings,
+        hmac_secret,
+        form
+    ),
+    fields(email = %form.email)
+)]
+pub async fn resend_confirmation(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<EmailClient>,
+    email_templates: web::Data<EmailTemplates>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    retry_settings: web::Data<ConfirmationRetrySettings>,
+    settings: web::Data<SubscriptionSettings>,
+    hmac_secret: web::Data<HmacSecret>,
+    form: web::Form<FormData>,
+) -> Result<HttpResponse, ResendConfirmationError> {
+    let email = SubscriberEmail::parse(form.0.email).map_err(|e| ValidationError(e.to_string()))?;
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+    let Some((subscriber_id, recipient)) = claim_confirmation_resend(
+        &mut transaction,
+        &email,
+        settings.confirmation_resend_cooldown_seconds,
+    )
+    .await
+    .context("Failed to check when the last confirmation email was sent.")?
+    else {
+        tracing::info!("The subscriber is not pending or is within the cooldown. Skipping.");
+        return Ok(HttpResponse::Ok().finish());
+    };
+    let recipient = SubscriberEmail::parse(recipient)
+        .context("The subscriber's stored email address is invalid.")?;
+    let subscription_token =
+        issue_confirmation_token(&mut transaction, &subscriber_id, &settings, &hmac_secret.0)
+            .await
+            .context("Failed to store the resent confirmation token.")?;
+    schedule_confirmation_retry(
+        &mut transaction,
+        &retry_settings,
+        subscriber_id,
+        &subscription_token,
+    )
+    .await
+    .context("Failed to schedule a retry of the confirmation email.")?;
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to resend a confirmation email.")?;
+
+    match send_confirmation_email_with_retry(
+        &email_client,
+        &email_templates,
+        &retry_settings,
+        &recipient,
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    {
+        Ok(()) => cancel_confirmation_retry(&pool, subscriber_id).await,
+        Err(e) => tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to send the confirmation email. Leaving it to the outbox."
+        ),
+    }
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The error type for the resend-confirmation endpoint.
+#[derive(thiserror::Error)]
+pub enum ResendConfirmationError {
+    /// The email address is invalid.
+    #[error("{0}")]
+    ValidationError(String),
+    /// An error occurred while processing the request.
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for ResendConfirmationError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ValidationError(_) => StatusCode::BAD_REQUEST,
+            UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Debug for ResendConfirmationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Records a new confirmation email for a pending subscriber whose last one was sent
+/// before the cooldown, and returns its id and the address to send the email to.
+///
+/// While an email change is pending, the link goes to the new address rather than
+/// to the one the request was made for, as only the new address can confirm the change.
+/// Checking and updating `last_confirmation_sent_at` in a single statement means
+/// that concurrent requests cannot both get past the cooldown.
+#[tracing::instrument(name = "Claim a confirmation resend", skip(tx, email))]
+async fn claim_confirmation_resend(
+    tx: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+    cooldown_seconds: u64,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET last_confirmation_sent_at = now()
+        WHERE email = $1
+          AND status = 'pending_confirmation'
+          AND (
+            last_confirmation_sent_at IS NULL
+            OR last_confirmation_sent_at <= now() - make_interval(secs => $2)
+          )
+        RETURNING id, COALESCE(pending_email, email) AS "recipient!"
+        "#,
+        email.as_ref(),
+        cooldown_seconds as f64,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(record.map(|r| (r.id, r.recipient)))
+}
@@ -0,0 +1,153 @@
+use crate::domain::SubscriptionStatus;
+use crate::utils::e500;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The query parameters for the subscription status endpoint.
+///
+/// # Fields
+///
+/// - `email`: The email address of the subscriber.
+/// - `token`: A subscription token that was sent to that address.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    email: String,
+    token: String,
+}
+
+/// The state of a subscription, as reported to the subscriber.
+#[derive(serde::Serialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionState {
+    Confirmed,
+    Pending,
+    Unsubscribed,
+}
+
+impl From<SubscriptionStatus> for SubscriptionState {
+    fn from(status: SubscriptionStatus) -> Self {
+        match status {
+            SubscriptionStatus::Confirmed => Self::Confirmed,
+            SubscriptionStatus::PendingConfirmation => Self::Pending,
+            SubscriptionStatus::Unsubscribed => Self::Unsubscribed,
+        }
+    }
+}
+
+/// The JSON body returned by the subscription status endpoint.
+#[derive(serde::Serialize)]
+pub struct SubscriptionStatusResponse {
+    status: SubscriptionState,
+}
+
+/// Check the state of a subscription.
+///
+/// The subscription token is required, so that the endpoint cannot be used
+/// to find out whether an arbitrary email address is subscribed.
+///
+/// # Request
+///
+/// ### Query Parameters
+///
+/// Field   | Description
+/// --------|--------------------------------------------------
+/// `email` | The email address of the subscriber.
+/// `token` | A subscription token that was sent to that address.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a [SubscriptionStatusResponse].
+/// - **404 Not Found**: The email address and token do not belong to the same subscriber.
+///   This is returned whether the email address is subscribed or not.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Check a subscription status", skip(pool, parameters))]
+pub async fn subscription_status(
+    pool: web::Data<PgPool>,
+    parameters: web::Query<Parameters>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT s.status AS "status: SubscriptionStatus"
+        FROM subscription_tokens t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE t.subscription_token = $1 AND lower(s.email) = lower($2)
+        "#,
+        parameters.token,
+        parameters.email.trim()
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to look up the subscription.")
+    .map_err(e500)?;
+
+    match record {
+        Some(record) => Ok(HttpResponse::Ok().json(SubscriptionStatusResponse {
+            status: record.status.into(),
+        })),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+/// Check the state of a subscription by the subscriber's id.
+///
+/// This is the resource that the `Location` header of a new subscription points to.
+/// The id is a random UUID that is only returned to the client that subscribed,
+/// so it cannot be used to find out whether an arbitrary email address is subscribed.
+///
+/// # Request
+///
+/// ### Path Parameters
+///
+/// Field | Description
+/// ------|--------------------------
+/// `id`  | The id of the subscriber.
+///
+/// # Response
+///
+/// - **200 OK**: The body is a [SubscriptionStatusResponse].
+/// - **404 Not Found**: There is no subscriber with the given id.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Check a subscription status by id", skip(pool))]
+pub async fn subscription_by_id(
+    pool: web::Data<PgPool>,
+    subscriber_id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let record = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1"#,
+        subscriber_id.into_inner()
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .context("Failed to look up the subscription.")
+    .map_err(e500)?;
+
+    match record {
+        Some(record) => Ok(HttpResponse::Ok().json(SubscriptionStatusResponse {
+            status: record.status.into(),
+        })),
+        None => Ok(HttpResponse::NotFound().finish()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_statuses_are_mapped_to_subscription_states() {
+        assert_eq!(
+            SubscriptionState::from(SubscriptionStatus::Confirmed),
+            SubscriptionState::Confirmed
+        );
+        assert_eq!(
+            SubscriptionState::from(SubscriptionStatus::PendingConfirmation),
+            SubscriptionState::Pending
+        );
+        assert_eq!(
+            SubscriptionState::from(SubscriptionStatus::Unsubscribed),
+            SubscriptionState::Unsubscribed
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::domain::SubscriberEmail;
+use crate::startup::HmacSecret;
+use crate::utils::error_chain_fmt;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::fmt::{Debug, Formatter};
+use url::Url;
+use UnsubscribeError::*;
+
+/// The query parameters for the unsubscribe endpoint.
+///
+/// # Fields
+///
+/// - `email`: The email address to unsubscribe.
+/// - `token`: The signature of `email`, as included in the `List-Unsubscribe` header.
+#[derive(serde::Deserialize)]
+pub struct Parameters {
+    email: String,
+    token: String,
+}
+
+/// Unsubscribe an email address in one click, as described by RFC 8058.
+///
+/// Mail clients send a `POST` request with the `List-Unsubscribe=One-Click` body
+/// to the link found in the `List-Unsubscribe` header of every newsletter issue.
+/// The link is signed with the `hmac_secret`, so it does not need to be stored.
+///
+/// # Request
+///
+/// ### Query Parameters
+///
+/// Field   | Description
+/// --------|-------------------------------------------------
+/// `email` | The email address to unsubscribe.
+/// `token` | The signature of `email`, generated by [UnsubscribeLinks].
+///
+/// # Response
+///
+/// - **200 OK**: The email address is no longer subscribed.
+///   Unsubscribing an address twice, or one that is not subscribed, also succeeds.
+/// - **401 Unauthorized**: The token does not match the email address.
+/// - **500 Internal Server Error**: An error occurred while processing the request.
+#[tracing::instrument(name = "Unsubscribe", skip(pool, hmac_secret, parameters))]
+pub async fn unsubscribe(
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+    parameters: web::Query<Parameters>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    if !verify_token(&hmac_secret.0, &parameters.email, &parameters.token) {
+        return Err(InvalidTokenError);
+    }
+    // Links sent before emails were normalized are signed over the address as it was typed.
+    // That address may still be stored as is, next to a newer row with the normalized
+    // address, so the normalized address is only used when there is no exact match.
+    let normalized_email = SubscriberEmail::parse(parameters.email.clone())
+        .map(SubscriberEmail::into_inner)
+        .unwrap_or_else(|_| parameters.email.clone());
+    // The subscriber's tokens are revoked, so that an old confirmation link
+    // cannot subscribe them again.
+    sqlx::query!(
+        r#"
+        WITH unsubscribed AS (
+            UPDATE subscriptions
+            SET status = 'unsubscribed'
+            WHERE email = COALESCE((SELECT email FROM subscriptions WHERE email = $1), $2)
+                AND status IN ('pending_confirmation', 'confirmed')
+            RETURNING id
+        )
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id IN (SELECT id FROM unsubscribed)
+        "#,
+        parameters.email,
+        normalized_email
+    )
+    .execute(pool.as_ref())
+    .await
+    .context("Failed to set status `unsubscribed` in the database.")?;
+
+    Ok(HttpResponse::Ok().finish())
+}
+
+/// The error type for the unsubscribe endpoint.
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    /// The token does not match the email address.
+    #[error("The unsubscribe link is invalid.")]
+    InvalidTokenError,
+    /// An error occurred while processing the request.
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            InvalidTokenError => StatusCode::UNAUTHORIZED,
+            UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Builds the signed one-click unsubscribe links included in newsletter issues.
+#[derive(Clone)]
+pub struct UnsubscribeLinks {
+    base_url: Url,
+    hmac_secret: Secret<String>,
+}
+
+impl UnsubscribeLinks {
+    /// `base_url` must end with a slash, as
+    /// [crate::configuration::ApplicationSettings::public_url] does.
+    pub fn new(base_url: Url, hmac_secret: Secret<String>) -> Self {
+        Self {
+            base_url,
+            hmac_secret,
+        }
+    }
+
+    pub fn link(&self, email: &str) -> Result<Url, anyhow::Error> {
+        let mut link = self
+            .base_url
+            .join("subscriptions/unsubscribe")
+            .context("Failed to build the unsubscribe link.")?;
+        link.query_pairs_mut()
+            .append_pair("email", email)
+            .append_pair("token", &sign(&self.hmac_secret, email));
+        Ok(link)
+    }
+}
+
+fn mac(hmac_secret: &Secret<String>, email: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length.");
+    mac.update(b"unsubscribe:");
+    mac.update(email.as_bytes());
+    mac
+}
+
+fn sign(hmac_secret: &Secret<String>, email: &str) -> String {
+    hex::encode(mac(hmac_secret, email).finalize().into_bytes())
+}
+
+/// Compares the token with the signature of `email` in constant time.
+fn verify_token(hmac_secret: &Secret<String>, email: &str, token: &str) -> bool {
+    let Ok(token) = hex::decode(token) else {
+        return false;
+    };
+    mac(hmac_secret, email).verify_slice(&token).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links() -> UnsubscribeLinks {
+        UnsubscribeLinks::new(
+            Url::parse("https://example.com/newsletter/").unwrap(),
+            Secret::new("secret".into()),
+        )
+    }
+
+    #[test]
+    fn the_link_carries_a_token_for_the_email_address() {
+        let link = links().link("ursula@example.com").unwrap();
+        assert_eq!(link.path(), "/newsletter/subscriptions/unsubscribe");
+
+        let query: std::collections::HashMap<_, _> = link.query_pairs().collect();
+        assert_eq!(query["email"], "ursula@example.com");
+        assert!(verify_token(
+            &Secret::new("secret".into()),
+            "ursula@example.com",
+            &query["token"]
+        ));
+    }
+
+    #[test]
+    fn a_token_does_not_unsubscribe_another_address() {
+        let token = sign(&Secret::new("secret".into()), "ursula@example.com");
+        assert!(!verify_token(
+            &Secret::new("secret".into()),
+            "someone-else@example.com",
+            &token
+        ));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = sign(&Secret::new("another secret".into()), "ursula@example.com");
+        assert!(!verify_token(
+            &Secret::new("secret".into()),
+            "ursula@example.com",
+            &token
+        ));
+    }
+
+    #[test]
+    fn a_malformed_token_is_rejected() {
+        assert!(!verify_token(
+            &Secret::new("secret".into()),
+            "ursula@example.com",
+            "not hex"
+        ));
+    }
+}
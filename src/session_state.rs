@@ -1,3 +1,4 @@
+use crate::authentication::LastLogin;
 use actix_session::{Session, SessionExt, SessionGetError, SessionInsertError};
 use actix_web::dev::Payload;
 use actix_web::{FromRequest, HttpRequest};
@@ -8,6 +9,12 @@ pub struct TypedSession(Session);
 
 impl TypedSession {
     const USER_ID_KEY: &'static str = "user_id";
+    const PENDING_TWO_FACTOR_USER_ID_KEY: &'static str = "pending_two_factor_user_id";
+    const TWO_FACTOR_FAILURES_KEY: &'static str = "two_factor_failures";
+    const TOTP_SETUP_SECRET_KEY: &'static str = "totp_setup_secret";
+    const PREVIOUS_LOGIN_KEY: &'static str = "previous_login";
+    const NEXT_PATH_KEY: &'static str = "next_path";
+    const CSRF_TOKEN_KEY: &'static str = "csrf_token";
 
     pub fn renew(&self) {
         self.0.renew();
@@ -21,6 +28,77 @@ impl TypedSession {
         self.0.get(Self::USER_ID_KEY)
     }
 
+    /// Stores the user who passed the password check but still has to provide a 2FA code.
+    pub fn insert_pending_two_factor_user_id(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PENDING_TWO_FACTOR_USER_ID_KEY, user_id)
+    }
+
+    pub fn get_pending_two_factor_user_id(&self) -> Result<Option<Uuid>, SessionGetError> {
+        self.0.get(Self::PENDING_TWO_FACTOR_USER_ID_KEY)
+    }
+
+    /// Also forgets the invalid codes submitted for the pending login.
+    pub fn remove_pending_two_factor_user_id(&self) {
+        self.0.remove(Self::PENDING_TWO_FACTOR_USER_ID_KEY);
+        self.0.remove(Self::TWO_FACTOR_FAILURES_KEY);
+    }
+
+    /// Stores how many invalid 2FA codes have been submitted for the pending login.
+    pub fn insert_two_factor_failures(&self, failures: u32) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::TWO_FACTOR_FAILURES_KEY, failures)
+    }
+
+    pub fn get_two_factor_failures(&self) -> Result<Option<u32>, SessionGetError> {
+        self.0.get(Self::TWO_FACTOR_FAILURES_KEY)
+    }
+
+    /// Stores the TOTP secret shown during setup until the user confirms it with a valid code.
+    pub fn insert_totp_setup_secret(&self, secret: &str) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::TOTP_SETUP_SECRET_KEY, secret)
+    }
+
+    pub fn get_totp_setup_secret(&self) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::TOTP_SETUP_SECRET_KEY)
+    }
+
+    pub fn remove_totp_setup_secret(&self) {
+        self.0.remove(Self::TOTP_SETUP_SECRET_KEY);
+    }
+
+    /// Stores the login that preceded the one which started this session.
+    pub fn insert_previous_login(&self, login: &LastLogin) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::PREVIOUS_LOGIN_KEY, login)
+    }
+
+    pub fn get_previous_login(&self) -> Result<Option<LastLogin>, SessionGetError> {
+        self.0.get(Self::PREVIOUS_LOGIN_KEY)
+    }
+
+    /// Stores where to send the user once the second login step has been completed.
+    pub fn insert_next_path(&self, path: &str) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::NEXT_PATH_KEY, path)
+    }
+
+    pub fn get_next_path(&self) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::NEXT_PATH_KEY)
+    }
+
+    pub fn remove_next_path(&self) {
+        self.0.remove(Self::NEXT_PATH_KEY);
+    }
+
+    /// Stores the token that forms submitted during this session have to echo back.
+    pub fn insert_csrf_token(&self, token: &str) -> Result<(), SessionInsertError> {
+        self.0.insert(Self::CSRF_TOKEN_KEY, token)
+    }
+
+    pub fn get_csrf_token(&self) -> Result<Option<String>, SessionGetError> {
+        self.0.get(Self::CSRF_TOKEN_KEY)
+    }
+
     pub fn log_out(&self) {
         self.0.purge();
     }
@@ -0,0 +1,93 @@
+use crate::configuration::SessionBackend;
+use actix_session::storage::{
+    CookieSessionStore, LoadError, RedisSessionStore, SaveError, SessionKey, SessionStore,
+    UpdateError,
+};
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use std::collections::HashMap;
+
+/// The session store in use, picked at startup according to [SessionBackend].
+#[derive(Clone)]
+pub enum SessionStoreBackend {
+    Redis(RedisSessionStore),
+    /// The session state is kept in the encrypted session cookie itself.
+    /// Sessions cannot be invalidated before they expire.
+    Cookie,
+}
+
+impl SessionStoreBackend {
+    /// Connects to Redis. If it cannot be reached, [SessionBackend::CookieFallback]
+    /// stores sessions in cookies until the next restart, and [SessionBackend::Redis] fails.
+    pub async fn connect(redis_url: &str, backend: SessionBackend) -> Result<Self, anyhow::Error> {
+        match RedisSessionStore::new(redis_url).await {
+            Ok(store) => Ok(Self::Redis(store)),
+            Err(e) if backend == SessionBackend::CookieFallback => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to connect to Redis. Sessions are stored in cookies instead."
+                );
+                Ok(Self::Cookie)
+            }
+            Err(e) => Err(e).context("Failed to connect to Redis for the session store."),
+        }
+    }
+}
+
+impl SessionStore for SessionStoreBackend {
+    async fn load(
+        &self,
+        session_key: &SessionKey,
+    ) -> Result<Option<HashMap<String, String>>, LoadError> {
+        match self {
+            Self::Redis(store) => store.load(session_key).await,
+            Self::Cookie => CookieSessionStore::default().load(session_key).await,
+        }
+    }
+
+    async fn save(
+        &self,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        match self {
+            Self::Redis(store) => store.save(session_state, ttl).await,
+            Self::Cookie => CookieSessionStore::default().save(session_state, ttl).await,
+        }
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: HashMap<String, String>,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        match self {
+            Self::Redis(store) => store.update(session_key, session_state, ttl).await,
+            Self::Cookie => {
+                CookieSessionStore::default()
+                    .update(session_key, session_state, ttl)
+                    .await
+            }
+        }
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> anyhow::Result<()> {
+        match self {
+            Self::Redis(store) => store.update_ttl(session_key, ttl).await,
+            Self::Cookie => {
+                CookieSessionStore::default()
+                    .update_ttl(session_key, ttl)
+                    .await
+            }
+        }
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> anyhow::Result<()> {
+        match self {
+            Self::Redis(store) => store.delete(session_key).await,
+            Self::Cookie => CookieSessionStore::default().delete(session_key).await,
+        }
+    }
+}
@@ -1,53 +1,116 @@
 use crate::authentication::reject_anonymous_user;
-use crate::configuration::Settings;
+use crate::client_ip::TrustedProxy;
+use crate::configuration::{
+    ConfirmationRetrySettings, CookieSameSite, FeatureFlags, MaintenanceSettings,
+    NewsletterSettings, PasswordPolicySettings, PreferencesSettings, SessionBackend, Settings,
+    SubscriptionSettings, WorkerSettings,
+};
+use crate::disposable_domains::DisposableDomains;
 use crate::email_client::EmailClient;
+use crate::email_templates::EmailTemplates;
+use crate::flash_cookies::FlashCookieStore;
+use crate::https::{enforce_https, HstsPolicy};
+use crate::issue_delivery_worker::WorkerState;
+use crate::maintenance::{reject_mutations_during_maintenance, MaintenanceMode};
+use crate::metrics::{record_request_metrics, Metrics};
+use crate::request_timeout::{enforce_request_timeout, RequestTimeout};
 use crate::routes::*;
-use actix_session::storage::RedisSessionStore;
+use crate::session_store::SessionStoreBackend;
+use actix_cors::Cors;
 use actix_session::SessionMiddleware;
 use actix_web::cookie::Key;
 use actix_web::dev::Server;
-use actix_web::{web, App, HttpServer};
+use actix_web::http::header::{self, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::{Compress, Condition, DefaultHeaders, ErrorHandlers};
+use actix_web::{guard, web, App, HttpServer};
 use actix_web_flash_messages::storage::CookieMessageStore;
 use actix_web_flash_messages::FlashMessagesFramework;
 use actix_web_lab::middleware::from_fn;
+use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
-use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use std::time::Duration;
 use tera::Tera;
 use tracing_actix_web::TracingLogger;
+use url::Url;
 
 pub struct Application {
     pub port: u16,
     server: Server,
     connection_pool: web::Data<PgPool>,
+    worker_state: WorkerState,
+    email_client: EmailClient,
 }
 
 impl Application {
     pub async fn build(configurations: &Settings) -> Result<Self, anyhow::Error> {
-        let connection_pool = PgPoolOptions::new()
-            .acquire_timeout(std::time::Duration::from_secs(2))
-            .connect_lazy_with(configurations.database.with_db());
-        let connection_pool = web::Data::new(connection_pool);
-        let email_client = configurations.email_client.client();
+        let connection_pool = web::Data::new(configurations.database.connection_pool());
+        let replica_pool = configurations
+            .database
+            .replica_connection_pool()
+            .context("Failed to parse the read replica URL.")?
+            .unwrap_or_else(|| connection_pool.get_ref().clone());
+        let email_client = configurations.email_client.confirmation_client();
+        if configurations.email_client.verify_on_startup {
+            email_client
+                .verify()
+                .await
+                .context("The email client settings failed the startup verification.")?;
+        }
 
-        let templates_engine = Tera::new("templates/**/*").expect("Failed to parsing templates.");
+        let templates_engine = load_templates(&configurations.application.templates_directory)?;
+        let email_templates = EmailTemplates::new(&configurations.email_templates)?;
+        let disposable_domains = DisposableDomains::load(
+            configurations
+                .subscriptions
+                .disposable_domains_file
+                .as_deref(),
+        )?;
 
         let address = format!(
             "{}:{}",
             configurations.application.host, configurations.application.port
         );
 
+        let worker_state = WorkerState::default();
+
         let listener = TcpListener::bind(address)?;
         let port = listener.local_addr().unwrap().port();
         let server = run(
             listener,
             connection_pool.clone(),
-            email_client,
+            replica_pool,
+            email_client.clone(),
             templates_engine,
-            configurations.application.base_url.to_owned(),
+            email_templates,
+            disposable_domains,
+            configurations.application.public_url(),
+            configurations.application.mount_path(),
             configurations.application.hmac_secret.to_owned(),
             configurations.redis_url.to_owned(),
+            configurations.session_backend,
+            worker_state.clone(),
+            configurations.application.max_payload_bytes,
+            configurations.application.allowed_origins.clone(),
+            configurations.application.cookie_secure,
+            configurations.application.cookie_same_site,
+            configurations.newsletter.clone(),
+            configurations.preferences.clone(),
+            configurations.password_policy.clone(),
+            configurations.subscriptions.clone(),
+            configurations.confirmation_retry.clone(),
+            configurations.features.clone(),
+            configurations.email_client.newsletter_client(),
+            configurations.email_client.readiness_check_interval(),
+            configurations.worker.clone(),
+            configurations.maintenance.clone(),
+            configurations.application.enforce_https,
+            configurations.application.hsts_max_age_seconds,
+            &configurations.application.content_security_policy,
+            configurations.application.trusted_proxy,
+            configurations.application.request_timeout_seconds,
         )
         .await?;
 
@@ -55,6 +118,8 @@ impl Application {
             port,
             server,
             connection_pool,
+            worker_state,
+            email_client,
         })
     }
 
@@ -69,55 +134,326 @@ impl Application {
     pub fn get_connection_pool(&self) -> web::Data<PgPool> {
         self.connection_pool.clone()
     }
+
+    /// The state to be updated by the worker and reported by `/admin/system/worker/status`.
+    pub fn get_worker_state(&self) -> WorkerState {
+        self.worker_state.clone()
+    }
+
+    /// The client used to send confirmation emails, to be shared with the confirmation outbox.
+    pub fn get_email_client(&self) -> EmailClient {
+        self.email_client.clone()
+    }
+}
+
+/// Cross-origin access to the public subscription API.
+///
+/// Credentials are not allowed, and the middleware is only applied to the subscription
+/// resources, so the cookie-based `/admin` routes stay same-origin.
+/// The `Location` of a new subscriber is exposed, so that clients can follow it.
+fn cors(allowed_origins: &[String]) -> Cors {
+    allowed_origins
+        .iter()
+        .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+        .allowed_methods(["GET", "POST"])
+        .allowed_headers([header::CONTENT_TYPE, header::ACCEPT, header::AUTHORIZATION])
+        .expose_headers([header::LOCATION])
+        .max_age(3600)
 }
 
-pub struct ApplicationBaseUrl(pub String);
+/// Headers hardening every response against MIME sniffing, framing, and injected content.
+///
+/// They are only added when the handler has not set them itself.
+fn security_headers(content_security_policy: HeaderValue) -> DefaultHeaders {
+    DefaultHeaders::new()
+        .add((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .add((header::X_FRAME_OPTIONS, "DENY"))
+        .add((header::CONTENT_SECURITY_POLICY, content_security_policy))
+}
+
+/// The page templates rendered by the routes.
+const REQUIRED_TEMPLATES: &[&str] = &[
+    "home.html",
+    "login.html",
+    "login_two_factor.html",
+    "subscriptions/form.html",
+    "subscriptions/check_your_email.html",
+    "subscriptions/confirm.html",
+    "preferences/center.html",
+    "preferences/unsubscribed.html",
+    "admin/dashboard.html",
+    "admin/logout.html",
+    "admin/newsletter.html",
+    "admin/password.html",
+    "admin/two_factor_setup.html",
+];
+
+/// Loads the page templates, failing if any template rendered by a route is missing,
+/// so that a broken deployment is caught at startup rather than on the first request.
+fn load_templates(directory: &str) -> Result<Tera, anyhow::Error> {
+    let tera = Tera::new(&format!("{}/**/*", directory))
+        .with_context(|| format!("Failed to parse the templates in `{}`.", directory))?;
+    let available: Vec<&str> = tera.get_template_names().collect();
+    let missing: Vec<&str> = REQUIRED_TEMPLATES
+        .iter()
+        .copied()
+        .filter(|name| !available.contains(name))
+        .collect();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Missing templates in `{}`: {}.",
+            directory,
+            missing.join(", ")
+        );
+    }
+    Ok(tera)
+}
+
+/// The public URL of the application, including its base path, used to build links in emails.
+pub struct ApplicationBaseUrl(pub Url);
+/// The path the application is mounted at, e.g. `/newsletter`, or empty at the root.
+/// Redirects and the links of the rendered pages are prefixed with it.
+pub struct BasePath(pub String);
 pub struct HmacSecret(pub Secret<String>);
+/// The pool used by read-only handlers: the read replica if configured, the primary otherwise.
+pub struct ReadReplicaPool(pub PgPool);
+/// The client used to send newsletter issues, with a longer timeout than confirmation emails.
+pub struct NewsletterEmailClient(pub EmailClient);
 
+#[allow(clippy::too_many_arguments)]
 async fn run(
     listener: TcpListener,
     connection_pool: web::Data<PgPool>,
+    replica_pool: PgPool,
     email_client: EmailClient,
     templates_engine: Tera,
-    base_url: String,
+    email_templates: EmailTemplates,
+    disposable_domains: DisposableDomains,
+    base_url: Url,
+    base_path: String,
     hmac_secret: Secret<String>,
     redis_url: Secret<String>,
+    session_backend: SessionBackend,
+    worker_state: WorkerState,
+    max_payload_bytes: usize,
+    allowed_origins: Vec<String>,
+    cookie_secure: bool,
+    cookie_same_site: CookieSameSite,
+    newsletter_settings: NewsletterSettings,
+    preferences_settings: PreferencesSettings,
+    password_policy: PasswordPolicySettings,
+    subscription_settings: SubscriptionSettings,
+    confirmation_retry_settings: ConfirmationRetrySettings,
+    feature_flags: FeatureFlags,
+    newsletter_email_client: EmailClient,
+    email_provider_check_interval: Duration,
+    worker_settings: WorkerSettings,
+    maintenance_settings: MaintenanceSettings,
+    enforce_https_redirect: bool,
+    hsts_max_age_seconds: u64,
+    content_security_policy: &str,
+    trusted_proxy: bool,
+    request_timeout_seconds: u64,
 ) -> Result<Server, anyhow::Error> {
+    let replica_pool = web::Data::new(ReadReplicaPool(replica_pool));
+    let dependency_checks = web::Data::new(DependencyChecks::new(
+        redis_url.expose_secret(),
+        email_client.clone(),
+        email_provider_check_interval,
+    )?);
     let email_client = web::Data::new(email_client);
     let templates_engine = web::Data::new(templates_engine);
+    let email_templates = web::Data::new(email_templates);
+    let disposable_domains = web::Data::new(disposable_domains);
+    let unsubscribe_links =
+        web::Data::new(UnsubscribeLinks::new(base_url.clone(), hmac_secret.clone()));
     let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let mount_path = web::Data::new(BasePath(base_path.clone()));
+    let worker_state = web::Data::new(worker_state);
+    let trusted_proxy = web::Data::new(TrustedProxy(trusted_proxy));
+    let newsletter_settings = web::Data::new(newsletter_settings);
+    let preferences_settings = web::Data::new(preferences_settings);
+    let password_policy = web::Data::new(password_policy);
+    let subscription_settings = web::Data::new(subscription_settings);
+    let confirmation_retry_settings = web::Data::new(confirmation_retry_settings);
+    let feature_flags = web::Data::new(feature_flags);
+    let newsletter_email_client = web::Data::new(NewsletterEmailClient(newsletter_email_client));
+    let worker_settings = web::Data::new(worker_settings);
+    let request_timeout =
+        web::Data::new(RequestTimeout(Duration::from_secs(request_timeout_seconds)));
+    let hsts_policy = web::Data::new(HstsPolicy {
+        max_age_seconds: hsts_max_age_seconds,
+    });
+    let content_security_policy = HeaderValue::from_str(content_security_policy)
+        .context("The content security policy is not a valid header value.")?;
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
-    let message_store = CookieMessageStore::builder(secret_key.clone()).build();
+    let hmac_secret = web::Data::new(HmacSecret(hmac_secret));
+    let message_store = FlashCookieStore::new(
+        CookieMessageStore::builder(secret_key.clone()).build(),
+        cookie_secure,
+        cookie_same_site.into(),
+    );
     let message_framework = FlashMessagesFramework::builder(message_store).build();
-    let redis_store = RedisSessionStore::new(redis_url.expose_secret()).await?;
+    let session_store =
+        SessionStoreBackend::connect(redis_url.expose_secret(), session_backend).await?;
+    // Without the flag, mutations are let through as when it cannot be read.
+    let maintenance_mode =
+        match MaintenanceMode::new(redis_url.expose_secret(), &maintenance_settings).await {
+            Ok(maintenance_mode) => Some(web::Data::new(maintenance_mode)),
+            Err(e) if session_backend == SessionBackend::CookieFallback => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to connect to Redis. Maintenance mode is unavailable."
+                );
+                None
+            }
+            Err(e) => {
+                return Err(e).context("Failed to connect to Redis for the maintenance-mode flag.")
+            }
+        };
+    let request_metrics =
+        web::Data::new(Metrics::new().context("Failed to register the request metrics.")?);
     let server = HttpServer::new(move || {
-        App::new()
+        let app = App::new()
             .wrap(TracingLogger::default())
+            // Pages are gzip/brotli/zstd compressed when the client's `Accept-Encoding` allows it.
+            .wrap(Compress::default())
+            .wrap(security_headers(content_security_policy.clone()))
+            .wrap(ErrorHandlers::new().handler(StatusCode::METHOD_NOT_ALLOWED, method_not_allowed))
+            .wrap(from_fn(reject_mutations_during_maintenance))
+            .wrap(Condition::new(
+                enforce_https_redirect,
+                from_fn(enforce_https),
+            ))
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
+            .wrap(
+                SessionMiddleware::builder(session_store.clone(), secret_key.clone())
+                    .cookie_secure(cookie_secure)
+                    .cookie_same_site(cookie_same_site.into())
+                    .cookie_http_only(true)
+                    .build(),
+            )
+            // Registered after every other middleware, so that the timeout covers the whole pipeline.
+            .wrap(Condition::new(
+                request_timeout_seconds > 0,
+                from_fn(enforce_request_timeout),
             ))
-            .route("/", web::get().to(home))
-            .route("/login", web::get().to(login_form))
-            .route("/login", web::post().to(login))
-            .route("/health_check", web::get().to(health_check))
-            .route("/subscriptions", web::post().to(subscribe))
-            .route("/subscriptions/confirm", web::get().to(confirm))
+            // Outside of the timeout, so that requests cut short by it are recorded as well.
+            .wrap(from_fn(record_request_metrics))
             .service(
-                web::scope("/admin")
-                    .wrap(from_fn(reject_anonymous_user))
-                    .route("/dashboard", web::get().to(admin_dashboard))
-                    .route("/password", web::get().to(change_password_form))
-                    .route("/password", web::post().to(change_password))
-                    .route("/newsletters", web::get().to(publish_newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter))
-                    .route("/logout", web::post().to(log_out)),
+                web::scope(&base_path)
+                    .route("/", web::get().to(home))
+                    .route("/login", web::get().to(login_form))
+                    .route("/login", web::post().to(login))
+                    .route("/login/2fa", web::get().to(login_two_factor_form))
+                    .route("/login/2fa", web::post().to(login_two_factor))
+                    .route("/health_check", web::get().to(health_check))
+                    .route("/health_check/details", web::get().to(health_check_details))
+                    .route("/ready", web::get().to(ready))
+                    .route("/metrics", web::get().to(metrics))
+                    .service(
+                        web::resource("/subscriptions")
+                            .wrap(cors(&allowed_origins))
+                            .route(web::post().to(subscribe)),
+                    )
+                    .route("/subscriptions/confirm", web::get().to(confirm))
+                    .route("/subscriptions/confirm", web::post().to(confirm_via_post))
+                    .route("/subscriptions/resend", web::post().to(resend_confirmation))
+                    .route("/subscriptions/status", web::get().to(subscription_status))
+                    .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+                    .route("/subscriptions/change-email", web::post().to(change_email))
+                    .service(
+                        web::resource("/subscriptions/{id}")
+                            .wrap(cors(&allowed_origins))
+                            .route(web::get().to(subscription_by_id)),
+                    )
+                    .route("/preferences", web::get().to(preference_center))
+                    .route("/preferences", web::post().to(update_preferences))
+                    .route(
+                        "/preferences/unsubscribe",
+                        web::post().to(unsubscribe_from_preferences),
+                    )
+                    .route("/preferences/rotate-token", web::post().to(rotate_token))
+                    .service(
+                        web::scope("/admin")
+                            .wrap(from_fn(reject_anonymous_user))
+                            .route("/dashboard", web::get().to(admin_dashboard))
+                            .route("/me", web::get().to(current_user))
+                            .route("/password", web::get().to(change_password_form))
+                            .route("/password", web::post().to(change_password))
+                            .route("/newsletters", web::get().to(publish_newsletter_form))
+                            .route("/newsletters", web::post().to(publish_newsletter))
+                            .route("/newsletters/draft", web::get().to(get_newsletter_draft))
+                            .route("/newsletters/draft", web::post().to(save_newsletter_draft))
+                            .route("/newsletters/{id}", web::get().to(get_newsletter_issue))
+                            .route(
+                                "/newsletters/{id}/cancel",
+                                web::post().to(cancel_newsletter_issue),
+                            )
+                            .route(
+                                "/newsletters/{id}/resend-failed",
+                                web::post().to(resend_failed_deliveries),
+                            )
+                            .route("/security/rehash", web::post().to(rehash_all_passwords))
+                            .route("/2fa/setup", web::get().to(two_factor_setup_form))
+                            .route("/2fa/setup", web::post().to(enable_two_factor))
+                            .route("/subscribers", web::get().to(list_subscribers))
+                            .route(
+                                "/subscribers/import",
+                                web::post()
+                                    .guard(guard::fn_guard(is_csv))
+                                    .to(import_subscribers_csv),
+                            )
+                            .route("/subscribers/import", web::post().to(import_subscribers))
+                            .route("/subscribers/status", web::get().to(subscriber_status))
+                            .route("/subscribers/{id}", web::patch().to(update_subscriber))
+                            .route("/users", web::get().to(list_users))
+                            .route("/users", web::post().to(create_user))
+                            .route("/users/{id}/deactivate", web::post().to(deactivate_user))
+                            .route("/dead-letters", web::get().to(list_dead_letters))
+                            .route(
+                                "/dead-letters/{id}/requeue",
+                                web::post().to(requeue_dead_letter),
+                            )
+                            .route("/system/worker/status", web::get().to(worker_status))
+                            .route("/worker/dispatch", web::post().to(dispatch_pending_tasks))
+                            .route("/logout", web::get().to(log_out_form))
+                            .route("/logout", web::post().to(log_out)),
+                    ),
             )
+            .default_service(web::to(not_found))
             .app_data(connection_pool.clone())
+            .app_data(replica_pool.clone())
             .app_data(email_client.clone())
+            .app_data(dependency_checks.clone())
             .app_data(templates_engine.clone())
+            .app_data(email_templates.clone())
+            .app_data(disposable_domains.clone())
             .app_data(base_url.clone())
+            .app_data(mount_path.clone())
+            .app_data(hmac_secret.clone())
+            .app_data(unsubscribe_links.clone())
+            .app_data(worker_state.clone())
+            .app_data(newsletter_settings.clone())
+            .app_data(preferences_settings.clone())
+            .app_data(password_policy.clone())
+            .app_data(subscription_settings.clone())
+            .app_data(confirmation_retry_settings.clone())
+            .app_data(feature_flags.clone())
+            .app_data(newsletter_email_client.clone())
+            .app_data(worker_settings.clone())
+            .app_data(hsts_policy.clone())
+            .app_data(trusted_proxy.clone())
+            .app_data(request_timeout.clone())
+            .app_data(request_metrics.clone())
+            .app_data(web::PayloadConfig::new(max_payload_bytes))
+            .app_data(web::FormConfig::default().limit(max_payload_bytes))
+            .app_data(web::JsonConfig::default().limit(max_payload_bytes));
+        match &maintenance_mode {
+            Some(maintenance_mode) => app.app_data(maintenance_mode.clone()),
+            None => app,
+        }
     })
     .listen(listener)?
     .run();
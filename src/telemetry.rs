@@ -1,3 +1,10 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::TracerProvider;
+use opentelemetry_sdk::{runtime, Resource};
+use std::future::Future;
+use std::time::Instant;
 use tokio::task::JoinHandle;
 use tracing::subscriber::set_global_default;
 use tracing::Subscriber;
@@ -14,20 +21,60 @@ use tracing_subscriber::{EnvFilter, Registry};
 /// - `name`: The name of the service.
 /// - `env_filter`: The default filter used to determine the verbosity of the logs.
 /// - `sink`: The sink to write the logs to.
+/// - `otlp_endpoint`: If set, spans are also exported to this OTLP/HTTP endpoint.
 ///
 /// # Returns
 /// The tracing subscriber.
-pub fn get_subscriber<T>(name: String, env_filter: String, sink: T) -> impl Subscriber + Send + Sync
+///
+/// # Panics
+/// This function panics if it fails to build the OTLP exporter.
+/// The exporter runs on the Tokio runtime, so it must be called from within one
+/// when `otlp_endpoint` is set.
+pub fn get_subscriber<T>(
+    name: String,
+    env_filter: String,
+    sink: T,
+    otlp_endpoint: Option<&str>,
+) -> impl Subscriber + Send + Sync
 where
     T: for<'a> MakeWriter<'a> + Send + Sync + 'static,
 {
     let env_filter =
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(env_filter));
+    let otlp_layer = otlp_endpoint.map(|endpoint| {
+        let tracer = otlp_tracer_provider(&name, endpoint)
+            .expect("Failed to build the OTLP exporter.")
+            .tracer(name.clone());
+        tracing_opentelemetry::layer().with_tracer(tracer)
+    });
     let formatting_layer = BunyanFormattingLayer::new(name, sink);
     Registry::default()
         .with(env_filter)
         .with(JsonStorageLayer)
         .with(formatting_layer)
+        .with(otlp_layer)
+}
+
+/// Builds a tracer provider that exports spans in batches to the given OTLP/HTTP endpoint,
+/// and registers it as the global provider so that it can be flushed on shutdown
+/// with [opentelemetry::global::shutdown_tracer_provider].
+fn otlp_tracer_provider(
+    name: &str,
+    endpoint: &str,
+) -> Result<TracerProvider, opentelemetry::trace::TraceError> {
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()?;
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new([KeyValue::new(
+            "service.name",
+            name.to_owned(),
+        )]))
+        .build();
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    Ok(provider)
 }
 
 /// Sets the given tracing subscriber as the global subscriber.
@@ -65,3 +112,57 @@ where
     let current_span = tracing::Span::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(f))
 }
+
+/// Awaits a database query and emits an event with its name and how long it took.
+///
+/// # Parameters
+///
+/// - `name`: The name of the query, recorded as the `query` field.
+/// - `query`: The future running the query.
+///
+/// # Examples
+///
+/// ```ignore
+/// let record = timed_query("get_subscriber", sqlx::query!(...).fetch_one(pool)).await?;
+/// ```
+pub async fn timed_query<F, T, E>(name: &'static str, query: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = query.await;
+    tracing::info!(
+        query = name,
+        elapsed_milliseconds = start.elapsed().as_secs_f64() * 1000.0,
+        success = result.is_ok(),
+        "Query completed."
+    );
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn the_subscriber_builds_with_an_otlp_endpoint() {
+        let subscriber = get_subscriber(
+            "test".into(),
+            "info".into(),
+            std::io::sink,
+            Some("http://localhost:4318/v1/traces"),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("exported span").in_scope(|| tracing::info!("Hello"));
+        });
+    }
+
+    #[tokio::test]
+    async fn timed_query_returns_the_query_result() {
+        let ok: Result<u32, String> = timed_query("ok", async { Ok(42) }).await;
+        let err: Result<u32, String> = timed_query("err", async { Err("failed".into()) }).await;
+        assert_eq!(ok, Ok(42));
+        assert_eq!(err, Err("failed".into()));
+    }
+}
@@ -1,5 +1,6 @@
-use actix_web::http::header;
-use actix_web::HttpResponse;
+use crate::startup::BasePath;
+use actix_web::http::header::{self, Header, Quality};
+use actix_web::{HttpRequest, HttpResponse};
 use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages, Level};
 use std::fmt::Formatter;
 
@@ -37,6 +38,42 @@ pub fn see_other(location: &str) -> HttpResponse {
         .finish()
 }
 
+/// A `303 See Other` to `path`, a path of the application such as `/login`,
+/// under the base path the application is mounted at.
+pub fn see_other_in_app(base_path: &BasePath, path: &str) -> HttpResponse {
+    see_other(&format!("{}{}", base_path.0, path))
+}
+
+/// A page template context with the `base_path` that the links of the page are prefixed with.
+pub fn template_context(base_path: &BasePath) -> tera::Context {
+    let mut context = tera::Context::new();
+    context.insert("base_path", &base_path.0);
+    context
+}
+
+/// Whether the client explicitly accepts a JSON response, and does not prefer HTML.
+pub fn accepts_json(req: &HttpRequest) -> bool {
+    accepted_quality(req, "application/json")
+        .is_some_and(|json| accepted_quality(req, "text/html").is_none_or(|html| json >= html))
+}
+
+/// Whether the client explicitly accepts an HTML response, as browsers submitting a form do.
+pub fn accepts_html(req: &HttpRequest) -> bool {
+    accepted_quality(req, "text/html").is_some()
+}
+
+/// The quality the `Accept` header of the request gives to `essence`.
+/// `None` when the media type is not listed, or is excluded with `q=0`.
+fn accepted_quality(req: &HttpRequest, essence: &str) -> Option<Quality> {
+    header::Accept::parse(req)
+        .ok()?
+        .iter()
+        .filter(|q| q.item.essence_str() == essence)
+        .map(|q| q.quality)
+        .max()
+        .filter(|quality| *quality > Quality::ZERO)
+}
+
 pub fn set_flash_messages(
     context: &mut tera::Context,
     flash_messages: IncomingFlashMessages,
@@ -49,3 +86,52 @@ pub fn set_flash_messages(
         .collect();
     context.insert("flash_messages", &flash_messages);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts_html, accepts_json};
+    use actix_web::test::TestRequest;
+
+    fn request(accept: &str) -> actix_web::HttpRequest {
+        TestRequest::default()
+            .insert_header(("Accept", accept))
+            .to_http_request()
+    }
+
+    #[test]
+    fn listed_media_types_are_accepted() {
+        let req = request("application/json");
+        assert!(accepts_json(&req));
+        assert!(!accepts_html(&req));
+
+        let req = request("text/html,application/xhtml+xml,*/*;q=0.8");
+        assert!(!accepts_json(&req));
+        assert!(accepts_html(&req));
+    }
+
+    #[test]
+    fn media_types_with_a_zero_quality_are_not_accepted() {
+        let req = request("application/json;q=0, text/html");
+        assert!(!accepts_json(&req));
+        assert!(accepts_html(&req));
+
+        let req = request("application/json, text/html;q=0");
+        assert!(accepts_json(&req));
+        assert!(!accepts_html(&req));
+    }
+
+    #[test]
+    fn json_is_not_chosen_when_html_is_preferred() {
+        assert!(!accepts_json(&request(
+            "application/json;q=0.5, text/html;q=0.9"
+        )));
+        assert!(accepts_json(&request("application/json, text/html")));
+    }
+
+    #[test]
+    fn a_missing_accept_header_accepts_neither() {
+        let req = TestRequest::default().to_http_request();
+        assert!(!accepts_json(&req));
+        assert!(!accepts_html(&req));
+    }
+}
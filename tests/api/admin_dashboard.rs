@@ -1,4 +1,7 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, replica_url, spawn_app,
+    spawn_app_with_config,
+};
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
@@ -9,7 +12,7 @@ async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
     let response = app.get_admin_dashboard().await;
 
     // Assert
-    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdashboard");
 }
 
 #[tokio::test]
@@ -37,3 +40,203 @@ async fn logout_clears_session_state() {
     let html_page = app.get_login_html().await;
     assert!(html_page.contains("<p><i>You have successfully logged out.</i></p>"));
 }
+
+#[tokio::test]
+async fn logging_out_requires_a_csrf_token() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/logout", app.address))
+        .form(&serde_json::json!({}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_dashboard_shows_the_previous_login() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(!html_page.contains("Last login:"));
+    app.post_logout().await;
+    let first_login_at = sqlx::query!(
+        "SELECT last_login_at FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap()
+    .last_login_at
+    .unwrap();
+
+    // Act
+    app.test_user.login(&app).await;
+    let html_page = app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(html_page.contains(&format!(
+        "Last login: {} from 127.0.0.1",
+        first_login_at.format("%Y-%m-%d %H:%M:%S UTC")
+    )));
+    let second_login_at = sqlx::query!(
+        "SELECT last_login_at FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap()
+    .last_login_at
+    .unwrap();
+    assert!(second_login_at > first_login_at);
+}
+
+#[tokio::test]
+async fn the_login_ip_is_taken_from_x_forwarded_for_behind_a_trusted_proxy() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.trusted_proxy = true).await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/login", app.address))
+        .header("X-Forwarded-For", "203.0.113.7")
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let record = sqlx::query!(
+        "SELECT last_login_ip FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(record.last_login_ip.as_deref(), Some("203.0.113.7"));
+}
+
+#[tokio::test]
+async fn a_spoofed_x_forwarded_for_is_ignored_without_a_trusted_proxy() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    app.api_client
+        .post(format!("{}/login", app.address))
+        .header("X-Forwarded-For", "203.0.113.7")
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let record = sqlx::query!(
+        "SELECT last_login_ip FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(record.last_login_ip.as_deref(), Some("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn the_admin_dashboard_is_compressed_when_the_client_accepts_gzip() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/dashboard", app.address))
+        .header("Accept-Encoding", "gzip")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Encoding"], "gzip");
+}
+
+#[tokio::test]
+async fn the_admin_dashboard_is_not_compressed_without_accept_encoding() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_admin_dashboard().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Content-Encoding").is_none());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_dashboard_works_with_a_read_replica_configured() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.database.replica_url = Some(replica_url(&c.database))).await;
+
+    // Act
+    app.test_user.login(&app).await;
+    let html_page = app.get_admin_dashboard_html().await;
+
+    // Assert
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn the_dashboard_is_served_as_json_when_requested() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/admin/dashboard", app.address))
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/json"
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["username"], app.test_user.username);
+    assert_eq!(body["subscribers"]["confirmed"], 1);
+    assert_eq!(body["subscribers"]["pending_confirmation"], 0);
+    assert_eq!(body["subscribers"]["unsubscribed"], 0);
+    assert_eq!(body["queue_depth"]["ready"], 0);
+    assert_eq!(body["queue_depth"]["postponed"], 0);
+    assert_eq!(body["queue_depth"]["pending_fan_out"], 0);
+    assert!(body["issues_with_errors"].as_array().unwrap().is_empty());
+    assert!(body.get("last_login").is_some());
+}
@@ -0,0 +1,32 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_get_the_current_user() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_admin_me().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fme");
+}
+
+#[tokio::test]
+async fn the_current_user_is_the_logged_in_one() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_admin_me().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["user_id"], app.test_user.user_id.to_string());
+    assert_eq!(body["username"], app.test_user.username);
+    assert_eq!(body["two_factor_enabled"], false);
+    assert!(body["last_login"].is_null());
+    assert_eq!(body["csrf_token"], app.csrf_token().await);
+}
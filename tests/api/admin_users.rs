@@ -0,0 +1,174 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+const SECOND_ADMIN_PASSWORD: &str = "A-long-enough-passw0rd";
+
+async fn create_second_admin(app: &TestApp) -> Uuid {
+    let response = app
+        .post_create_user(&serde_json::json!({
+            "username": "second-admin",
+            "password": SECOND_ADMIN_PASSWORD,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let body: serde_json::Value = response.json().await.unwrap();
+    body["user_id"].as_str().unwrap().parse().unwrap()
+}
+
+async fn login_as_second_admin(app: &TestApp) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "username": "second-admin",
+        "password": SECOND_ADMIN_PASSWORD,
+    }))
+    .await
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_users() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let list = app.get_users().await;
+    let create = app
+        .post_create_user(&serde_json::json!({
+            "username": "intruder",
+            "password": SECOND_ADMIN_PASSWORD,
+        }))
+        .await;
+    let deactivate = app.post_deactivate_user(app.test_user.user_id).await;
+
+    // Assert
+    assert_is_redirect_to(&list, "/login?next=%2Fadmin%2Fusers");
+    assert_is_redirect_to(&create, "/login");
+    assert_is_redirect_to(&deactivate, "/login");
+}
+
+#[tokio::test]
+async fn a_new_admin_can_log_in_until_they_are_deactivated() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let second_admin_id = create_second_admin(&app).await;
+    app.post_logout().await;
+
+    let response = login_as_second_admin(&app).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    app.post_logout().await;
+
+    // Act
+    app.test_user.login(&app).await;
+    let response = app.post_deactivate_user(second_admin_id).await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.post_logout().await;
+
+    // Assert
+    let response = login_as_second_admin(&app).await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn users_are_listed_with_their_status() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let second_admin_id = create_second_admin(&app).await;
+    app.post_deactivate_user(second_admin_id).await;
+
+    // Act
+    let response = app.get_users().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let users: Vec<serde_json::Value> = response.json().await.unwrap();
+    let second_admin = users
+        .iter()
+        .find(|u| u["username"] == "second-admin")
+        .unwrap();
+    assert_eq!(second_admin["is_active"], false);
+    let test_user = users
+        .iter()
+        .find(|u| u["username"] == app.test_user.username.as_str())
+        .unwrap();
+    assert_eq!(test_user["is_active"], true);
+}
+
+#[tokio::test]
+async fn you_cannot_deactivate_yourself() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_deactivate_user(app.test_user.user_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn invalid_new_users_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            serde_json::json!({ "username": "new-admin", "password": "short" }),
+            400,
+            "a password that is too short",
+        ),
+        (
+            serde_json::json!({ "username": "new-admin", "password": "password123456" }),
+            400,
+            "a common password",
+        ),
+        (
+            serde_json::json!({ "username": "  ", "password": SECOND_ADMIN_PASSWORD }),
+            400,
+            "an empty username",
+        ),
+        (
+            serde_json::json!({
+                "username": &app.test_user.username,
+                "password": SECOND_ADMIN_PASSWORD,
+            }),
+            409,
+            "a username that is already taken",
+        ),
+    ];
+
+    for (body, expected_status, description) in test_cases {
+        // Act
+        let response = app.post_create_user(&body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            expected_status,
+            "The API did not fail with {} when the payload had {}.",
+            expected_status,
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn deactivating_an_unknown_user_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_deactivate_user(Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
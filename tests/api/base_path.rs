@@ -0,0 +1,162 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_unconfirmed_subscriber, spawn_app_with_config,
+    subscription_token, TestApp,
+};
+use newsletter_lib::domain::SubscriptionStatus;
+
+async fn spawn_app_under_base_path() -> TestApp {
+    spawn_app_with_config(|c| c.application.base_path = "/newsletter/".into()).await
+}
+
+/// The values of every `attribute` in a page, with the HTML escaping of Tera undone.
+fn attribute_values(html: &str, attribute: &str) -> Vec<String> {
+    let marker = format!(r#"{}=""#, attribute);
+    html.split(&marker)
+        .skip(1)
+        .filter_map(|rest| rest.split_once('"'))
+        .map(|(value, _)| value.replace("&#x2F;", "/").replace("&amp;", "&"))
+        .collect()
+}
+
+/// The value of the hidden CSRF token field of a page.
+fn csrf_token(html: &str) -> String {
+    let marker = r#"name="csrf_token" value=""#;
+    let (_, rest) = html.split_once(marker).unwrap();
+    rest.split_once('"').unwrap().0.to_owned()
+}
+
+async fn get_html(app: &TestApp, path: &str) -> String {
+    let response = app
+        .api_client
+        .get(format!("http://127.0.0.1:{}{}", app.port, path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 200, "GET {path} failed.");
+    response.text().await.unwrap()
+}
+
+async fn submit(app: &TestApp, action: &str, form: &serde_json::Value) -> reqwest::Response {
+    app.api_client
+        .post(format!("http://127.0.0.1:{}{}", app.port, action))
+        .form(form)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_admin_forms_work_under_a_base_path() {
+    // Arrange
+    let app = spawn_app_under_base_path().await;
+
+    // Act - Part 1 - Get redirected to the login form
+    let response = app
+        .api_client
+        .get(format!("{}/admin/newsletters", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/newsletter/login?next=%2Fadmin%2Fnewsletters");
+
+    // Act - Part 2 - Log in through the login form
+    let login_page = get_html(&app, "/newsletter/login?next=%2Fadmin%2Fnewsletters").await;
+    let action = &attribute_values(&login_page, "action")[0];
+    assert_eq!(action, "/newsletter/login");
+    let response = submit(
+        &app,
+        action,
+        &serde_json::json!({
+            "username": app.test_user.username,
+            "password": app.test_user.password,
+            "next": "/admin/newsletters",
+        }),
+    )
+    .await;
+    assert_is_redirect_to(&response, "/newsletter/admin/newsletters");
+
+    // Act - Part 3 - Save a draft through the publish form
+    let newsletter_page = get_html(&app, "/newsletter/admin/newsletters").await;
+    let formaction = &attribute_values(&newsletter_page, "formaction")[0];
+    assert_eq!(formaction, "/newsletter/admin/newsletters/draft");
+    let response = submit(
+        &app,
+        formaction,
+        &serde_json::json!({
+            "title": "Newsletter title",
+            "csrf_token": csrf_token(&newsletter_page),
+        }),
+    )
+    .await;
+    assert_is_redirect_to(&response, "/newsletter/admin/newsletters");
+
+    // Act - Part 4 - Log out through the dashboard
+    let dashboard_page = get_html(&app, "/newsletter/admin/dashboard").await;
+    assert!(attribute_values(&dashboard_page, "href")
+        .iter()
+        .all(|href| href.starts_with("/newsletter/admin/")));
+    let action = &attribute_values(&dashboard_page, "action")[0];
+    assert_eq!(action, "/newsletter/admin/logout");
+    let response = submit(
+        &app,
+        action,
+        &serde_json::json!({ "csrf_token": csrf_token(&dashboard_page) }),
+    )
+    .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/newsletter/login");
+    let draft = sqlx::query!("SELECT title FROM newsletter_drafts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(draft.title, "Newsletter title");
+}
+
+#[tokio::test]
+async fn the_preference_forms_work_under_a_base_path() {
+    // Arrange
+    let app = spawn_app_under_base_path().await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let token = subscription_token(&links.html);
+
+    // Act - Part 1 - Save the preferences
+    let center_page = get_html(&app, &format!("/newsletter/preferences?token={token}")).await;
+    let actions = attribute_values(&center_page, "action");
+    assert_eq!(
+        actions,
+        [
+            format!("/newsletter/preferences?token={token}"),
+            format!("/newsletter/preferences/unsubscribe?token={token}"),
+        ]
+    );
+    let response = submit(
+        &app,
+        &actions[0],
+        &serde_json::json!({
+            "name": "Ursula K. Le Guin",
+            "content_format": "text",
+            "frequency": "weekly",
+        }),
+    )
+    .await;
+    assert_is_redirect_to(&response, &actions[0]);
+
+    // Act - Part 2 - Unsubscribe
+    let response = submit(&app, &actions[1], &serde_json::json!({})).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved =
+        sqlx::query!(r#"SELECT name, status AS "status: SubscriptionStatus" FROM subscriptions"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(saved.name, "Ursula K. Le Guin");
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+}
@@ -0,0 +1,170 @@
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app, subscription_token, TestApp};
+use newsletter_lib::domain::SubscriptionStatus;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const NEW_EMAIL: &str = "ursula.new@example.com";
+
+/// Creates a confirmed subscriber and returns their email address and subscription token.
+async fn create_confirmed_subscriber_with_token(app: &TestApp) -> (String, String) {
+    let confirmation_links = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let token = subscription_token(&confirmation_links.html);
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .email;
+    (email, token)
+}
+
+#[tokio::test]
+async fn the_new_address_is_confirmed_before_the_email_is_changed() {
+    // Arrange
+    let app = spawn_app().await;
+    let (old_email, token) = create_confirmed_subscriber_with_token(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act 1 - Request the change
+    let response = app.post_change_email(&token, NEW_EMAIL).await;
+
+    // Assert 1 - The confirmation goes to the new address, the row keeps the old one
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], NEW_EMAIL);
+    let saved = sqlx::query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.email, old_email);
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+    assert_eq!(saved.pending_email.as_deref(), Some(NEW_EMAIL));
+
+    // Act 2 - Follow the link sent to the new address
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert 2 - The email has been changed
+    let saved = sqlx::query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.email, NEW_EMAIL);
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn the_old_token_stops_working_once_a_change_is_requested() {
+    // Arrange
+    let app = spawn_app().await;
+    let (old_email, token) = create_confirmed_subscriber_with_token(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_change_email(&token, NEW_EMAIL)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token={}",
+        app.address, token
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved =
+        sqlx::query!(r#"SELECT email, status AS "status: SubscriptionStatus" FROM subscriptions"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(saved.email, old_email);
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn an_invalid_new_email_is_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, token) = create_confirmed_subscriber_with_token(&app).await;
+
+    // Act
+    let response = app
+        .post_change_email(&token, "definitely-not-an-email")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let saved = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_change_email("unknowntoken", NEW_EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn an_address_used_by_another_subscriber_is_rejected_with_a_409() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, token) = create_confirmed_subscriber_with_token(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions(&serde_json::json!({ "name": "someone", "email": NEW_EMAIL }))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.post_change_email(&token, NEW_EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+}
@@ -1,4 +1,4 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, captured_logs, spawn_app};
 use rand::distributions::Alphanumeric;
 use rand::Rng;
 use uuid::Uuid;
@@ -12,14 +12,14 @@ async fn you_must_be_logged_in_to_see_the_change_password_form() {
     let response = app.get_change_password().await;
 
     // Assert
-    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fpassword");
 }
 
 #[tokio::test]
 async fn you_must_be_logged_in_to_change_your_password() {
     // Arrange
     let app = spawn_app().await;
-    let new_password = Uuid::new_v4().to_string();
+    let new_password = generate_strong_password();
 
     // Act
     let response = app
@@ -38,8 +38,8 @@ async fn you_must_be_logged_in_to_change_your_password() {
 async fn new_password_fields_must_match() {
     // Arrange
     let app = spawn_app().await;
-    let new_password = Uuid::new_v4().to_string();
-    let another_new_password = Uuid::new_v4().to_string();
+    let new_password = generate_strong_password();
+    let another_new_password = generate_strong_password();
 
     // Act 1 - Login
     app.test_user.login(&app).await;
@@ -66,7 +66,7 @@ async fn current_password_must_be_valid() {
     // Arrange
     let app = spawn_app().await;
     let wrong_password = Uuid::new_v4().to_string();
-    let new_password = Uuid::new_v4().to_string();
+    let new_password = generate_strong_password();
 
     // Act 1 - Login
     app.test_user.login(&app).await;
@@ -90,7 +90,7 @@ async fn current_password_must_be_valid() {
 async fn new_password_must_be_at_least_12_characters_long() {
     // Arrange
     let app = spawn_app().await;
-    let invalid_new_password = generate_random_alphanumeric(11);
+    let invalid_new_password = generate_password_of_length(11);
 
     // Act 1 - Login
     app.test_user.login(&app).await;
@@ -116,7 +116,7 @@ async fn new_password_must_be_at_least_12_characters_long() {
 async fn new_password_must_be_at_most_128_characters_long() {
     // Arrange
     let app = spawn_app().await;
-    let invalid_new_password = generate_random_alphanumeric(129);
+    let invalid_new_password = generate_password_of_length(129);
 
     // Act 1 - Login
     app.test_user.login(&app).await;
@@ -142,7 +142,7 @@ async fn new_password_must_be_at_most_128_characters_long() {
 async fn changing_password_works() {
     // Arrange
     let app = spawn_app().await;
-    let new_password = Uuid::new_v4().to_string();
+    let new_password = generate_strong_password();
 
     // Act 1 - Login
     let login_body = serde_json::json!({
@@ -167,7 +167,7 @@ async fn changing_password_works() {
 
     // Act 4 - Check if the user is logged out
     let response = app.get_admin_dashboard().await;
-    assert_is_redirect_to(&response, "/login");
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdashboard");
 
     // Act 5 - Login with new password
     let login_body = serde_json::json!({
@@ -178,10 +178,152 @@ async fn changing_password_works() {
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
 
-fn generate_random_alphanumeric(length: usize) -> String {
-    rand::thread_rng()
+#[tokio::test]
+async fn changing_password_requires_a_csrf_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password = generate_strong_password();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/admin/password", app.address))
+        .form(&serde_json::json!({
+            "current_password": app.test_user.password,
+            "new_password": &new_password,
+            "new_password_confirm": &new_password,
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn changing_password_rejects_an_invalid_csrf_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password = generate_strong_password();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": app.test_user.password,
+            "new_password": &new_password,
+            "new_password_confirm": &new_password,
+            "csrf_token": "not-the-session-token",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    app.post_logout().await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": app.test_user.username,
+            "password": &new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn passwords_never_appear_in_the_logs_when_changing_them() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password = generate_strong_password();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_confirm": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let logs = captured_logs();
+    assert!(!logs.contains(&app.test_user.password));
+    assert!(!logs.contains(&new_password));
+}
+
+/// A password that satisfies every rule of the password policy.
+fn generate_strong_password() -> String {
+    format!("{}-Aa1", Uuid::new_v4())
+}
+
+/// A password of the given length that satisfies every other rule of the password policy.
+fn generate_password_of_length(length: usize) -> String {
+    let random: String = rand::thread_rng()
         .sample_iter(&Alphanumeric)
-        .take(length)
+        .take(length - 4)
         .map(char::from)
-        .collect()
+        .collect();
+    format!("Aa1-{}", random)
+}
+
+#[tokio::test]
+async fn every_validation_failure_is_reported_in_a_single_message() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password = generate_password_of_length(11);
+    let another_new_password = generate_password_of_length(11);
+    app.test_user.login(&app).await;
+
+    // Act 1 - Try changing password
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": Uuid::new_v4().to_string(),
+            "new_password": &new_password,
+            "new_password_confirm": &another_new_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Act 2 - Follow redirect
+    let html_page = app.get_change_password_html().await;
+
+    // Assert
+    assert!(html_page.contains(
+        "<p><i>You entered two different new passwords - the field values must match. \
+         The new password must be at least 12 characters long. \
+         The current password is incorrect.</i></p>"
+    ));
+    assert_eq!(html_page.matches("<p><i>").count(), 1);
+}
+
+#[tokio::test]
+async fn new_password_must_satisfy_the_password_policy() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act 1 - Try changing password
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": "password123456",
+            "new_password_confirm": "password123456",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    // Act 2 - Follow redirect
+    let html_page = app.get_change_password_html().await;
+
+    // Assert
+    assert!(html_page.contains(
+        "<p><i>The new password must contain both lowercase and uppercase letters. \
+         The new password must contain at least one symbol. \
+         The new password is too common.</i></p>"
+    ));
 }
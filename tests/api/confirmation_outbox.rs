@@ -0,0 +1,123 @@
+use crate::helpers::spawn_app_with_config;
+use std::collections::HashSet;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn a_failed_confirmation_email_is_retried_until_it_succeeds() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.confirmation_retry.base_delay_milliseconds = 0;
+        c.confirmation_retry.max_jitter_milliseconds = 0;
+        c.confirmation_retry.immediate_attempts = 1;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions_with_str(body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act
+    app.dispatch_all_pending_confirmations().await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[2];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+
+    let outbox = sqlx::query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 0);
+}
+
+#[tokio::test]
+async fn retries_are_spread_by_jitter() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.confirmation_retry.base_delay_milliseconds = 1_000;
+        c.confirmation_retry.max_jitter_milliseconds = 60_000;
+        c.confirmation_retry.immediate_attempts = 1;
+    })
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for i in 0..5 {
+        let body = format!("name=le%20guin&email=reader{}%40gmail.com", i);
+        let response = app
+            .api_client
+            .post(format!("{}/subscriptions", &app.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    // Assert
+    let retries = sqlx::query!("SELECT next_attempt_at FROM confirmation_email_outbox")
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(retries.len(), 5);
+    let distinct: HashSet<_> = retries.iter().map(|r| r.next_attempt_at).collect();
+    assert!(
+        distinct.len() > 1,
+        "All retries were scheduled at the same instant."
+    );
+}
+
+#[tokio::test]
+async fn retries_stop_after_the_maximum_number_of_attempts() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.confirmation_retry.base_delay_milliseconds = 0;
+        c.confirmation_retry.max_jitter_milliseconds = 0;
+        c.confirmation_retry.max_attempts = 3;
+        c.confirmation_retry.immediate_attempts = 1;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions_with_str(body).await;
+
+    // Act
+    app.dispatch_all_pending_confirmations().await;
+
+    // Assert
+    let outbox = sqlx::query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 0);
+}
@@ -0,0 +1,105 @@
+use crate::helpers::{spawn_app_with_config, TestApp};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const ALLOWED_ORIGIN: &str = "https://www.example.com";
+
+async fn spawn_app_with_allowed_origin() -> TestApp {
+    spawn_app_with_config(|c| c.application.allowed_origins = vec![ALLOWED_ORIGIN.into()]).await
+}
+
+async fn preflight(app: &TestApp, route: &str, origin: &str) -> reqwest::Response {
+    app.api_client
+        .request(
+            reqwest::Method::OPTIONS,
+            format!("{}{}", app.address, route),
+        )
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn a_preflight_request_from_an_allowed_origin_succeeds() {
+    // Arrange
+    let app = spawn_app_with_allowed_origin().await;
+
+    // Act
+    let response = preflight(&app, "/subscriptions", ALLOWED_ORIGIN).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Access-Control-Allow-Origin"],
+        ALLOWED_ORIGIN
+    );
+    assert!(response
+        .headers()
+        .get("Access-Control-Allow-Credentials")
+        .is_none());
+}
+
+#[tokio::test]
+async fn a_subscription_from_an_allowed_origin_carries_the_allow_origin_header() {
+    // Arrange
+    let app = spawn_app_with_allowed_origin().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", app.address))
+        .header("Origin", ALLOWED_ORIGIN)
+        .form(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()["Access-Control-Allow-Origin"],
+        ALLOWED_ORIGIN
+    );
+}
+
+#[tokio::test]
+async fn requests_from_a_disallowed_origin_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_allowed_origin().await;
+
+    // Act
+    let response = preflight(&app, "/subscriptions", "https://evil.example.org").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert!(response
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+}
+
+#[tokio::test]
+async fn admin_routes_are_not_cross_origin_accessible() {
+    // Arrange
+    let app = spawn_app_with_allowed_origin().await;
+
+    // Act
+    let response = preflight(&app, "/admin/newsletters", ALLOWED_ORIGIN).await;
+
+    // Assert
+    assert!(response
+        .headers()
+        .get("Access-Control-Allow-Origin")
+        .is_none());
+}
@@ -0,0 +1,145 @@
+use crate::helpers::{spawn_app, TestApp};
+use reqwest::Method;
+use uuid::Uuid;
+
+/// How an admin endpoint receives its CSRF token.
+enum Payload {
+    /// An HTML form, carrying the token in its `csrf_token` field.
+    Form(serde_json::Value),
+    /// A JSON body, with the token in the `X-CSRF-Token` header.
+    Json(serde_json::Value),
+    /// A CSV body, with the token in the `X-CSRF-Token` header.
+    Csv(&'static str),
+    /// No body, with the token in the `X-CSRF-Token` header.
+    Empty,
+}
+
+/// Every admin endpoint that changes state, with a body it would otherwise accept.
+fn state_changing_endpoints() -> Vec<(Method, String, Payload)> {
+    let id = Uuid::new_v4();
+    vec![
+        (
+            Method::POST,
+            "/admin/newsletters/draft".into(),
+            Payload::Form(serde_json::json!({ "title": "Draft" })),
+        ),
+        (
+            Method::POST,
+            "/admin/2fa/setup".into(),
+            Payload::Form(serde_json::json!({ "code": "123456" })),
+        ),
+        (
+            Method::POST,
+            format!("/admin/newsletters/{id}/cancel"),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            format!("/admin/newsletters/{id}/resend-failed"),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            "/admin/security/rehash".into(),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            "/admin/users".into(),
+            Payload::Json(serde_json::json!({
+                "username": "new-admin",
+                "password": "a-long-enough-password",
+            })),
+        ),
+        (
+            Method::POST,
+            format!("/admin/users/{id}/deactivate"),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            format!("/admin/dead-letters/{id}/requeue"),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            "/admin/worker/dispatch".into(),
+            Payload::Empty,
+        ),
+        (
+            Method::POST,
+            "/admin/subscribers/import".into(),
+            Payload::Json(serde_json::json!({ "subscribers": [] })),
+        ),
+        (
+            Method::POST,
+            "/admin/subscribers/import".into(),
+            Payload::Csv("email,name\n"),
+        ),
+        (
+            Method::PATCH,
+            format!("/admin/subscribers/{id}"),
+            Payload::Json(serde_json::json!({ "name": "Ursula" })),
+        ),
+    ]
+}
+
+async fn send(
+    app: &TestApp,
+    method: Method,
+    path: &str,
+    payload: &Payload,
+    csrf_token: Option<&str>,
+) -> reqwest::Response {
+    let request = app
+        .api_client
+        .request(method, format!("{}{}", app.address, path));
+    let request = match payload {
+        Payload::Form(body) => {
+            let mut body = body.clone();
+            if let Some(csrf_token) = csrf_token {
+                body["csrf_token"] = csrf_token.into();
+            }
+            request.form(&body)
+        }
+        Payload::Json(body) => request.json(body),
+        Payload::Csv(body) => request.header("Content-Type", "text/csv").body(*body),
+        Payload::Empty => request,
+    };
+    let request = match (payload, csrf_token) {
+        (Payload::Form(_), _) | (_, None) => request,
+        (_, Some(csrf_token)) => request.header("X-CSRF-Token", csrf_token),
+    };
+    request.send().await.expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn state_changing_admin_endpoints_reject_a_missing_or_invalid_csrf_token() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for (method, path, payload) in state_changing_endpoints() {
+        for csrf_token in [None, Some(""), Some("not-the-session-token")] {
+            // Act
+            let response = send(&app, method.clone(), &path, &payload, csrf_token).await;
+
+            // Assert
+            assert_eq!(
+                response.status().as_u16(),
+                400,
+                "{method} {path} was not rejected with the CSRF token {csrf_token:?}."
+            );
+            assert_eq!(
+                response.text().await.unwrap(),
+                "Missing or invalid CSRF token.",
+                "{method} {path} was rejected for another reason than the CSRF token."
+            );
+        }
+    }
+    let users = sqlx::query!("SELECT username FROM users WHERE username = 'new-admin'")
+        .fetch_optional(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert!(users.is_none());
+}
@@ -0,0 +1,140 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, spawn_app_with_config, TestApp,
+};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn publish_newsletter(app: &TestApp) -> Uuid {
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn insert_dead_letter(app: &TestApp, newsletter_issue_id: Uuid, email: &str) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO dead_letter (
+            id, newsletter_issue_id, subscriber_email, n_retries, reason, dead_lettered_at
+        )
+        VALUES ($1, $2, $3, 5, 'The email provider is down.', now())
+        "#,
+        id,
+        newsletter_issue_id,
+        email
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_list_dead_letters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_dead_letters().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdead-letters");
+}
+
+#[tokio::test]
+async fn a_requeued_dead_letter_is_delivered_by_the_worker() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_issue_id = publish_newsletter(&app).await;
+    app.fan_out_pending_issues().await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .email;
+    let id = insert_dead_letter(&app, newsletter_issue_id, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_requeue_dead_letter(id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let receipt = sqlx::query!("SELECT subscriber_email, status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.subscriber_email, email);
+    assert_eq!(receipt.status, "delivered");
+    let dead_letters: Vec<serde_json::Value> = app.get_dead_letters().await.json().await.unwrap();
+    assert!(dead_letters.is_empty());
+}
+
+#[tokio::test]
+async fn requeuing_an_unknown_dead_letter_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_requeue_dead_letter(Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn a_delivery_is_dead_lettered_once_it_runs_out_of_retries() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.max_retries = 2).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = publish_newsletter(&app).await;
+
+    // Act - The first attempt is retried, the second one is dead-lettered
+    let first = app.post_worker_dispatch().await;
+    let second = app.post_worker_dispatch().await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 500);
+    assert_eq!(second.status().as_u16(), 200);
+    let dead_letters: Vec<serde_json::Value> = app.get_dead_letters().await.json().await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(
+        dead_letters[0]["newsletter_issue_id"],
+        newsletter_issue_id.to_string()
+    );
+    assert_eq!(dead_letters[0]["n_retries"], 2);
+    let queued = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM issue_delivery_queue")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(queued.count, 0);
+}
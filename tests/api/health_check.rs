@@ -1,4 +1,6 @@
 use crate::helpers::spawn_app;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
 
 /// This test module is responsible for testing the /health_check endpoint.
 /// It will spawn our application and then send a GET request to the /health_check endpoint.
@@ -22,3 +24,125 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test]
+async fn health_check_details_reports_the_version_and_the_latest_migration() {
+    // Arrange
+    let server_address = &spawn_app().await.address;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/health_check/details", server_address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert!(!body["version"].as_str().unwrap().is_empty());
+    assert!(!body["git_sha"].as_str().unwrap().is_empty());
+    assert!(body["migration_version"].as_i64().unwrap() > 0);
+}
+
+#[tokio::test]
+async fn health_check_works_when_the_client_accepts_compressed_responses() {
+    // Arrange
+    let server_address = &spawn_app().await.address;
+    let client = reqwest::Client::new();
+
+    // Act
+    let response = client
+        .get(format!("{}/health_check", server_address))
+        .header("Accept-Encoding", "gzip, br")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert!(response.status().is_success());
+    assert_eq!(Some(0), response.content_length());
+}
+
+#[tokio::test]
+async fn ready_reports_every_dependency_as_ok_when_they_are_reachable() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({"database": "ok", "redis": "ok", "email_provider": "ok"})
+    );
+}
+
+#[tokio::test]
+async fn ready_returns_503_and_marks_the_database_when_the_pool_is_closed() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.connection_pool.close().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/ready", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 503);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({"database": "error", "redis": "ok", "email_provider": "ok"})
+    );
+}
+
+#[tokio::test]
+async fn ready_reuses_the_result_of_the_email_provider_check() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..2 {
+        // Act
+        let response = app
+            .api_client
+            .get(format!("{}/ready", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 503);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["email_provider"], "error");
+    }
+}
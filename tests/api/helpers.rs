@@ -1,29 +1,70 @@
 use actix_web::web;
 use argon2::password_hash::SaltString;
 use argon2::{Argon2, PasswordHasher};
-use newsletter_lib::configuration::{get_configuration, DatabaseSettings};
+use fake::faker::internet::en::SafeEmail;
+use fake::faker::name::en::Name;
+use fake::Fake;
+use newsletter_lib::configuration::{
+    get_configuration, ConfirmationRetrySettings, DatabaseSettings, Settings, WorkerSettings,
+};
+use newsletter_lib::confirmation_outbox::try_execute_confirmation_task;
 use newsletter_lib::email_client::EmailClient;
-use newsletter_lib::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+use newsletter_lib::email_templates::EmailTemplates;
+use newsletter_lib::issue_delivery_worker::{
+    try_execute_task, try_fan_out_issue, DeliveryThrottle, ExecutionOutcome, WorkerState,
+};
+use newsletter_lib::maintenance::MaintenanceMode;
+use newsletter_lib::routes::UnsubscribeLinks;
 use newsletter_lib::startup::Application;
 use newsletter_lib::telemetry::{get_subscriber, init_subscriber};
 use once_cell::sync::Lazy;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::sync::Mutex;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     let default_filter_level = "info".into();
     let subscriber_name = "test".into();
-
-    if std::env::var("TEST_LOG").is_ok() {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::stdout);
-        init_subscriber(subscriber);
-    } else {
-        let subscriber = get_subscriber(subscriber_name, default_filter_level, std::io::sink);
-        init_subscriber(subscriber);
-    };
+    let echo = std::env::var("TEST_LOG").is_ok();
+    let subscriber = get_subscriber(
+        subscriber_name,
+        default_filter_level,
+        move || LogCapture { echo },
+        None,
+    );
+    init_subscriber(subscriber);
 });
 
+/// Every log line emitted since the first test app was spawned, by all the tests of the run.
+static CAPTURED_LOGS: Lazy<Mutex<Vec<u8>>> = Lazy::new(Default::default);
+
+/// Writes log lines to [CAPTURED_LOGS], and to stdout as well when `TEST_LOG` is set.
+struct LogCapture {
+    echo: bool,
+}
+
+impl std::io::Write for LogCapture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        CAPTURED_LOGS.lock().unwrap().extend_from_slice(buf);
+        if self.echo {
+            std::io::stdout().write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
+
+/// The logs captured so far. Tests run concurrently, so they include the logs of other tests.
+pub fn captured_logs() -> String {
+    String::from_utf8_lossy(&CAPTURED_LOGS.lock().unwrap()).into_owned()
+}
+
 pub struct TestUser {
     pub user_id: Uuid,
     pub username: String,
@@ -70,11 +111,21 @@ pub struct TestApp {
     pub address: String,
     pub port: u16,
     pub connection_pool: web::Data<PgPool>,
+    #[allow(dead_code)]
     pub database: DatabaseSettings,
     pub email_server: MockServer,
     pub test_user: TestUser,
     pub api_client: reqwest::Client,
     pub email_client: EmailClient,
+    pub worker: WorkerSettings,
+    pub unsubscribe_links: UnsubscribeLinks,
+    pub worker_state: WorkerState,
+    pub maintenance_mode: MaintenanceMode,
+    pub maintenance_retry_after_seconds: u64,
+    pub base_url: reqwest::Url,
+    pub confirmation_email_client: EmailClient,
+    pub confirmation_retry: ConfirmationRetrySettings,
+    pub email_templates: EmailTemplates,
 }
 
 pub struct ConfirmationLinks {
@@ -84,16 +135,46 @@ pub struct ConfirmationLinks {
 
 impl TestApp {
     pub async fn dispatch_all_pending_emails(&self) {
+        let throttle = DeliveryThrottle::new(&self.worker);
+        loop {
+            throttle.until_ready().await;
+            let outcome = try_execute_task(
+                &self.connection_pool,
+                &self.email_client,
+                &self.worker,
+                &self.unsubscribe_links,
+            )
+            .await;
+            self.worker_state.record(&outcome);
+            if let ExecutionOutcome::EmptyQueue = outcome.unwrap() {
+                break;
+            }
+        }
+    }
+
+    /// Enqueues the delivery tasks of every published issue without sending any email.
+    pub async fn fan_out_pending_issues(&self) {
         while let ExecutionOutcome::TaskCompleted =
-            try_execute_task(&self.connection_pool, &self.email_client)
-                .await
-                .unwrap()
+            try_fan_out_issue(&self.connection_pool).await.unwrap()
+        {}
+    }
+
+    pub async fn dispatch_all_pending_confirmations(&self) {
+        while let ExecutionOutcome::TaskCompleted = try_execute_confirmation_task(
+            &self.connection_pool,
+            &self.confirmation_email_client,
+            &self.email_templates,
+            &self.base_url,
+            &self.confirmation_retry,
+        )
+        .await
+        .unwrap()
         {}
     }
 
     pub async fn post_subscriptions(&self, body: &serde_json::Value) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .form(body)
             .send()
@@ -101,9 +182,18 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_subscriptions_as_json(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_subscriptions_with_str(&self, body: &'static str) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -111,6 +201,51 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_subscriptions_with_api_token(
+        &self,
+        body: &'static str,
+        api_token: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .bearer_auth(api_token)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_confirm(&self, subscription_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/confirm", self.address))
+            .form(&[("subscription_token", subscription_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_confirmation(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/resend", &self.address))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Submits the subscribe form the way a browser does, accepting an HTML response.
+    pub async fn post_subscriptions_as_html(&self, body: &'static str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", "text/html,application/xhtml+xml,*/*;q=0.8")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     /// Extracts the confirmation links from the request to the email API.
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let email_body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
@@ -129,14 +264,18 @@ impl TestApp {
             confirmation_link
         };
 
-        let html = get_link(&email_body["HtmlBody"].as_str().unwrap());
-        let plain_text = get_link(&email_body["TextBody"].as_str().unwrap());
+        let plain_text = get_link(email_body["TextBody"].as_str().unwrap());
+        // Plain-text emails have no HTML body: the text link stands in for it.
+        let html = match email_body["HtmlBody"].as_str() {
+            Some(html_body) => get_link(html_body),
+            None => plain_text.clone(),
+        };
         ConfirmationLinks { html, plain_text }
     }
 
     pub async fn get_publish_newsletter_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/admin/newsletters", self.address))
+            .get(format!("{}/admin/newsletters", self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -146,20 +285,88 @@ impl TestApp {
     }
 
     pub async fn post_publish_newsletter(&self, body: &serde_json::Value) -> reqwest::Response {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/newsletters", self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_draft(&self, body: &serde_json::Value) -> reqwest::Response {
+        let body = self.with_csrf_token(body).await;
         self.api_client
-            .post(&format!("{}/admin/newsletters", self.address))
+            .post(format!("{}/admin/newsletters/draft", self.address))
             .form(&body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_newsletter_draft(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/draft", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_issues(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters", self.address))
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_issue(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        self.api_client
+            .get(format!(
+                "{}/admin/newsletters/{}",
+                self.address, newsletter_issue_id
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_cancel_newsletter_issue(
+        &self,
+        newsletter_issue_id: Uuid,
+    ) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/cancel",
+                self.address, newsletter_issue_id
+            ))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_failed(&self, newsletter_issue_id: Uuid) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!(
+                "{}/admin/newsletters/{}/resend-failed",
+                self.address, newsletter_issue_id
+            ))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", self.address))
+            .post(format!("{}/login", self.address))
             .form(body)
             .send()
             .await
@@ -168,7 +375,7 @@ impl TestApp {
 
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", self.address))
+            .get(format!("{}/login", self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -179,7 +386,15 @@ impl TestApp {
 
     pub async fn get_admin_dashboard(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/dashboard", self.address))
+            .get(format!("{}/admin/dashboard", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_me(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/me", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -191,7 +406,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", self.address))
+            .get(format!("{}/admin/password", self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -205,17 +420,312 @@ impl TestApp {
     where
         Body: serde::Serialize,
     {
+        let body = self.with_csrf_token(body).await;
+        self.api_client
+            .post(format!("{}/admin/password", self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_two_factor_setup_html(&self) -> String {
         self.api_client
-            .post(&format!("{}/admin/password", self.address))
+            .get(format!("{}/admin/2fa/setup", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_two_factor_setup(&self, code: &str) -> reqwest::Response {
+        let body = self
+            .with_csrf_token(&serde_json::json!({ "code": code }))
+            .await;
+        self.api_client
+            .post(format!("{}/admin/2fa/setup", self.address))
+            .form(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_login_two_factor_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/login/2fa", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_login_two_factor(&self, code: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/login/2fa", self.address))
+            .form(&serde_json::json!({ "code": code }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscription_status(&self, email: &str, token: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/subscriptions/status", &self.address))
+            .query(&[("email", email), ("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_change_email(
+        &self,
+        subscription_token: &str,
+        email: &str,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/change-email", &self.address))
+            .form(&serde_json::json!({
+                "subscription_token": subscription_token,
+                "email": email,
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_rotate_token(&self, subscription_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/preferences/rotate-token", self.address))
+            .query(&[("subscription_token", subscription_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/preferences", self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_preferences(
+        &self,
+        token: &str,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/preferences", self.address))
+            .query(&[("token", token)])
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_unsubscribe_from_preferences(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/preferences/unsubscribe", self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_status(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/status", self.address))
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscribers(&self, query: &[(&str, &str)]) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers", self.address))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn patch_subscriber(
+        &self,
+        subscriber_id: Uuid,
+        body: &serde_json::Value,
+    ) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .patch(format!(
+                "{}/admin/subscribers/{}",
+                self.address, subscriber_id
+            ))
+            .json(body)
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_users(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/users", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_create_user(&self, body: &serde_json::Value) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!("{}/admin/users", self.address))
+            .json(body)
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_deactivate_user(&self, user_id: Uuid) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!(
+                "{}/admin/users/{}/deactivate",
+                self.address, user_id
+            ))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import_subscribers(&self, body: &serde_json::Value) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", self.address))
+            .json(body)
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_import_subscribers_csv(
+        &self,
+        csv: &'static str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!("{}/admin/subscribers/import", self.address))
+            .query(query)
+            .header("Content-Type", "text/csv")
+            .body(csv)
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_rehash_passwords(&self) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!("{}/admin/security/rehash", self.address))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dead_letters(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/dead-letters", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_requeue_dead_letter(&self, id: Uuid) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!(
+                "{}/admin/dead-letters/{}/requeue",
+                self.address, id
+            ))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/metrics", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_worker_status(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/system/worker/status", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_worker_dispatch(&self) -> reqwest::Response {
+        let csrf_token = self.csrf_token().await;
+        self.api_client
+            .post(format!("{}/admin/worker/dispatch", self.address))
+            .header("X-CSRF-Token", csrf_token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// The CSRF token of the current session, or an empty string when not logged in.
+    pub async fn csrf_token(&self) -> String {
+        let html = self
+            .api_client
+            .get(format!("{}/admin/logout", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap();
+        let marker = r#"name="csrf_token" value=""#;
+        html.split_once(marker)
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map(|(token, _)| token.to_owned())
+            .unwrap_or_default()
+    }
+
+    /// Adds the session's CSRF token to a form body, unless the body already sets one.
+    async fn with_csrf_token<Body>(&self, body: &Body) -> serde_json::Value
+    where
+        Body: serde::Serialize,
+    {
+        let mut body = serde_json::to_value(body).unwrap();
+        if body.get("csrf_token").is_none() {
+            body["csrf_token"] = self.csrf_token().await.into();
+        }
+        body
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
+        let body = self.with_csrf_token(&serde_json::json!({})).await;
         self.api_client
-            .post(&format!("{}/admin/logout", self.address))
+            .post(format!("{}/admin/logout", self.address))
+            .form(&body)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -223,6 +733,14 @@ impl TestApp {
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with_config(|_| {}).await
+}
+
+/// Spawns the application after letting the caller adjust the test configuration.
+pub async fn spawn_app_with_config<F>(customize: F) -> TestApp
+where
+    F: FnOnce(&mut Settings),
+{
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -232,6 +750,11 @@ pub async fn spawn_app() -> TestApp {
         c.database.database_name = Uuid::new_v4().to_string();
         c.application.port = 0;
         c.email_client.base_url = email_server.uri();
+        // Failed deliveries are retried right away, so that tests can drain the queue.
+        c.worker.retry_base_delay_milliseconds = 0;
+        // Test apps share a Redis instance: a flag per app keeps maintenance tests isolated.
+        c.maintenance.redis_key = format!("maintenance_mode:{}", Uuid::new_v4());
+        customize(&mut c);
         c
     };
     configure_database(&configurations.database).await;
@@ -240,10 +763,22 @@ pub async fn spawn_app() -> TestApp {
         .await
         .expect("Failed to build application.");
     let connection_pool = application.get_connection_pool();
-    let address = format!("http://127.0.0.1:{}", application.port);
+    let worker_state = application.get_worker_state();
+    let address = format!(
+        "http://127.0.0.1:{}{}",
+        application.port,
+        configurations.application.mount_path()
+    );
     let port = application.port;
     tokio::spawn(application.run_until_stopped());
 
+    let maintenance_mode = MaintenanceMode::new(
+        configurations.redis_url.expose_secret(),
+        &configurations.maintenance,
+    )
+    .await
+    .expect("Failed to connect to Redis.");
+
     let user = TestUser::generate();
     user.store(&connection_pool).await;
 
@@ -261,15 +796,89 @@ pub async fn spawn_app() -> TestApp {
         email_server,
         test_user: user,
         api_client: client,
-        email_client: configurations.email_client.client(),
+        email_client: configurations.email_client.newsletter_client(),
+        worker: configurations.worker,
+        unsubscribe_links: UnsubscribeLinks::new(
+            configurations.application.public_url(),
+            configurations.application.hmac_secret.clone(),
+        ),
+        worker_state,
+        maintenance_mode,
+        maintenance_retry_after_seconds: configurations.maintenance.retry_after_seconds,
+        base_url: configurations.application.public_url(),
+        confirmation_email_client: configurations.email_client.confirmation_client(),
+        confirmation_retry: configurations.confirmation_retry,
+        email_templates: EmailTemplates::new(&configurations.email_templates)
+            .expect("Failed to load the email templates."),
     }
 }
 
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    // Some generated names, e.g. "O'Reilly", contain characters that `SubscriberName` rejects.
+    let name: String = Name().fake::<String>().replace('\'', "");
+    let email: String = SafeEmail().fake();
+    let body = serde_json::json!({
+        "name": name,
+        "email": email,
+    });
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(&body)
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_links = create_unconfirmed_subscriber(app).await;
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Extracts the subscription token from a confirmation link.
+pub fn subscription_token(link: &reqwest::Url) -> String {
+    link.query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .unwrap()
+        .1
+        .into_owned()
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location);
 }
 
+/// A replica URL pointing at the test database itself, as no actual replica is available.
+pub fn replica_url(db_settings: &DatabaseSettings) -> Secret<String> {
+    Secret::new(format!(
+        "postgres://{}:{}@{}:{}/{}",
+        db_settings.username,
+        db_settings.password.expose_secret(),
+        db_settings.host,
+        db_settings.port,
+        db_settings.database_name
+    ))
+}
+
 async fn configure_database(db_settings: &DatabaseSettings) {
     let mut connection = PgConnection::connect_with(&db_settings.without_db())
         .await
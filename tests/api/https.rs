@@ -0,0 +1,109 @@
+use crate::helpers::spawn_app_with_config;
+
+#[tokio::test]
+async fn plain_http_requests_are_redirected_to_https() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.enforce_https = true).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/login?next=%2Fadmin", app.address))
+        .header("X-Forwarded-Proto", "http")
+        .header("X-Forwarded-Host", "newsletter.example.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 301);
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        "https://newsletter.example.com/login?next=%2Fadmin"
+    );
+}
+
+#[tokio::test]
+async fn https_responses_carry_the_hsts_header() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.enforce_https = true;
+        c.application.hsts_max_age_seconds = 600;
+    })
+    .await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/login", app.address))
+        .header("X-Forwarded-Proto", "https")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("Strict-Transport-Security").unwrap(),
+        "max-age=600; includeSubDomains"
+    );
+}
+
+#[tokio::test]
+async fn the_health_check_is_not_redirected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.enforce_https = true).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", app.address))
+        .header("X-Forwarded-Proto", "http")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn plain_http_form_submissions_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.enforce_https = true).await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", app.address))
+        .header("X-Forwarded-Proto", "http")
+        .form(&[("name", "le guin"), ("email", "ursula_le_guin@gmail.com")])
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn https_is_not_enforced_by_default() {
+    // Arrange
+    let app = spawn_app_with_config(|_| {}).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/login", app.address))
+        .header("X-Forwarded-Proto", "http")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("Strict-Transport-Security")
+        .is_none());
+}
@@ -0,0 +1,240 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use newsletter_lib::domain::SubscriptionStatus;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_import_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({ "subscribers": [] }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn pre_verified_subscribers_are_confirmed_without_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "subscribers": [{
+                "email": "ursula_le_guin@gmail.com",
+                "name": "le guin",
+                "pre_verified": true,
+                "consented_at": "2023-04-01T12:30:00Z",
+            }]
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let summary: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(summary["confirmed"], 1);
+    assert_eq!(summary["pending_confirmation"], 0);
+
+    let saved = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus", confirmed_source, consented_at FROM subscriptions WHERE email = $1"#,
+        "ursula_le_guin@gmail.com"
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.confirmed_source.as_deref(), Some("import"));
+    assert_eq!(
+        saved.consented_at.unwrap().to_rfc3339(),
+        "2023-04-01T12:30:00+00:00"
+    );
+}
+
+#[tokio::test]
+async fn subscribers_that_are_not_pre_verified_receive_a_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "subscribers": [{
+                "email": "ursula_le_guin@gmail.com",
+                "name": "le guin",
+            }]
+        }))
+        .await;
+    app.dispatch_all_pending_confirmations().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions WHERE email = $1"#,
+        "ursula_le_guin@gmail.com"
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+    assert_eq!(saved.confirmed_source, None);
+}
+
+#[tokio::test]
+async fn an_import_does_not_fail_when_the_email_provider_is_down() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(500))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "subscribers": [
+                { "email": "ursula_le_guin@gmail.com", "name": "le guin" },
+                { "email": "octavia_butler@gmail.com", "name": "butler" },
+            ]
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let outbox = sqlx::query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 2);
+}
+
+#[tokio::test]
+async fn pre_verified_subscribers_without_a_consent_timestamp_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .post_import_subscribers(&serde_json::json!({
+            "subscribers": [{
+                "email": "ursula_le_guin@gmail.com",
+                "name": "le guin",
+                "pre_verified": true,
+            }]
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_optional(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert!(saved.is_none());
+}
+
+#[tokio::test]
+async fn a_csv_import_reports_invalid_rows_without_aborting() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers_csv(
+            "email,name\nursula_le_guin@gmail.com,le guin\nnot-an-email,someone\n",
+            &[],
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["inserted"], 1);
+    assert_eq!(report["skipped"], 0);
+    assert_eq!(report["invalid"], 1);
+    assert_eq!(report["errors"][0]["line"], 3);
+    assert!(!report["errors"][0]["reason"].as_str().unwrap().is_empty());
+
+    let saved = sqlx::query!(r#"SELECT email, status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions"#)
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved[0].status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved[0].confirmed_source.as_deref(), Some("import"));
+}
+
+#[tokio::test]
+async fn a_csv_import_can_leave_subscribers_pending_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers_csv("ursula_le_guin@gmail.com,le guin\n", &[("pending", "true")])
+        .await;
+    app.dispatch_all_pending_confirmations().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["inserted"], 1);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn a_csv_import_skips_existing_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_import_subscribers_csv("ursula_le_guin@gmail.com,le guin\n", &[])
+        .await;
+
+    // Act
+    let response = app
+        .post_import_subscribers_csv("ursula_le_guin@gmail.com,le guin\n", &[])
+        .await;
+
+    // Assert
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(report["inserted"], 0);
+    assert_eq!(report["skipped"], 1);
+}
@@ -0,0 +1,174 @@
+use crate::helpers::{
+    assert_is_redirect_to, replica_url, spawn_app, spawn_app_with_config, TestApp,
+};
+
+async fn insert_subscriber(app: &TestApp, email: &str, name: &str) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, $3, now(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4(),
+        email,
+        name
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to insert subscriber.");
+}
+
+async fn listed_emails(response: reqwest::Response) -> Vec<String> {
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let mut emails: Vec<String> = body["subscribers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["email"].as_str().unwrap().to_owned())
+        .collect();
+    emails.sort();
+    emails
+}
+
+async fn insert_readers(app: &TestApp) {
+    insert_subscriber(app, "ursula@example.com", "Ursula Le Guin").await;
+    insert_subscriber(app, "tolkien@example.org", "John Tolkien").await;
+    insert_subscriber(app, "ursa.minor@example.org", "Little Bear").await;
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_list_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscribers(&[]).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fsubscribers");
+}
+
+#[tokio::test]
+async fn searching_by_a_partial_email_only_lists_matching_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers(&[("search", "URS")]).await;
+
+    // Assert
+    assert_eq!(
+        listed_emails(response).await,
+        vec!["ursa.minor@example.org", "ursula@example.com"]
+    );
+}
+
+#[tokio::test]
+async fn searching_matches_names_too() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers(&[("search", "tolk")]).await;
+
+    // Assert
+    assert_eq!(listed_emails(response).await, vec!["tolkien@example.org"]);
+}
+
+#[tokio::test]
+async fn an_empty_search_lists_every_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers(&[("search", "")]).await;
+
+    // Assert
+    assert_eq!(listed_emails(response).await.len(), 3);
+}
+
+#[tokio::test]
+async fn special_characters_in_the_search_are_matched_literally() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    for search in ["%", "_", "' OR '1'='1", "\\"] {
+        // Act
+        let response = app.get_subscribers(&[("search", search)]).await;
+
+        // Assert
+        assert!(
+            listed_emails(response).await.is_empty(),
+            "The search `{}` matched subscribers.",
+            search
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribers_are_paginated() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let first_page: serde_json::Value = app
+        .get_subscribers(&[("per_page", "2"), ("page", "1")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    let second_page: serde_json::Value = app
+        .get_subscribers(&[("per_page", "2"), ("page", "2")])
+        .await
+        .json()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(first_page["total"], 3);
+    assert_eq!(first_page["subscribers"].as_array().unwrap().len(), 2);
+    assert_eq!(second_page["subscribers"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn out_of_range_pagination_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for query in [[("page", "0")], [("per_page", "0")], [("per_page", "101")]] {
+        // Act
+        let response = app.get_subscribers(&query).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+    }
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_from_the_read_replica_when_configured() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.database.replica_url = Some(replica_url(&c.database))).await;
+    insert_readers(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscribers(&[("search", "URS")]).await;
+
+    // Assert
+    assert_eq!(
+        listed_emails(response).await,
+        vec!["ursa.minor@example.org", "ursula@example.com"]
+    );
+}
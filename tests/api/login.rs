@@ -1,4 +1,5 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app};
+use crate::helpers::{assert_is_redirect_to, captured_logs, spawn_app, spawn_app_with_config};
+use newsletter_lib::configuration::CookieSameSite;
 
 #[tokio::test]
 async fn an_error_flash_message_is_set_on_failure() {
@@ -43,3 +44,158 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn logging_in_leads_back_to_the_requested_admin_page() {
+    // Arrange
+    let app = spawn_app().await;
+    let response = app.get_change_password().await;
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fpassword");
+
+    // Act 1 - Follow the redirect to the login form
+    let location = response
+        .headers()
+        .get("Location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let html_page = app
+        .api_client
+        .get(format!("{}{}", app.address, location))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page
+        .contains(r#"<input type="hidden" name="next" value="&#x2F;admin&#x2F;password">"#));
+
+    // Act 2 - Login
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+            "next": "/admin/password",
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+}
+
+#[tokio::test]
+async fn a_next_page_outside_of_the_admin_area_is_ignored() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for next in [
+        "https://evil.example.com/admin",
+        "//evil.example.com",
+        "/login",
+    ] {
+        // Act
+        let response = app
+            .post_login(&serde_json::json!({
+                "username": &app.test_user.username,
+                "password": &app.test_user.password,
+                "next": next,
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/dashboard");
+        app.post_logout().await;
+    }
+}
+
+#[tokio::test]
+async fn cookies_carry_the_configured_security_attributes() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.cookie_secure = true;
+        c.application.cookie_same_site = CookieSameSite::Strict;
+    })
+    .await;
+
+    // Act 1 - Successful login
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert 1 - Session cookie
+    let session_cookie = response.cookies().find(|c| c.name() == "id").unwrap();
+    assert!(session_cookie.secure());
+    assert!(session_cookie.http_only());
+    assert!(session_cookie.same_site_strict());
+
+    // Act 2 - Failed login
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": "random-username",
+            "password": "random-password"
+        }))
+        .await;
+
+    // Assert 2 - Flash message cookie
+    let flash_cookie = response.cookies().find(|c| c.name() == "_flash").unwrap();
+    assert!(flash_cookie.secure());
+    assert!(flash_cookie.http_only());
+    assert!(flash_cookie.same_site_strict());
+}
+
+#[tokio::test]
+async fn cookies_are_not_secure_when_disabled() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.cookie_secure = false;
+        c.application.cookie_same_site = CookieSameSite::Lax;
+    })
+    .await;
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+
+    // Assert
+    let session_cookie = response.cookies().find(|c| c.name() == "id").unwrap();
+    assert!(!session_cookie.secure());
+    assert!(session_cookie.http_only());
+    assert!(session_cookie.same_site_lax());
+}
+
+#[tokio::test]
+async fn passwords_never_appear_in_the_logs() {
+    // Arrange
+    let app = spawn_app().await;
+    let wrong_password = uuid::Uuid::new_v4().to_string();
+
+    // Act - A failed and a successful login
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &wrong_password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login");
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Assert
+    let logs = captured_logs();
+    assert!(logs.contains(&app.test_user.username));
+    assert!(!logs.contains(&wrong_password));
+    assert!(!logs.contains(&app.test_user.password));
+}
@@ -1,8 +1,38 @@
 mod admin_dashboard;
+mod admin_me;
+mod admin_users;
+mod base_path;
+mod change_email;
 mod change_password;
+mod confirmation_outbox;
+mod cors;
+mod csrf;
+mod dead_letters;
 mod health_check;
 mod helpers;
+mod https;
+mod import_subscribers;
+mod list_subscribers;
 mod login;
+mod maintenance;
+mod metrics;
+mod newsletter_drafts;
+mod newsletter_issues;
 mod newsletters;
+mod pending_sweeper;
+mod preference_center;
+mod preferences;
+mod request_timeout;
+mod routing;
+mod security;
+mod security_headers;
+mod startup;
+mod subscriber_status;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_resend;
+mod subscriptions_status;
+mod subscriptions_unsubscribe;
+mod two_factor;
+mod update_subscriber;
+mod worker_status;
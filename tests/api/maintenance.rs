@@ -0,0 +1,71 @@
+use crate::helpers::spawn_app;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn mutating_requests_are_rejected_with_503_during_maintenance() {
+    // Arrange
+    let app = spawn_app().await;
+    app.maintenance_mode.set_enabled(true).await.unwrap();
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 503);
+    assert_eq!(
+        response.headers().get("Retry-After").unwrap(),
+        &app.maintenance_retry_after_seconds.to_string()
+    );
+    let saved = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to count subscriptions.");
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn the_health_check_stays_up_during_maintenance() {
+    // Arrange
+    let app = spawn_app().await;
+    app.maintenance_mode.set_enabled(true).await.unwrap();
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn mutating_requests_are_accepted_again_once_maintenance_is_over() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.maintenance_mode.set_enabled(true).await.unwrap();
+    assert_eq!(
+        app.post_subscriptions_with_str(body)
+            .await
+            .status()
+            .as_u16(),
+        503
+    );
+
+    // Act
+    app.maintenance_mode.set_enabled(false).await.unwrap();
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,41 @@
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn requests_are_recorded_in_the_latency_histogram() {
+    // Arrange
+    let app = spawn_app().await;
+    app.api_client
+        .get(format!("{}/health_check", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Act
+    let response = app.get_metrics().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(body.contains(
+        r#"http_request_duration_seconds_count{method="GET",path="/health_check",status="200"} 1"#
+    ));
+    assert!(
+        body.contains(r#"http_requests_total{method="GET",path="/health_check",status="200"} 1"#)
+    );
+}
+
+#[tokio::test]
+async fn requests_are_labelled_with_the_route_pattern() {
+    // Arrange
+    let app = spawn_app().await;
+    let issue_id = uuid::Uuid::new_v4();
+    app.test_user.login(&app).await;
+    app.get_newsletter_issue(issue_id).await;
+
+    // Act
+    let body = app.get_metrics().await.text().await.unwrap();
+
+    // Assert
+    assert!(body.contains(r#"path="/admin/newsletters/{id}""#));
+    assert!(!body.contains(&issue_id.to_string()));
+}
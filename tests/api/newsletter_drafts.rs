@@ -0,0 +1,97 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn draft_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Draft title",
+        "html_content": "<p>Draft body as HTML</p>",
+        "text_content": "Draft body as plain text",
+    })
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_save_a_draft() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_newsletter_draft(&draft_body()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn a_saved_draft_prefills_the_publish_form() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act - Part 1 - Save a draft
+    let response = app.post_newsletter_draft(&draft_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 2 - Load the publish form
+    let html_page = app.get_publish_newsletter_html().await;
+
+    // Assert
+    assert!(html_page.contains("<p><i>The draft has been saved.</i></p>"));
+    assert!(html_page.contains(r#"value="Draft title""#));
+    assert!(html_page.contains("&lt;p&gt;Draft body as HTML&lt;&#x2F;p&gt;"));
+    assert!(html_page.contains("Draft body as plain text"));
+}
+
+#[tokio::test]
+async fn saving_a_draft_replaces_the_previous_one() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_newsletter_draft(&draft_body()).await;
+
+    // Act
+    app.post_newsletter_draft(&serde_json::json!({
+        "title": "Another title",
+        "html_content": "<p>Another body</p>",
+        "text_content": "Another body",
+    }))
+    .await;
+
+    // Assert
+    let response = app.get_newsletter_draft().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let draft: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(draft["title"], "Another title");
+    assert_eq!(draft["html_content"], "<p>Another body</p>");
+    assert_eq!(draft["text_content"], "Another body");
+}
+
+#[tokio::test]
+async fn publishing_clears_the_draft() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    app.post_newsletter_draft(&draft_body()).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Draft title",
+            "html_content": "<p>Draft body as HTML</p>",
+            "text_content": "Draft body as plain text",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert
+    let response = app.get_newsletter_draft().await;
+    assert_eq!(response.status().as_u16(), 404);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(!html_page.contains("Draft title"));
+}
@@ -0,0 +1,105 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use uuid::Uuid;
+
+async fn publish_issue(app: &TestApp, title: &str) {
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": title,
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_read_newsletter_issues() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let list = app.get_newsletter_issues().await;
+    let issue_id = Uuid::new_v4();
+    let issue = app.get_newsletter_issue(issue_id).await;
+
+    // Assert
+    assert_is_redirect_to(&list, "/login?next=%2Fadmin%2Fnewsletters");
+    assert_is_redirect_to(
+        &issue,
+        &format!("/login?next=%2Fadmin%2Fnewsletters%2F{}", issue_id),
+    );
+}
+
+#[tokio::test]
+async fn a_published_issue_can_be_fetched_by_id() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "Newsletter title").await;
+    let issues: Vec<serde_json::Value> = app.get_newsletter_issues().await.json().await.unwrap();
+    let issue_id: Uuid = issues[0]["newsletter_issue_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    // Act
+    let response = app.get_newsletter_issue(issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let issue: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(issue["title"], "Newsletter title");
+    assert_eq!(issue["html_content"], "<p>Newsletter body as HTML</p>");
+    assert_eq!(issue["text_content"], "Newsletter body as plain text");
+    assert!(issue["published_at"].is_string());
+}
+
+#[tokio::test]
+async fn issues_are_listed_most_recent_first() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    publish_issue(&app, "First issue").await;
+    publish_issue(&app, "Second issue").await;
+
+    // Act
+    let response = app.get_newsletter_issues().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let issues: Vec<serde_json::Value> = response.json().await.unwrap();
+    let titles: Vec<_> = issues
+        .iter()
+        .map(|i| i["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Second issue", "First issue"]);
+    assert!(issues[0].get("html_content").is_none());
+}
+
+#[tokio::test]
+async fn the_publish_form_is_still_served_as_html() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let html_page = app.get_publish_newsletter_html().await;
+
+    // Assert
+    assert!(html_page.contains("idempotency_key"));
+}
+
+#[tokio::test]
+async fn an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_newsletter_issue(Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -1,50 +1,13 @@
-use crate::helpers::{assert_is_redirect_to, spawn_app, ConfirmationLinks, TestApp};
-use fake::faker::internet::en::SafeEmail;
-use fake::faker::name::en::Name;
-use fake::Fake;
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+    spawn_app_with_config, TestApp,
+};
+use newsletter_lib::issue_delivery_worker::{try_execute_task, ExecutionOutcome};
+use sqlx::Executor;
 use std::time::Duration;
 use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
-    let body = serde_json::json!({
-        "name": name,
-        "email": email,
-    });
-
-    let _mock_guard = Mock::given(path("/email"))
-        .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
-        .expect(1)
-        .mount_as_scoped(&app.email_server)
-        .await;
-    app.post_subscriptions(&body)
-        .await
-        .error_for_status()
-        .unwrap();
-    let email_request = &app
-        .email_server
-        .received_requests()
-        .await
-        .unwrap()
-        .pop()
-        .unwrap();
-
-    app.get_confirmation_links(email_request)
-}
-
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_links = create_unconfirmed_subscriber(app).await;
-    reqwest::get(confirmation_links.html)
-        .await
-        .unwrap()
-        .error_for_status()
-        .unwrap();
-}
-
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     // Arrange
@@ -76,6 +39,43 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     // verify whether the newsletter email was not sent to the unconfirmed subscriber.
 }
 
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_subscribers_who_unsubscribed_after_the_fan_out() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.fan_out_pending_issues().await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let receipt = sqlx::query!("SELECT status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.status, "skipped");
+    assert_eq!(get_issue_status(&app).await, "completed");
+}
+
 #[tokio::test]
 async fn newsletters_are_delivered_to_confirmed_subscribers() {
     // Arrange
@@ -154,6 +154,25 @@ async fn newsletters_returns_400_for_invalid_data() {
             }),
             "missing idempotency_key",
         ),
+        (
+            serde_json::json!({
+                "title": "Newsletter title",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "text_content": "Newsletter body as plain text",
+                "idempotency_key": "key\nwith a newline",
+            }),
+            "malformed idempotency_key",
+        ),
+        (
+            serde_json::json!({
+                "title": "Newsletter title",
+                "content_type": "markdown",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "text_content": "Newsletter body as plain text",
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }),
+            "missing content_markdown",
+        ),
     ];
 
     for (invalid_body, error_message) in test_cases {
@@ -233,6 +252,111 @@ async fn newsletter_creation_is_idempotent() {
     // Mock is dropped here and verify whether the newsletter email was sent just once.
 }
 
+#[tokio::test]
+async fn reusing_an_idempotency_key_with_a_different_payload_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": idempotency_key,
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Resubmit the form with the same key but a different title
+    let changed_request_body = serde_json::json!({
+        "title": "Another newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": idempotency_key,
+    });
+    let response = app.post_publish_newsletter(&changed_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 422);
+    app.dispatch_all_pending_emails().await;
+    // Mock verifies on Drop that we have sent the newsletter email **once**
+}
+#[tokio::test]
+async fn a_failure_after_storing_the_issue_leaves_nothing_half_committed() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    // Fail the request right after the issue has been inserted.
+    app.connection_pool
+        .execute(
+            r#"
+            CREATE FUNCTION fail_fan_out() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'injected failure';
+            END;
+            $$ LANGUAGE plpgsql;
+            CREATE TRIGGER fail_fan_out BEFORE INSERT ON issue_fanout_queue
+            FOR EACH ROW EXECUTE FUNCTION fail_fan_out();
+            "#,
+        )
+        .await
+        .unwrap();
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+
+    // Act 1 - Publish while the fan-out cannot be scheduled
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert 1
+    assert_eq!(response.status().as_u16(), 500);
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT count(*) FROM newsletter_issues) AS "issues!",
+            (SELECT count(*) FROM issue_fanout_queue) AS "fan_outs!",
+            (SELECT count(*) FROM issue_delivery_queue) AS "deliveries!",
+            (SELECT count(*) FROM idempotency) AS "idempotency_keys!"
+        "#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(counts.issues, 0);
+    assert_eq!(counts.fan_outs, 0);
+    assert_eq!(counts.deliveries, 0);
+    assert_eq!(counts.idempotency_keys, 0);
+
+    // Act 2 - Retry with the same idempotency key once the failure is gone
+    app.connection_pool
+        .execute("DROP TRIGGER fail_fan_out ON issue_fanout_queue")
+        .await
+        .unwrap();
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert 2
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+    let enqueued = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(enqueued.count, 1);
+}
+
 #[tokio::test]
 async fn concurrent_form_submission_is_handled_gracefully() {
     // Arrange
@@ -269,3 +393,1303 @@ async fn concurrent_form_submission_is_handled_gracefully() {
 
     // Mock is dropped here and verify whether the newsletter email was sent just once.
 }
+
+#[tokio::test]
+async fn markdown_newsletters_are_rendered_before_delivery() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Post new newsletter written in Markdown
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content_type": "markdown",
+        "content_markdown": "Read [the docs](https://example.com/docs).\n\n```\nlet x = 1;\n```",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let email_body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(
+        email_body["HtmlBody"],
+        "<p>Read <a href=\"https://example.com/docs\">the docs</a>.</p>\n\
+         <pre><code>let x = 1;\n</code></pre>\n"
+    );
+    assert_eq!(
+        email_body["TextBody"],
+        "Read the docs (https://example.com/docs).\n\nlet x = 1;"
+    );
+}
+
+#[tokio::test]
+async fn subscribers_receive_the_translation_matching_their_locale() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber_with_tags(&app, "amelie@example.com", "").await;
+    create_confirmed_subscriber_with_tags(&app, "ursula@example.com", "").await;
+    app.connection_pool
+        .execute("UPDATE subscriptions SET locale = 'fr-CA' WHERE email = 'amelie@example.com'")
+        .await
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - Publish an issue with a French translation
+    let localizations = serde_json::json!({
+        "fr": {
+            "title": "Titre de la newsletter",
+            "html_content": "<p>Le corps en HTML</p>",
+            "text_content": "Le corps en texte",
+        },
+    });
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "localizations": localizations.to_string(),
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_bodies: Vec<serde_json::Value> = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect();
+    let body_sent_to = |email: &str| {
+        email_bodies
+            .iter()
+            .rev()
+            .find(|body| body["To"] == email)
+            .unwrap()
+            .clone()
+    };
+    let french = body_sent_to("amelie@example.com");
+    assert_eq!(french["Subject"], "Titre de la newsletter");
+    assert_eq!(french["TextBody"], "Le corps en texte");
+    let default = body_sent_to("ursula@example.com");
+    assert_eq!(default["Subject"], "Newsletter title");
+    assert_eq!(default["TextBody"], "Newsletter body as plain text");
+}
+
+#[tokio::test]
+async fn newsletters_with_an_invalid_translation_are_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let test_cases = [
+        ("not json", "missing JSON object"),
+        (
+            r#"{"not a locale": {"title": "t", "html_content": "h", "text_content": "t"}}"#,
+            "invalid locale",
+        ),
+        (
+            r#"{"fr": {"title": "", "html_content": "h", "text_content": "t"}}"#,
+            "empty title",
+        ),
+        (
+            r#"{"fr": {"title": "t", "html_content": "h"}}"#,
+            "missing text content",
+        ),
+    ];
+
+    for (localizations, error_message) in test_cases {
+        // Act
+        let body = serde_json::json!({
+            "title": "Newsletter title",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "localizations": localizations,
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        });
+        let response = app.post_publish_newsletter(&body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn newsletter_delivery_is_not_bound_by_the_confirmation_timeout() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.email_client.confirmation_timeout_milliseconds = 200;
+        c.email_client.newsletter_timeout_milliseconds = 10_000;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+
+    // Assert
+    let outcome = try_execute_task(
+        &app.connection_pool,
+        &app.email_client,
+        &app.worker,
+        &app.unsubscribe_links,
+    )
+    .await;
+    assert!(matches!(outcome, Ok(ExecutionOutcome::TaskCompleted)));
+}
+
+#[tokio::test]
+async fn newsletter_delivery_fails_if_it_exceeds_the_newsletter_timeout() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.email_client.confirmation_timeout_milliseconds = 10_000;
+        c.email_client.newsletter_timeout_milliseconds = 200;
+    })
+    .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+
+    // Assert
+    let outcome = try_execute_task(
+        &app.connection_pool,
+        &app.email_client,
+        &app.worker,
+        &app.unsubscribe_links,
+    )
+    .await;
+    assert!(outcome.is_err());
+}
+
+#[tokio::test]
+async fn dry_run_drains_the_queue_without_sending_emails() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.dry_run = true).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let remaining = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(remaining.count, 0);
+    let receipt = sqlx::query!("SELECT status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.status, "dry_run");
+    let issue = sqlx::query!("SELECT delivered_count, skipped_count FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(issue.delivered_count, 0);
+    assert_eq!(issue.skipped_count, 0);
+
+    // Mock is dropped here and verify that no email was sent.
+}
+
+#[tokio::test]
+async fn newsletters_with_a_title_over_the_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.newsletter.max_title_length = 10).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "A title that is way too long",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "`title` must be at most 10 characters long."
+    );
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn newsletters_with_a_body_over_the_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.newsletter.max_content_length = 16).await;
+    app.test_user.login(&app).await;
+
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "title": "Newsletter title",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "text_content": "Short body",
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }),
+            "`html_content` must be at most 16 bytes long.",
+        ),
+        (
+            serde_json::json!({
+                "title": "Newsletter title",
+                "content_type": "markdown",
+                "content_markdown": "A *Markdown* body that is too long",
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }),
+            "`content_markdown` must be at most 16 bytes long.",
+        ),
+    ];
+
+    for (body, message) in test_cases {
+        // Act
+        let response = app.post_publish_newsletter(&body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+        assert_eq!(response.text().await.unwrap(), message);
+    }
+}
+
+async fn create_confirmed_subscriber_with_tags(app: &TestApp, email: &str, tags: &str) {
+    let body = serde_json::json!({
+        "name": "le guin",
+        "email": email,
+        "tags": tags,
+    });
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(&body)
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn newsletters_published_to_a_segment_are_only_enqueued_for_matching_subscribers() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber_with_tags(&app, "reader@example.com", "rust, go").await;
+    create_confirmed_subscriber_with_tags(&app, "other@example.com", "python").await;
+    create_confirmed_subscriber_with_tags(&app, "untagged@example.com", "").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "segment": "Rust",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+
+    // Assert
+    let enqueued: Vec<_> = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.subscriber_email)
+        .collect();
+    assert_eq!(enqueued, vec!["reader@example.com"]);
+}
+
+#[tokio::test]
+async fn newsletters_without_a_segment_are_enqueued_for_every_confirmed_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber_with_tags(&app, "reader@example.com", "rust").await;
+    create_confirmed_subscriber_with_tags(&app, "untagged@example.com", "").await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "segment": "",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+
+    // Assert
+    let enqueued = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(enqueued.count, 2);
+}
+
+#[tokio::test]
+async fn the_fan_out_enqueues_every_subscriber_of_a_large_list_once() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        SELECT gen_random_uuid(), 'reader' || n || '@example.com', 'reader', now(),
+               (CASE WHEN n % 10 = 0 THEN 'pending_confirmation' ELSE 'confirmed' END)
+                   ::subscription_status
+        FROM generate_series(1, 5000) AS n
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to insert subscribers.");
+    app.test_user.login(&app).await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.fan_out_pending_issues().await;
+
+    // Assert
+    let enqueued = sqlx::query!(
+        r#"
+        SELECT count(*) AS "count!", count(DISTINCT subscriber_email) AS "distinct!"
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(enqueued.count, 4500);
+    assert_eq!(enqueued.distinct, 4500);
+}
+
+async fn insert_confirmed_subscriber_with_invalid_email(app: &TestApp, email: &str) {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, $2, 'le guin', now(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4(),
+        email
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to insert subscriber.");
+}
+
+async fn get_issue_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .status
+}
+
+#[tokio::test]
+async fn an_issue_where_every_recipient_is_invalid_completes_with_errors() {
+    // Arrange
+    let app = spawn_app().await;
+    insert_confirmed_subscriber_with_invalid_email(&app, "not-an-email").await;
+    insert_confirmed_subscriber_with_invalid_email(&app, "also@not@valid").await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(get_issue_status(&app).await, "completed_with_errors");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(
+        "The newsletter issue \"Newsletter title\" completed with errors: 2 recipient(s) skipped"
+    ));
+}
+
+#[tokio::test]
+async fn an_issue_with_successful_deliveries_completes() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    insert_confirmed_subscriber_with_invalid_email(&app, "not-an-email").await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_eq!(get_issue_status(&app).await, "in_progress");
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(get_issue_status(&app).await, "completed");
+}
+
+#[tokio::test]
+async fn an_issue_enqueued_twice_for_the_same_email_is_delivered_once() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Act - Enqueue the same issue for the same subscriber again
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_receipts
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let delivered_count = sqlx::query!("SELECT delivered_count FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .delivered_count;
+    assert_eq!(delivered_count, 1);
+    assert_eq!(get_issue_status(&app).await, "completed");
+}
+
+#[tokio::test]
+async fn deliveries_are_paced_to_the_configured_rate() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.max_emails_per_second = 10).await;
+    for _ in 0..5 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(5)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    let start = std::time::Instant::now();
+    app.dispatch_all_pending_emails().await;
+    let elapsed = start.elapsed();
+
+    // Assert - 5 deliveries and a final empty poll, 100ms apart
+    assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
+
+#[tokio::test]
+async fn resending_failed_deliveries_only_enqueues_the_failed_ones() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    insert_confirmed_subscriber_with_invalid_email(&app, "not-an-email").await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    // Act
+    let response = app.post_resend_failed(issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["enqueued"], 1);
+    let queued =
+        sqlx::query!("SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_queue")
+            .fetch_all(app.connection_pool.as_ref())
+            .await
+            .unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].newsletter_issue_id, issue_id);
+    assert_eq!(queued[0].subscriber_email, "not-an-email");
+    assert_eq!(get_issue_status(&app).await, "in_progress");
+}
+
+#[tokio::test]
+async fn resending_failed_deliveries_of_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_resend_failed(uuid::Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_resend_failed_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_resend_failed(uuid::Uuid::new_v4()).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn publishing_leaves_the_fan_out_to_the_worker() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        SELECT gen_random_uuid(), 'reader' || n || '@example.com', 'reader', now(), 'confirmed'
+        FROM generate_series(1, 500) AS n
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act 1 - Publish
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert 1 - Nothing has been enqueued by the request
+    let count_enqueued = || async {
+        sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .unwrap()
+            .count
+    };
+    assert_eq!(count_enqueued().await, 0);
+
+    // Act 2 - Let the worker fan out the issue
+    app.fan_out_pending_issues().await;
+
+    // Assert 2
+    assert_eq!(count_enqueued().await, 500);
+    assert_eq!(get_issue_status(&app).await, "in_progress");
+}
+
+#[tokio::test]
+async fn an_issue_without_recipients_is_completed_by_the_fan_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    assert_eq!(get_issue_status(&app).await, "in_progress");
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(get_issue_status(&app).await, "completed");
+}
+
+#[tokio::test]
+async fn subscribers_over_the_weekly_cap_are_postponed() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.max_emails_per_week = 1).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    for title in ["First issue", "Second issue"] {
+        let newsletter_request_body = serde_json::json!({
+            "title": title,
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "text_content": "Newsletter body as plain text",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        });
+        app.post_publish_newsletter(&newsletter_request_body).await;
+        app.dispatch_all_pending_emails().await;
+    }
+
+    // Assert
+    let received_requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value =
+        serde_json::from_slice(&received_requests.last().unwrap().body).unwrap();
+    assert_eq!(email_body["Subject"], "First issue");
+
+    let postponed = sqlx::query!(
+        r#"
+        SELECT i.title, q.execute_after > now() + interval '6 days' AS "later!"
+        FROM issue_delivery_queue q
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        "#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(postponed.title, "Second issue");
+    assert!(postponed.later);
+
+    let receipt = sqlx::query!(
+        r#"
+        SELECT r.status
+        FROM issue_delivery_receipts r
+        JOIN newsletter_issues i USING (newsletter_issue_id)
+        WHERE i.title = 'Second issue'
+        "#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(receipt.status, "throttled");
+}
+
+#[tokio::test]
+async fn newsletters_include_the_reply_to_and_list_unsubscribe_headers() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.email_client.reply_to = Some("replies@example.com".into()))
+            .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let received_requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value =
+        serde_json::from_slice(&received_requests.last().unwrap().body).unwrap();
+    assert_eq!(email_body["ReplyTo"], "replies@example.com");
+    let headers = email_body["Headers"].as_array().unwrap();
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|h| h["Name"] == name)
+            .unwrap_or_else(|| panic!("Missing the {} header.", name))["Value"]
+            .as_str()
+            .unwrap()
+            .to_owned()
+    };
+    let list_unsubscribe = header("List-Unsubscribe");
+    assert!(list_unsubscribe.starts_with("<http://127.0.0.1/subscriptions/unsubscribe?email="));
+    assert!(list_unsubscribe.ends_with('>'));
+    assert_eq!(
+        header("List-Unsubscribe-Post"),
+        "List-Unsubscribe=One-Click"
+    );
+}
+
+#[tokio::test]
+async fn a_rate_limited_delivery_is_retried() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let receipt = sqlx::query!("SELECT status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.status, "delivered");
+}
+
+#[tokio::test]
+async fn a_failed_delivery_is_retried_after_a_backoff() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.retry_base_delay_milliseconds = 60_000).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_issue(&app).await;
+    app.fan_out_pending_issues().await;
+
+    // Act
+    let outcome = try_execute_task(
+        &app.connection_pool,
+        &app.email_client,
+        &app.worker,
+        &app.unsubscribe_links,
+    )
+    .await;
+
+    // Assert
+    assert!(outcome.is_err());
+    let task = sqlx::query!(
+        r#"
+        SELECT n_retries, execute_after > now() + interval '50 seconds' AS "postponed!"
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(task.n_retries, 1);
+    assert!(task.postponed);
+}
+
+#[tokio::test]
+async fn a_delivery_deferred_on_every_attempt_is_dead_lettered() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.max_retries = 3).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let receipt = sqlx::query!("SELECT status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.status, "failed");
+    let dead_letter = sqlx::query!("SELECT n_retries FROM dead_letter")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.n_retries, 3);
+}
+
+#[tokio::test]
+async fn a_permanently_rejected_delivery_is_dead_lettered() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let receipt = sqlx::query!("SELECT status FROM issue_delivery_receipts")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(receipt.status, "failed");
+    let remaining = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(remaining.count, 0);
+    let issue = sqlx::query!("SELECT status, skipped_count FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "completed_with_errors");
+    assert_eq!(issue.skipped_count, 1);
+}
+
+#[tokio::test]
+async fn a_task_whose_issue_cannot_be_loaded_is_dead_lettered_instead_of_retried() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.max_retries = 5).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    publish_issue(&app).await;
+    app.fan_out_pending_issues().await;
+    // A blank title fails validation, so every attempt to load the issue would fail.
+    sqlx::query!("UPDATE newsletter_issues SET title = ''")
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let outcome = try_execute_task(
+        &app.connection_pool,
+        &app.email_client,
+        &app.worker,
+        &app.unsubscribe_links,
+    )
+    .await;
+
+    // Assert
+    assert!(matches!(outcome, Ok(ExecutionOutcome::TaskCompleted)));
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    let dead_letter = sqlx::query!("SELECT n_retries, reason FROM dead_letter")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.n_retries, 0);
+    assert!(
+        dead_letter.reason.contains("invalid"),
+        "{}",
+        dead_letter.reason
+    );
+}
+
+async fn publish_issue(app: &TestApp) -> uuid::Uuid {
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .newsletter_issue_id
+}
+
+async fn count_queued_deliveries(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn cancelling_an_issue_drops_its_pending_deliveries() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+    let issue_id = publish_issue(&app).await;
+    app.fan_out_pending_issues().await;
+    assert_eq!(count_queued_deliveries(&app).await, 3);
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_cancel_newsletter_issue(issue_id).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["dropped"], 3);
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    assert_eq!(get_issue_status(&app).await, "cancelled");
+}
+
+#[tokio::test]
+async fn the_worker_drops_a_task_of_a_cancelled_issue() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_issue(&app).await;
+    app.post_cancel_newsletter_issue(issue_id).await;
+    // A task enqueued while the issue was being cancelled
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        SELECT $1, email FROM subscriptions
+        "#,
+        issue_id
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(count_queued_deliveries(&app).await, 0);
+    assert_eq!(get_issue_status(&app).await, "cancelled");
+}
+
+#[tokio::test]
+async fn a_completed_issue_cannot_be_cancelled() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let issue_id = publish_issue(&app).await;
+    app.fan_out_pending_issues().await;
+
+    // Act
+    let response = app.post_cancel_newsletter_issue(issue_id).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(get_issue_status(&app).await, "completed");
+}
+
+#[tokio::test]
+async fn cancelling_an_unknown_issue_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_cancel_newsletter_issue(uuid::Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn publishing_is_refused_while_the_delivery_queue_is_too_deep() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.newsletter.max_queue_depth = 1).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "First issue",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    // Two deliveries are now queued, and none has been sent.
+    app.fan_out_pending_issues().await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Second issue",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 503);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("Too many emails are waiting to be delivered."));
+    let issues = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(issues.len(), 1);
+}
+
+#[tokio::test]
+async fn transactional_issues_are_delivered_ahead_of_queued_bulk_ones() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let n_sent_before = app.email_server.received_requests().await.unwrap().len();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    for (title, priority) in [("Bulk issue", "bulk"), ("Urgent issue", "transactional")] {
+        let response = app
+            .post_publish_newsletter(&serde_json::json!({
+                "title": title,
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+                "priority": priority,
+                "idempotency_key": uuid::Uuid::new_v4().to_string(),
+            }))
+            .await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        app.fan_out_pending_issues().await;
+    }
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let received_requests = app.email_server.received_requests().await.unwrap();
+    let subjects: Vec<String> = received_requests[n_sent_before..]
+        .iter()
+        .map(|r| {
+            let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+            body["Subject"].as_str().unwrap().to_owned()
+        })
+        .collect();
+    assert_eq!(subjects, ["Urgent issue", "Bulk issue"]);
+}
@@ -0,0 +1,213 @@
+use crate::helpers::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app, subscription_token,
+};
+use newsletter_lib::domain::SubscriptionStatus;
+use newsletter_lib::pending_sweeper::purge_stale_pending;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+#[tokio::test]
+async fn stale_pending_subscribers_are_purged_with_their_tokens() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET subscribed_at = now() - interval '8 days',
+            last_confirmation_sent_at = now() - interval '8 days'
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT id, 'rust' FROM subscriptions WHERE status = 'pending_confirmation'
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    let stale_id =
+        sqlx::query_scalar!("SELECT id FROM subscriptions WHERE status = 'pending_confirmation'")
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .unwrap();
+    create_unconfirmed_subscriber(&app).await;
+
+    // Act
+    let n_purged = purge_stale_pending(app.connection_pool.as_ref(), TTL)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 1);
+    let remaining = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus", subscribed_at > now() - interval '1 day' AS recent FROM subscriptions"#
+    )
+    .fetch_all(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(remaining.len(), 2);
+    assert!(remaining
+        .iter()
+        .any(|s| s.status == SubscriptionStatus::Confirmed));
+    assert!(remaining
+        .iter()
+        .any(|s| s.status == SubscriptionStatus::PendingConfirmation && s.recent == Some(true)));
+    let n_tokens = sqlx::query_scalar!(
+        "SELECT count(*) FROM subscription_tokens WHERE subscriber_id = $1",
+        stale_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(n_tokens, Some(0));
+}
+
+#[tokio::test]
+async fn a_recent_confirmation_email_keeps_an_old_pending_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = now() - interval '8 days'")
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+
+    // Act
+    let n_purged = purge_stale_pending(app.connection_pool.as_ref(), TTL)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 0);
+}
+
+#[tokio::test]
+async fn requesting_an_email_change_keeps_an_old_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET subscribed_at = now() - interval '8 days',
+            last_confirmation_sent_at = now() - interval '8 days'
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_change_email(
+        &subscription_token(&confirmation_links.html),
+        "ursula.new@example.com",
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+
+    // Act
+    let n_purged = purge_stale_pending(app.connection_pool.as_ref(), TTL)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 0);
+}
+
+#[tokio::test]
+async fn an_expired_email_change_is_reverted_instead_of_deleting_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let email = sqlx::query_scalar!("SELECT email FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_change_email(
+        &subscription_token(&confirmation_links.html),
+        "ursula.mistyped@example.com",
+    )
+    .await
+    .error_for_status()
+    .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT id, 'rust' FROM subscriptions
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET subscribed_at = now() - interval '30 days',
+            last_confirmation_sent_at = now() - interval '8 days'
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+
+    // Act
+    let n_purged = purge_stale_pending(app.connection_pool.as_ref(), TTL)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(n_purged, 0);
+    let saved = sqlx::query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.email, email);
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.pending_email, None);
+    let n_tags = sqlx::query_scalar!("SELECT count(*) FROM subscriber_tags")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(n_tags, Some(1));
+    let n_tokens = sqlx::query_scalar!("SELECT count(*) FROM subscription_tokens")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(n_tokens, Some(0));
+
+    // A later sweep leaves the confirmed subscriber alone.
+    let n_purged = purge_stale_pending(app.connection_pool.as_ref(), TTL)
+        .await
+        .unwrap();
+    assert_eq!(n_purged, 0);
+}
@@ -0,0 +1,240 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_unconfirmed_subscriber, spawn_app, subscription_token, TestApp,
+};
+use newsletter_lib::domain::SubscriptionStatus;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Creates a confirmed subscriber and returns their subscription token.
+async fn confirmed_subscriber_token(app: &TestApp) -> String {
+    let links = create_unconfirmed_subscriber(app).await;
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    subscription_token(&links.html)
+}
+
+#[tokio::test]
+async fn the_preference_center_is_shown_for_a_valid_token() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+
+    // Act
+    let response = app.get_preferences(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!(r#"value="{}""#, saved.name)));
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_preferences() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+
+    // Act
+    let response = app
+        .post_preferences(
+            &token,
+            &serde_json::json!({
+                "name": "Ursula K. Le Guin",
+                "content_format": "text",
+                "frequency": "weekly",
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+    let saved = sqlx::query!("SELECT name, content_format, frequency FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.name, "Ursula K. Le Guin");
+    assert_eq!(saved.content_format, "text");
+    assert_eq!(saved.frequency, "weekly");
+
+    let html_page = app.get_preferences(&token).await.text().await.unwrap();
+    assert!(html_page.contains("<p><i>Your preferences have been saved.</i></p>"));
+}
+
+#[tokio::test]
+async fn an_invalid_name_is_not_saved() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+
+    let name_before = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .name;
+
+    // Act
+    let response = app
+        .post_preferences(
+            &token,
+            &serde_json::json!({
+                "name": "",
+                "content_format": "html",
+                "frequency": "every_issue",
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.name, name_before);
+}
+
+#[tokio::test]
+async fn a_tampered_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+    let tampered = format!("{}x", token);
+
+    let name_before = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .name;
+
+    // Act
+    let get_response = app.get_preferences(&tampered).await;
+    let post_response = app
+        .post_preferences(
+            &tampered,
+            &serde_json::json!({
+                "name": "Mallory",
+                "content_format": "html",
+                "frequency": "every_issue",
+            }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(get_response.status().as_u16(), 401);
+    assert_eq!(post_response.status().as_u16(), 401);
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.name, name_before);
+}
+
+#[tokio::test]
+async fn subscribers_can_unsubscribe_from_the_preference_center() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+
+    // Act
+    let response = app.post_unsubscribe_from_preferences(&token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+    assert_eq!(app.get_preferences(&token).await.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn an_old_confirmation_link_does_not_resubscribe_after_unsubscribing_from_the_preference_center(
+) {
+    // Arrange
+    let app = spawn_app().await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.post_unsubscribe_from_preferences(&subscription_token(&links.html))
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+    let tokens = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscription_tokens"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(tokens.count, 0);
+}
+
+#[tokio::test]
+async fn plain_text_subscribers_receive_issues_without_an_html_body() {
+    // Arrange
+    let app = spawn_app().await;
+    let token = confirmed_subscriber_token(&app).await;
+    app.post_preferences(
+        &token,
+        &serde_json::json!({
+            "name": "Ursula",
+            "content_format": "text",
+            "frequency": "every_issue",
+        }),
+    )
+    .await;
+    app.test_user.login(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": uuid::Uuid::new_v4().to_string(),
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let email_body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(email_body["HtmlBody"]
+        .as_str()
+        .unwrap_or_default()
+        .is_empty());
+    assert_eq!(email_body["TextBody"], "Newsletter body as plain text");
+}
@@ -0,0 +1,217 @@
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app, spawn_app_with_config};
+use newsletter_lib::configuration::ConfirmationTokenScheme;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn token_from(link: &reqwest::Url) -> String {
+    link.query_pairs()
+        .find(|(k, _)| k == "subscription_token")
+        .map(|(_, v)| v.into_owned())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn rotating_an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_rotate_token("unknown-token").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn after_rotation_the_old_token_is_rejected_and_the_new_one_works() {
+    // Arrange
+    let app = spawn_app().await;
+    let old_links = create_unconfirmed_subscriber(&app).await;
+    let old_token = token_from(&old_links.html);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_rotate_token(&old_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let new_links = app.get_confirmation_links(email_request);
+    assert_ne!(token_from(&new_links.html), old_token);
+
+    let response = reqwest::get(old_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app.post_rotate_token(&old_token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = reqwest::get(new_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn token_rotations_are_rate_limited() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.preferences.max_token_rotations_per_hour = 1).await;
+    let links = create_unconfirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_rotate_token(&token_from(&links.html)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let new_links = app.get_confirmation_links(email_request);
+
+    // Act
+    let response = app.post_rotate_token(&token_from(&new_links.html)).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["X-RateLimit-Remaining"], "0");
+    let retry_after: u64 = response.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 3600);
+}
+
+#[tokio::test]
+async fn token_rotations_report_the_remaining_quota() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.preferences.max_token_rotations_per_hour = 3).await;
+    let links = create_unconfirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app.post_rotate_token(&token_from(&links.html)).await;
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let new_links = app.get_confirmation_links(email_request);
+    let second = app.post_rotate_token(&token_from(&new_links.html)).await;
+
+    // Assert
+    for response in [&first, &second] {
+        assert_eq!(response.status().as_u16(), 200);
+        assert_eq!(response.headers()["X-RateLimit-Limit"], "3");
+        assert!(response.headers().get("X-RateLimit-Reset").is_some());
+        assert!(response.headers().get("Retry-After").is_none());
+    }
+    assert_eq!(first.headers()["X-RateLimit-Remaining"], "2");
+    assert_eq!(second.headers()["X-RateLimit-Remaining"], "1");
+}
+
+#[tokio::test]
+async fn a_confirmed_subscriber_gets_a_working_preference_center_link() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.confirmation_token_scheme = ConfirmationTokenScheme::Signed;
+    })
+    .await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    // A token stored before switching to signed confirmation links.
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_tokens (subscriber_id, subscription_token)
+        SELECT id, 'storedtoken' FROM subscriptions
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_rotate_token("storedtoken").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let link = app.get_confirmation_links(email_request).html;
+    assert_eq!(link.path(), "/preferences");
+    let token = link
+        .query_pairs()
+        .find(|(k, _)| k == "token")
+        .map(|(_, v)| v.into_owned())
+        .unwrap();
+    let response = app.get_preferences(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn the_old_token_keeps_working_when_the_new_link_cannot_be_sent() {
+    // Arrange
+    let app = spawn_app().await;
+    let links = create_unconfirmed_subscriber(&app).await;
+    let old_token = token_from(&links.html);
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_rotate_token(&old_token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 500);
+    let n_tokens = sqlx::query_scalar!(
+        "SELECT count(*) FROM subscription_tokens WHERE subscription_token = $1",
+        old_token
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(n_tokens, Some(1));
+    let response = reqwest::get(links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
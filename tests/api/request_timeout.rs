@@ -0,0 +1,66 @@
+use crate::helpers::spawn_app_with_config;
+use std::time::{Duration, Instant};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn a_request_taking_longer_than_the_timeout_returns_504() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.request_timeout_seconds = 1).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let start = Instant::now();
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 504);
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[tokio::test]
+async fn requests_within_the_timeout_are_served() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.request_timeout_seconds = 1).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/health_check", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn a_confirmation_email_cut_off_by_the_timeout_is_left_to_the_outbox() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.request_timeout_seconds = 1).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 504);
+    let outbox = sqlx::query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 1);
+}
@@ -0,0 +1,40 @@
+use crate::helpers::spawn_app;
+
+#[tokio::test]
+async fn unknown_paths_return_a_json_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/nonexistent", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "error": "not_found" }));
+}
+
+#[tokio::test]
+async fn unsupported_methods_return_a_json_405() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/subscriptions", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 405);
+    assert_eq!(response.headers().get("Allow").unwrap(), "POST");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "error": "method_not_allowed" }));
+}
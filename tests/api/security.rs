@@ -0,0 +1,77 @@
+use crate::helpers::{spawn_app, TestApp};
+use argon2::{Algorithm, Params, PasswordHash};
+
+struct StoredPassword {
+    password_hash: String,
+    needs_rehash: bool,
+}
+
+async fn stored_password(app: &TestApp) -> StoredPassword {
+    sqlx::query_as!(
+        StoredPassword,
+        "SELECT password_hash, needs_rehash FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_flag_passwords_for_a_rehash() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_rehash_passwords().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 303);
+    assert!(!stored_password(&app).await.needs_rehash);
+}
+
+#[tokio::test]
+async fn outdated_hashes_are_upgraded_on_login() {
+    // Arrange
+    let app = spawn_app().await;
+    // The test user is stored with the default parameters of the `argon2` crate.
+    let before = stored_password(&app).await;
+
+    // Act
+    app.test_user.login(&app).await;
+
+    // Assert
+    let after = stored_password(&app).await;
+    assert_ne!(after.password_hash, before.password_hash);
+    let password_hash = PasswordHash::new(&after.password_hash).unwrap();
+    assert_eq!(password_hash.algorithm, Algorithm::Argon2id.ident());
+    assert_eq!(Params::try_from(&password_hash).unwrap().m_cost(), 15000);
+}
+
+#[tokio::test]
+async fn flagged_passwords_are_rehashed_on_the_next_login() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let response = app.post_rehash_passwords().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let users = sqlx::query!(r#"SELECT count(*) AS "count!" FROM users"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(body["flagged"], users.count);
+    app.post_logout().await;
+    let before = stored_password(&app).await;
+    assert!(before.needs_rehash);
+
+    // Act
+    app.test_user.login(&app).await;
+
+    // Assert
+    let after = stored_password(&app).await;
+    assert!(!after.needs_rehash);
+    assert_ne!(after.password_hash, before.password_hash);
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(response.status().as_u16(), 200);
+}
@@ -0,0 +1,71 @@
+use crate::helpers::{spawn_app, spawn_app_with_config};
+
+#[tokio::test]
+async fn the_login_page_is_served_with_security_headers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/login", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let headers = response.headers();
+    assert_eq!(headers.get("X-Content-Type-Options").unwrap(), "nosniff");
+    assert_eq!(headers.get("X-Frame-Options").unwrap(), "DENY");
+    assert_eq!(
+        headers.get("Content-Security-Policy").unwrap(),
+        "default-src 'self'"
+    );
+}
+
+#[tokio::test]
+async fn json_responses_are_served_with_nosniff() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/health_check/details", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "application/json"
+    );
+    assert_eq!(
+        response.headers().get("X-Content-Type-Options").unwrap(),
+        "nosniff"
+    );
+}
+
+#[tokio::test]
+async fn the_content_security_policy_is_configurable() {
+    // Arrange
+    let policy = "default-src 'self'; img-src 'self' https://images.example.com";
+    let app =
+        spawn_app_with_config(|c| c.application.content_security_policy = policy.into()).await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/login", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(
+        response.headers().get("Content-Security-Policy").unwrap(),
+        policy
+    );
+}
@@ -0,0 +1,201 @@
+use crate::helpers::spawn_app;
+use newsletter_lib::configuration::{get_configuration, SessionBackend};
+use newsletter_lib::startup::Application;
+use std::path::Path;
+use uuid::Uuid;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Copies the page templates to a fresh directory, leaving out `excluded`.
+fn copy_templates_without(excluded: &str) -> String {
+    fn copy_dir(from: &Path, to: &Path, excluded: &Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let source = entry.path();
+            if source == excluded {
+                continue;
+            }
+            let destination = to.join(entry.file_name());
+            if source.is_dir() {
+                copy_dir(&source, &destination, excluded);
+            } else {
+                std::fs::copy(&source, &destination).unwrap();
+            }
+        }
+    }
+
+    let directory = std::env::temp_dir().join(format!("templates-{}", Uuid::new_v4()));
+    copy_dir(
+        Path::new("templates"),
+        &directory,
+        &Path::new("templates").join(excluded),
+    );
+    directory.to_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn build_fails_when_a_page_template_is_missing() {
+    // Arrange
+    let templates_directory = copy_templates_without("home.html");
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.application.templates_directory = templates_directory.clone();
+
+    // Act
+    let result = Application::build(&configuration).await;
+    std::fs::remove_dir_all(&templates_directory).unwrap();
+
+    // Assert
+    let Err(e) = result else {
+        panic!("The application was built without the home page template.");
+    };
+    let message = e.to_string();
+    assert!(message.contains("Missing templates"), "{}", message);
+    assert!(message.contains("home.html"), "{}", message);
+}
+
+#[tokio::test]
+async fn build_fails_when_the_email_provider_is_unreachable() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.email_client.sender_email = "newsletter@localhost".into();
+    // Nothing listens on the discard port.
+    configuration.email_client.base_url = "http://127.0.0.1:9".into();
+    configuration.email_client.verify_on_startup = true;
+
+    // Act
+    let result = Application::build(&configuration).await;
+
+    // Assert
+    let Err(e) = result else {
+        panic!("The application was built with an unreachable email provider.");
+    };
+    let message = format!("{:?}", e);
+    assert!(
+        message.contains("email provider cannot be reached"),
+        "{}",
+        message
+    );
+    assert!(message.contains("http://127.0.0.1:9/server"), "{}", message);
+}
+
+#[tokio::test]
+async fn build_fails_when_the_sender_domain_does_not_resolve() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.email_client.sender_email = "newsletter@example.invalid".into();
+    configuration.email_client.verify_on_startup = true;
+
+    // Act
+    let result = Application::build(&configuration).await;
+
+    // Assert
+    let Err(e) = result else {
+        panic!("The application was built with an unresolvable sender domain.");
+    };
+    let message = format!("{:?}", e);
+    assert!(message.contains("`example.invalid`"), "{}", message);
+}
+
+#[tokio::test]
+async fn build_succeeds_when_the_email_provider_accepts_the_token() {
+    // Arrange
+    let email_server = MockServer::start().await;
+    Mock::given(path("/server"))
+        .and(method("GET"))
+        .and(header("X-Postmark-Server-Token", "my-secret-token"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&email_server)
+        .await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.email_client.sender_email = "newsletter@localhost".into();
+    configuration.email_client.base_url = email_server.uri();
+    configuration.email_client.authorization_token = "my-secret-token".to_owned().into();
+    configuration.email_client.verify_on_startup = true;
+
+    // Act
+    let result = Application::build(&configuration).await;
+
+    // Assert
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn build_fails_when_redis_is_unreachable_and_no_fallback_is_configured() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    // Nothing listens on the discard port.
+    configuration.redis_url = "redis://127.0.0.1:9".to_owned().into();
+    configuration.session_backend = SessionBackend::Redis;
+
+    // Act
+    let result = Application::build(&configuration).await;
+
+    // Assert
+    let Err(e) = result else {
+        panic!("The application was built with an unreachable Redis.");
+    };
+    let message = format!("{:?}", e);
+    assert!(message.contains("session store"), "{}", message);
+}
+
+#[tokio::test]
+async fn sessions_fall_back_to_cookies_when_redis_is_unreachable() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.application.port = 0;
+    configuration.redis_url = "redis://127.0.0.1:9".to_owned().into();
+    configuration.session_backend = SessionBackend::CookieFallback;
+
+    // Act
+    let application = Application::build(&configuration)
+        .await
+        .expect("Failed to build the application without Redis.");
+    let address = format!("http://127.0.0.1:{}", application.port());
+    tokio::spawn(application.run_until_stopped());
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap();
+    let health_check = client
+        .get(format!("{}/health_check", address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let dashboard = client
+        .get(format!("{}/admin/dashboard", address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(health_check.status().as_u16(), 200);
+    assert_eq!(dashboard.status().as_u16(), 303);
+}
+
+#[tokio::test]
+async fn the_home_page_is_rendered() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!("{}/", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+}
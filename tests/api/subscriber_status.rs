@@ -0,0 +1,74 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_look_up_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_subscriber_status("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    assert_is_redirect_to(
+        &response,
+        "/login?next=%2Fadmin%2Fsubscribers%2Fstatus%3Femail%3Dursula_le_guin%2540gmail.com",
+    );
+}
+
+#[tokio::test]
+async fn a_differently_cased_email_finds_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions_with_str("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_status("Ursula_Le_Guin@Gmail.COM").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "email": "ursula_le_guin@gmail.com",
+            "status": "pending_confirmation",
+        })
+    );
+}
+
+#[tokio::test]
+async fn an_unknown_email_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_status("nobody@example.com").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_invalid_email_returns_400() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_subscriber_status("not-an-email").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
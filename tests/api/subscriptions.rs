@@ -1,5 +1,11 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app, spawn_app_with_config};
+use newsletter_lib::authentication::hash_api_token;
+use newsletter_lib::domain::SubscriptionStatus;
+use newsletter_lib::routes::{generate_subscription_token_with_rng, store_token_with_rng};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use sqlx::query;
+use std::time::Duration;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
 
@@ -29,6 +35,74 @@ async fn subscribe_returns_a_200_for_valid_form_data() {
     assert_eq!(200, response.status().as_u16());
 }
 
+#[tokio::test]
+async fn subscribe_accepts_a_json_body() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "email": "ursula_le_guin@gmail.com",
+        "name": "le guin",
+    });
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_as_json(&body).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let saved =
+        query!(r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.name, "le guin");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_for_a_malformed_json_body() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/json")
+        .body(r#"{"email": "ursula_le_guin@gmail.com", "name": "#)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscribe_validates_a_json_body_like_form_data() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = serde_json::json!({
+        "email": "definitely-not-an-email",
+        "name": "le guin",
+    });
+
+    // Act
+    let response = app.post_subscriptions_as_json(&body).await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"].as_array().unwrap().len(), 1);
+}
+
 #[tokio::test]
 async fn subscribe_persists_the_new_subscriber() {
     // Arrange
@@ -45,16 +119,71 @@ async fn subscribe_persists_the_new_subscriber() {
     app.post_subscriptions_with_str(body).await;
 
     // Assert
-    let saved = query!("SELECT email, name, status FROM subscriptions")
-        .fetch_one(app.connection_pool.as_ref())
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved =
+        query!(r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .expect("Failed to fetch saved subscription.");
 
     app.connection_pool.close().await;
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
-    assert_eq!(saved.status, "pending_confirmation");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn subscribe_stores_the_email_with_a_lowercased_domain() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=Ursula_Le_Guin%40GMail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    let saved = query!("SELECT email FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "Ursula_Le_Guin@gmail.com");
+}
+
+#[tokio::test]
+async fn emails_differing_only_in_the_case_of_the_domain_conflict() {
+    // Arrange
+    let app = spawn_app().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions_with_str("name=le%20guin&email=ursula%40Example.com")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act
+    let response = app
+        .post_subscriptions_with_str("name=le%20guin&email=ursula%40example.COM")
+        .await;
+
+    // Assert
+    assert!(!response.status().is_success());
+    let saved = query!("SELECT email FROM subscriptions")
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscriptions.");
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "ursula@example.com");
 }
 
 /// This test is responsible for testing the /subscription endpoint.
@@ -111,6 +240,389 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_empty() {
     }
 }
 
+#[tokio::test]
+async fn subscribe_reports_every_invalid_field() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=&email=definitely-not-an-email";
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body["errors"],
+        serde_json::json!(["Invalid email address.", "Invalid subscriber name."])
+    );
+    assert_eq!(
+        body["fields"],
+        serde_json::json!({
+            "email": "Invalid email address.",
+            "name": "Invalid subscriber name.",
+        })
+    );
+}
+
+#[tokio::test]
+async fn subscribe_identifies_the_field_that_failed() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        ("name=le%20guin&email=definitely-not-an-email", "email"),
+        ("name=&email=ursula_le_guin%40gmail.com", "name"),
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&timezone=Mars%2FOlympus",
+            "timezone",
+        ),
+    ];
+
+    for (body, field) in test_cases {
+        // Act
+        let response = app.post_subscriptions_with_str(body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+        let body: serde_json::Value = response.json().await.unwrap();
+        let fields = body["fields"].as_object().unwrap();
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            [field],
+            "Expected only `{}` to be reported.",
+            field
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_for_a_blocked_disposable_domain() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.disposable_domains_file =
+            Some("configuration/disposable_domains.txt".into());
+    })
+    .await;
+
+    for body in [
+        "name=le%20guin&email=user%40mailinator.com",
+        "name=le%20guin&email=user%40MAILINATOR.com",
+    ] {
+        // Act
+        let response = app.post_subscriptions_with_str(body).await;
+
+        // Assert
+        assert_eq!(response.status().as_u16(), 400);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body["fields"]["email"],
+            "Email addresses from this domain are not accepted. Please use another address."
+        );
+    }
+    let saved = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn subscribe_accepts_domains_missing_from_the_blocklist() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.disposable_domains_file =
+            Some("configuration/disposable_domains.txt".into());
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+/// Stores an API token for the test user and returns it.
+async fn store_api_token(app: &crate::helpers::TestApp, source: &str) -> String {
+    let token = uuid::Uuid::new_v4().to_string();
+    query!(
+        "INSERT INTO api_tokens (api_token_id, user_id, token_hash, source) VALUES ($1, $2, $3, $4)",
+        uuid::Uuid::new_v4(),
+        app.test_user.user_id,
+        hash_api_token(&token),
+        source,
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    token
+}
+
+#[tokio::test]
+async fn subscribing_with_an_api_token_records_its_source() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_api_token = true).await;
+    let token = store_api_token(&app, "partner-blog").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_api_token(body, &token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!("SELECT source FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.source.as_deref(), Some("partner-blog"));
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_401_for_a_missing_or_invalid_api_token_when_one_is_required() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_api_token = true).await;
+    store_api_token(&app, "partner-blog").await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    // Act
+    let without_token = app.post_subscriptions_with_str(body).await;
+    let with_invalid_token = app
+        .post_subscriptions_with_api_token(body, "not-a-known-token")
+        .await;
+
+    // Assert
+    for response in [without_token, with_invalid_token] {
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+    }
+    let saved = query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 0);
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_401_for_an_invalid_api_token_even_when_none_is_required() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    // Act
+    let response = app
+        .post_subscriptions_with_api_token(body, "not-a-known-token")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn subscribe_persists_the_tags_of_the_new_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&tags=Fantasy%2C%20sci-fi";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let tags: Vec<_> = query!("SELECT tag FROM subscriber_tags ORDER BY tag")
+        .fetch_all(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved tags.")
+        .into_iter()
+        .map(|r| r.tag)
+        .collect();
+    assert_eq!(tags, vec!["fantasy", "sci-fi"]);
+}
+
+#[tokio::test]
+async fn subscribe_persists_the_locale_and_timezone_of_the_new_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body =
+        "name=le%20guin&email=ursula_le_guin%40gmail.com&locale=en-US&timezone=America%2FNew_York";
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!("SELECT locale, timezone FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.locale.as_deref(), Some("en-US"));
+    assert_eq!(saved.timezone.as_deref(), Some("America/New_York"));
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_when_the_locale_or_timezone_is_invalid() {
+    // Arrange
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&timezone=Mars%2FPhobos",
+            "an unknown timezone",
+        ),
+        (
+            "name=le%20guin&email=ursula_le_guin%40gmail.com&locale=not_a_locale",
+            "a malformed locale",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.post_subscriptions_with_str(body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not return a 400 Bad Request for {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_when_a_tag_is_invalid() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&tags=fantasy%2C%3Cscript%3E";
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn subscribe_returns_201_with_a_location_for_json_clients() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let saved = query!("SELECT id FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    let mut location = app
+        .base_url
+        .join(&format!("subscriptions/{}", saved.id))
+        .unwrap();
+    assert_eq!(
+        response.headers().get("Location").unwrap(),
+        location.as_str()
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "id": saved.id.to_string(),
+            "status": "pending_confirmation",
+        })
+    );
+    // The location can be followed by the client, without logging in.
+    location.set_port(Some(app.port)).unwrap();
+    let response = app.api_client.get(location).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "status": "pending" }));
+}
+
+#[tokio::test]
+async fn subscribe_renders_a_thank_you_page_for_html_clients() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_as_html(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .headers()
+        .get("Content-Type")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let html = response.text().await.unwrap();
+    assert!(html.contains("Thank you for subscribing, le guin!"));
+    assert!(html.contains("We have sent a confirmation link to <b>ursula_le_guin@gmail.com</b>."));
+}
+
+#[tokio::test]
+async fn subscribe_renders_the_form_again_with_errors_for_html_clients() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=&email=ursula_le_guin%40gmail.com";
+
+    // Act
+    let response = app.post_subscriptions_as_html(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+    let html = response.text().await.unwrap();
+    assert!(html.contains("<form action=\"/subscriptions\" method=\"post\">"));
+    assert!(html.contains("<li><i>"));
+    assert!(html.contains("value=\"ursula_le_guin@gmail.com\""));
+    let saved = query!("SELECT id FROM subscriptions")
+        .fetch_optional(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert!(saved.is_none());
+}
+
 #[tokio::test]
 async fn subscribe_sends_a_confirmation_email_for_valid_data() {
     // Arrange
@@ -151,6 +663,149 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     assert_eq!(confirmation_links.html, confirmation_links.plain_text);
 }
 
+#[tokio::test]
+async fn confirmation_emails_have_no_html_body_when_plain_text_is_preferred() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.email_templates.prefer_plain_text = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let email_body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert!(email_body["HtmlBody"]
+        .as_str()
+        .unwrap_or_default()
+        .is_empty());
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.plain_text)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn the_confirmation_email_is_sent_with_the_configured_sender_name() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.email_client.sender_email = "newsletter@example.com".into();
+        c.email_client.sender_name = Some("Newsletter Team".into());
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["From"], "Newsletter Team <newsletter@example.com>");
+}
+
+#[tokio::test]
+async fn a_trailing_slash_in_the_base_url_does_not_double_the_slash_in_links() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.base_url = reqwest::Url::parse("http://127.0.0.1/").unwrap();
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let email_body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let text_body = email_body["TextBody"].as_str().unwrap();
+    assert!(text_body.contains("http://127.0.0.1/subscriptions/confirm?subscription_token="));
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html.path(), "/subscriptions/confirm");
+}
+
+#[tokio::test]
+async fn confirmation_links_use_https_when_it_is_forced() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.base_url = reqwest::Url::parse("http://127.0.0.1").unwrap();
+        c.application.force_https_links = true;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html.scheme(), "https");
+    assert_eq!(confirmation_links.plain_text.scheme(), "https");
+}
+
+#[tokio::test]
+async fn confirmation_links_include_the_configured_base_path() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.base_path = "/newsletter/".into()).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(
+        confirmation_links.html.path(),
+        "/newsletter/subscriptions/confirm"
+    );
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Arrange
@@ -170,3 +825,281 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn a_confirmation_email_exceeding_the_timeout_is_handed_over_to_the_outbox() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.email_client.confirmation_timeout_milliseconds = 200;
+        c.email_client.newsletter_timeout_milliseconds = 10_000;
+        c.confirmation_retry.immediate_backoff_milliseconds = 0;
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(1)))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let outbox = query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 1);
+}
+
+#[tokio::test]
+async fn transient_failures_of_the_confirmation_email_are_retried_before_responding() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.confirmation_retry.immediate_backoff_milliseconds = 0).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = &app.email_server.received_requests().await.unwrap()[2];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let outbox = query!(r#"SELECT count(*) AS "count!" FROM confirmation_email_outbox"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(outbox.count, 0);
+}
+
+#[tokio::test]
+async fn subscribe_renders_the_confirmation_email_from_the_configured_templates() {
+    // Arrange
+    let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("confirmation.html"),
+        "<p>Thanks for joining Acme Weekly!</p><a href=\"{{ confirmation_link | safe }}\">Confirm</a>",
+    )
+    .unwrap();
+    std::fs::write(
+        directory.join("confirmation.txt"),
+        "Thanks for joining Acme Weekly! Confirm at {{ confirmation_link }}",
+    )
+    .unwrap();
+    let app = spawn_app_with_config(|c| {
+        c.email_templates.directory = directory.to_str().unwrap().to_owned();
+        c.email_templates.confirmation_subject = "Confirm your Acme Weekly subscription".into();
+    })
+    .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.post_subscriptions_with_str(body).await;
+
+    // Assert
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let email: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(email["Subject"], "Confirm your Acme Weekly subscription");
+    assert!(email["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("<p>Thanks for joining Acme Weekly!</p>"));
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Thanks for joining Acme Weekly!"));
+    let confirmation_links = app.get_confirmation_links(email_request);
+    assert_eq!(confirmation_links.html, confirmation_links.plain_text);
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_403_once_the_subscriber_limit_is_reached() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.max_subscribers = 1).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions_with_str("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Act
+    let response = app
+        .post_subscriptions_with_str("name=tolkien&email=tolkien%40gmail.com")
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 403);
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("The subscriber limit has been reached."));
+    let saved = query!(r#"SELECT count(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.count, 1);
+}
+
+#[tokio::test]
+async fn subscribers_are_confirmed_right_away_when_double_opt_in_is_disabled() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.features.double_opt_in = false).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", &app.address))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 201);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+    let saved = query!(
+        r#"SELECT status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.confirmed_source.as_deref(), Some("subscribe"));
+}
+
+#[tokio::test]
+async fn a_colliding_subscription_token_is_replaced_by_a_fresh_one() {
+    // Arrange
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let subscriber_id = query!("SELECT id FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .id;
+    let taken_token = generate_subscription_token_with_rng(&mut StdRng::seed_from_u64(42));
+    query!(
+        "INSERT INTO subscription_tokens (subscriber_id, subscription_token) VALUES ($1, $2)",
+        subscriber_id,
+        taken_token
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+
+    // Act - The generator is seeded to draw the taken token first
+    let mut tx = app.connection_pool.begin().await.unwrap();
+    let stored_token =
+        store_token_with_rng(&mut tx, &subscriber_id, &mut StdRng::seed_from_u64(42))
+            .await
+            .unwrap();
+    tx.commit().await.unwrap();
+
+    // Assert
+    assert_ne!(stored_token, taken_token);
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token={}",
+        app.address, stored_token
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn every_subscription_status_round_trips_through_the_database() {
+    // Arrange
+    let app = spawn_app().await;
+    let statuses = [
+        SubscriptionStatus::PendingConfirmation,
+        SubscriptionStatus::Confirmed,
+        SubscriptionStatus::Unsubscribed,
+    ];
+
+    for status in statuses {
+        // Act
+        let id = uuid::Uuid::new_v4();
+        query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, $2, 'le guin', now(), $3)
+            "#,
+            id,
+            format!("{}@example.com", id),
+            status as SubscriptionStatus,
+        )
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+
+        // Assert
+        let saved = query!(
+            r#"
+            SELECT status AS "status: SubscriptionStatus", status::text AS "stored!"
+            FROM subscriptions
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+        assert_eq!(saved.status, status);
+        assert_eq!(saved.stored, status.as_str());
+    }
+}
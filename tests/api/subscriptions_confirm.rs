@@ -1,4 +1,6 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with_config, subscription_token, TestApp};
+use newsletter_lib::configuration::ConfirmationTokenScheme;
+use newsletter_lib::domain::SubscriptionStatus;
 use sqlx::query;
 use wiremock::matchers::{method, path};
 use wiremock::Mock;
@@ -68,7 +70,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
         .unwrap();
 
     // Assert
-    let saved = query!("SELECT email, name, status FROM subscriptions")
+    let saved = query!(r#"SELECT email, name, status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions"#)
         .fetch_one(app.connection_pool.as_ref())
         .await
         .expect("Failed to fetch saved subscription.");
@@ -77,5 +79,330 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
-    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.confirmed_source.as_deref(), Some("email"));
+}
+
+#[tokio::test]
+async fn an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/confirm?subscription_token=unknowntoken",
+        app.address
+    ))
+    .await
+    .expect("Failed to execute a request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn concurrent_clicks_on_the_confirmation_link_confirm_the_subscriber_once() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions_with_str(body).await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    // Act
+    let (first, second) = tokio::join!(
+        reqwest::get(confirmation_links.html.clone()),
+        reqwest::get(confirmation_links.html.clone())
+    );
+
+    // Assert
+    assert_eq!(first.unwrap().status().as_u16(), 200);
+    assert_eq!(second.unwrap().status().as_u16(), 200);
+    let saved =
+        query!(r#"SELECT status AS "status: SubscriptionStatus", consented_at FROM subscriptions"#)
+            .fetch_one(app.connection_pool.as_ref())
+            .await
+            .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    let consented_at = saved.consented_at.unwrap();
+
+    // A later click keeps the time of the original consent.
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let saved = query!("SELECT consented_at FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.consented_at, Some(consented_at));
+}
+
+/// Subscribes with signed confirmation tokens valid for `ttl_seconds`
+/// and returns the confirmation link.
+async fn subscribe_with_a_signed_token(ttl_seconds: u64) -> (TestApp, reqwest::Url) {
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.confirmation_token_scheme = ConfirmationTokenScheme::Signed;
+        c.subscriptions.signed_token_ttl_seconds = ttl_seconds;
+    })
+    .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions_with_str("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let link = app.get_confirmation_links(email_request).html;
+    (app, link)
+}
+
+#[tokio::test]
+async fn a_signed_confirmation_link_confirms_a_subscriber_without_a_stored_token() {
+    // Arrange
+    let (app, confirmation_link) = subscribe_with_a_signed_token(3600).await;
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    let tokens = query!(r#"SELECT count(*) AS "count!" FROM subscription_tokens"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(tokens.count, 0);
+}
+
+#[tokio::test]
+async fn an_old_confirmation_link_does_not_resubscribe_an_unsubscribed_subscriber() {
+    // Arrange
+    let (app, confirmation_link) = subscribe_with_a_signed_token(3600).await;
+    reqwest::get(confirmation_link.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let mut unsubscribe_link = app
+        .unsubscribe_links
+        .link("ursula_le_guin@gmail.com")
+        .unwrap();
+    unsubscribe_link.set_port(Some(app.port)).unwrap();
+    reqwest::Client::new()
+        .post(unsubscribe_link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+}
+
+#[tokio::test]
+async fn a_tampered_signed_confirmation_link_is_rejected_with_a_401() {
+    // Arrange
+    let (app, mut confirmation_link) = subscribe_with_a_signed_token(3600).await;
+    let token = subscription_token(&confirmation_link);
+    let mut tampered = token.into_bytes();
+    let last = tampered.last_mut().unwrap();
+    *last = if *last == b'0' { b'1' } else { b'0' };
+    confirmation_link
+        .query_pairs_mut()
+        .clear()
+        .append_pair("subscription_token", &String::from_utf8(tampered).unwrap());
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn an_expired_signed_confirmation_link_is_rejected_with_a_410() {
+    // Arrange
+    let (app, confirmation_link) = subscribe_with_a_signed_token(0).await;
+
+    // Act
+    let response = reqwest::get(confirmation_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 410);
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn a_signed_link_sent_before_an_email_change_does_not_confirm_the_new_address() {
+    // Arrange
+    let (app, old_link) = subscribe_with_a_signed_token(3600).await;
+    // A stored token issued before switching to signed tokens authorizes the change.
+    query!(
+        r#"
+        INSERT INTO subscription_tokens (subscriber_id, subscription_token)
+        SELECT id, 'storedtoken' FROM subscriptions
+        "#
+    )
+    .execute(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    app.post_change_email("storedtoken", "ursula.new@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let new_link = app.get_confirmation_links(&email_request).html;
+
+    // Act 1 - Follow the link sent to the old address
+    let response = reqwest::get(old_link).await.unwrap();
+
+    // Assert 1
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+    assert_eq!(
+        saved.pending_email.as_deref(),
+        Some("ursula.new@example.com")
+    );
+
+    // Act 2 - Follow the signed link sent to the new address
+    let response = reqwest::get(new_link).await.unwrap();
+
+    // Assert 2
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus", pending_email FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.email, "ursula.new@example.com");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.pending_email, None);
+}
+
+#[tokio::test]
+async fn following_the_link_does_not_confirm_when_post_confirmation_is_required() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_post_confirmation = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions_with_str(body).await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    // Act - A prefetcher follows the link
+    let response = reqwest::get(confirmation_links.html.clone())
+        .await
+        .expect("Failed to execute a request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"<form action="/subscriptions/confirm" method="post">"#));
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn submitting_the_confirm_page_confirms_a_subscriber() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_post_confirmation = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(wiremock::ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions_with_str(body).await;
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+
+    // Act
+    let response = app
+        .post_confirm(&subscription_token(&confirmation_links.html))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = query!(
+        r#"SELECT status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions"#
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.confirmed_source.as_deref(), Some("email"));
+}
+
+#[tokio::test]
+async fn submitting_an_unknown_token_is_rejected_with_a_401() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_confirm("unknowntoken").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
 }
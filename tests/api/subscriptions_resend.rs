@@ -0,0 +1,168 @@
+use crate::helpers::{spawn_app, spawn_app_with_config, subscription_token, TestApp};
+use newsletter_lib::domain::SubscriptionStatus;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn subscribe(app: &TestApp) {
+    app.post_subscriptions_with_str("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+}
+
+async fn count_sent_emails(app: &TestApp) -> usize {
+    app.email_server.received_requests().await.unwrap().len()
+}
+
+#[tokio::test]
+async fn a_resend_right_after_subscribing_sends_nothing() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app).await;
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(count_sent_emails(&app).await, 1);
+}
+
+#[tokio::test]
+async fn two_resends_within_the_cooldown_send_a_single_email() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app).await;
+    sqlx::query!("UPDATE subscriptions SET last_confirmation_sent_at = now() - interval '1 hour'")
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+
+    // Act
+    let first = app.post_resend_confirmation(EMAIL).await;
+    let second = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(first.status().as_u16(), 200);
+    assert_eq!(second.status().as_u16(), 200);
+    assert_eq!(count_sent_emails(&app).await, 2);
+}
+
+#[tokio::test]
+async fn the_resent_link_confirms_the_subscriber() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.subscriptions.confirmation_resend_cooldown_seconds = 0).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app).await;
+
+    // Act
+    app.post_resend_confirmation(EMAIL).await;
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn resending_to_an_unknown_address_sends_nothing() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn resending_to_an_invalid_address_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_resend_confirmation("not-an-email").await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn a_resend_during_an_email_change_goes_to_the_new_address() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.subscriptions.confirmation_resend_cooldown_seconds = 0).await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    subscribe(&app).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let token = subscription_token(&confirmation_links.html);
+    app.post_change_email(&token, "ursula.new@example.com")
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app.post_resend_confirmation(EMAIL).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(body["To"], "ursula.new@example.com");
+    assert_eq!(count_sent_emails(&app).await, 3);
+}
@@ -0,0 +1,94 @@
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app, subscription_token, TestApp};
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .email
+}
+
+#[tokio::test]
+async fn a_pending_subscription_is_reported_as_pending() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    let token = subscription_token(&confirmation_links.html);
+    let email = subscriber_email(&app).await;
+
+    // Act
+    let response = app.get_subscription_status(&email, &token).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "pending");
+}
+
+#[tokio::test]
+async fn a_confirmed_subscription_is_reported_as_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let token = subscription_token(&confirmation_links.html);
+    let email = subscriber_email(&app).await;
+
+    // Act
+    let response = app
+        .get_subscription_status(&email.to_uppercase(), &token)
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+}
+
+#[tokio::test]
+async fn a_wrong_token_returns_404_whether_the_email_exists_or_not() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    let token = subscription_token(&confirmation_links.html);
+    let email = subscriber_email(&app).await;
+
+    // Act
+    let known_email = app.get_subscription_status(&email, "wrong-token").await;
+    let unknown_email = app
+        .get_subscription_status("someone.else@example.com", "wrong-token")
+        .await;
+    let token_of_another_email = app
+        .get_subscription_status("someone.else@example.com", &token)
+        .await;
+
+    // Assert
+    assert_eq!(known_email.status().as_u16(), 404);
+    assert_eq!(unknown_email.status().as_u16(), 404);
+    assert_eq!(token_of_another_email.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn an_unknown_subscriber_id_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client
+        .get(format!(
+            "{}/subscriptions/{}",
+            &app.address,
+            uuid::Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
@@ -0,0 +1,170 @@
+use crate::helpers::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app, TestApp,
+};
+use newsletter_lib::domain::SubscriptionStatus;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+/// Delivers an issue to the only subscriber and returns its one-click unsubscribe link.
+async fn deliver_an_issue(app: &TestApp) -> reqwest::Url {
+    app.test_user.login(app).await;
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let received_requests = app.email_server.received_requests().await.unwrap();
+    let email_body: serde_json::Value =
+        serde_json::from_slice(&received_requests.last().unwrap().body).unwrap();
+    let list_unsubscribe = email_body["Headers"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|h| h["Name"] == "List-Unsubscribe")
+        .unwrap()["Value"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let mut link = reqwest::Url::parse(list_unsubscribe.trim_matches(['<', '>'])).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+    link
+}
+
+async fn post_one_click_unsubscribe(link: reqwest::Url) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(link)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+#[tokio::test]
+async fn the_one_click_link_unsubscribes_the_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link = deliver_an_issue(&app).await;
+
+    // Act
+    let response = post_one_click_unsubscribe(link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+}
+
+#[tokio::test]
+async fn a_tampered_unsubscribe_link_is_rejected_with_401() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let link = deliver_an_issue(&app).await;
+    let mut tampered = link.clone();
+    let token = link
+        .query_pairs()
+        .find(|(key, _)| key == "token")
+        .unwrap()
+        .1
+        .into_owned();
+    tampered
+        .query_pairs_mut()
+        .clear()
+        .append_pair("email", "someone-else@example.com")
+        .append_pair("token", &token);
+
+    // Act
+    let response = post_one_click_unsubscribe(tampered).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+}
+
+#[tokio::test]
+async fn an_old_confirmation_link_does_not_resubscribe_after_a_one_click_unsubscribe() {
+    // Arrange
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    reqwest::get(confirmation_links.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let link = deliver_an_issue(&app).await;
+    post_one_click_unsubscribe(link)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 401);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(saved.status, SubscriptionStatus::Unsubscribed);
+    let tokens = sqlx::query!(r#"SELECT count(*) AS "count!" FROM subscription_tokens"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(tokens.count, 0);
+}
+
+#[tokio::test]
+async fn a_link_for_a_mixed_case_address_unsubscribes_that_exact_address() {
+    // Arrange
+    let app = spawn_app().await;
+    for email in ["u@EXAMPLE.com", "u@example.com"] {
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES (gen_random_uuid(), $1, 'u', now(), 'confirmed')
+            "#,
+            email
+        )
+        .execute(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    }
+    let mut link = app.unsubscribe_links.link("u@EXAMPLE.com").unwrap();
+    link.set_port(Some(app.port)).unwrap();
+
+    // Act
+    let response = post_one_click_unsubscribe(link).await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        r#"SELECT email, status AS "status: SubscriptionStatus" FROM subscriptions ORDER BY email"#
+    )
+    .fetch_all(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved[0].email, "u@EXAMPLE.com");
+    assert_eq!(saved[0].status, SubscriptionStatus::Unsubscribed);
+    assert_eq!(saved[1].email, "u@example.com");
+    assert_eq!(saved[1].status, SubscriptionStatus::Confirmed);
+}
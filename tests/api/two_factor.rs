@@ -0,0 +1,176 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+fn current_code(secret: &str) -> String {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "".to_string())
+        .unwrap()
+        .generate_current()
+        .unwrap()
+}
+
+fn wrong_code(secret: &str) -> String {
+    let code: u32 = current_code(secret).parse().unwrap();
+    format!("{:06}", (code + 500_000) % 1_000_000)
+}
+
+fn extract_secret(html_page: &str) -> String {
+    html_page
+        .split(r#"<code id="secret">"#)
+        .nth(1)
+        .and_then(|s| s.split("</code>").next())
+        .expect("The setup page did not show a secret.")
+        .to_owned()
+}
+
+/// Enables 2FA for the logged-in test user and returns the TOTP secret.
+async fn enable_two_factor(app: &TestApp) -> String {
+    let secret = extract_secret(&app.get_two_factor_setup_html().await);
+
+    let response = app.post_two_factor_setup(&current_code(&secret)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    secret
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_set_up_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_two_factor_setup("123456").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn enabling_two_factor_authentication_requires_a_code_on_login() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act 1 - Enable 2FA
+    let secret = enable_two_factor(&app).await;
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("<p><i>Two-factor authentication has been enabled.</i></p>"));
+    app.post_logout().await;
+
+    // Act 2 - Login with password only
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/2fa");
+
+    // Act 3 - The session is not established yet
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdashboard");
+
+    // Act 4 - Provide the code
+    let response = app.post_login_two_factor(&current_code(&secret)).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    // Assert
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn a_wrong_two_factor_code_is_rejected_on_login() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let secret = enable_two_factor(&app).await;
+    app.post_logout().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.post_login_two_factor(&wrong_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login/2fa");
+    let html_page = app.get_login_two_factor_html().await;
+    assert!(html_page.contains("<p><i>The authentication code is invalid.</i></p>"));
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdashboard");
+}
+
+#[tokio::test]
+async fn the_pending_login_is_dropped_after_five_wrong_codes() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let secret = enable_two_factor(&app).await;
+    app.post_logout().await;
+    app.test_user.login(&app).await;
+
+    // Act 1 - Four wrong codes can be retried
+    for _ in 0..4 {
+        let response = app.post_login_two_factor(&wrong_code(&secret)).await;
+        assert_is_redirect_to(&response, "/login/2fa");
+    }
+
+    // Act 2 - The fifth one drops the pending login
+    let response = app.post_login_two_factor(&wrong_code(&secret)).await;
+    assert_is_redirect_to(&response, "/login");
+    let html_page = app.get_login_html().await;
+    assert!(html_page
+        .contains("<p><i>Too many invalid authentication codes. Please log in again.</i></p>"));
+
+    // Act 3 - Even the right code no longer logs in
+    let response = app.post_login_two_factor(&current_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fdashboard");
+}
+
+#[tokio::test]
+async fn a_wrong_code_does_not_enable_two_factor_authentication() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let secret = extract_secret(&app.get_two_factor_setup_html().await);
+
+    // Act
+    let response = app.post_two_factor_setup(&wrong_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/2fa/setup");
+    let stored = sqlx::query!(
+        "SELECT totp_secret FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert!(stored.totp_secret.is_none());
+}
+
+#[tokio::test]
+async fn the_requested_admin_page_is_kept_across_the_two_factor_step() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+    let secret = enable_two_factor(&app).await;
+    app.post_logout().await;
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+            "next": "/admin/password",
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/login/2fa");
+    let response = app.post_login_two_factor(&current_code(&secret)).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+}
@@ -0,0 +1,148 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app, TestApp};
+use newsletter_lib::domain::SubscriptionStatus;
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn create_pending_subscriber(app: &TestApp) -> Uuid {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    app.post_subscriptions_with_str("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap()
+        .id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_update_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .patch_subscriber(Uuid::new_v4(), &serde_json::json!({ "name": "Ursula" }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn an_admin_can_confirm_a_pending_subscriber_and_fix_their_name() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_pending_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .patch_subscriber(
+            subscriber_id,
+            &serde_json::json!({ "name": "Ursula K. Le Guin", "status": "confirmed" }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        r#"SELECT name, status AS "status: SubscriptionStatus", confirmed_source FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.name, "Ursula K. Le Guin");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert_eq!(saved.confirmed_source.as_deref(), Some("admin"));
+}
+
+#[tokio::test]
+async fn omitted_fields_are_left_unchanged() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_pending_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .patch_subscriber(subscriber_id, &serde_json::json!({ "name": "Ursula" }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let saved = sqlx::query!(
+        r#"SELECT name, status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.name, "Ursula");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn invalid_input_is_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+    let subscriber_id = create_pending_subscriber(&app).await;
+    app.test_user.login(&app).await;
+    let test_cases = vec![
+        (
+            serde_json::json!({ "status": "unsubscribed" }),
+            "unknown status",
+        ),
+        (serde_json::json!({ "name": "" }), "empty name"),
+        (
+            serde_json::json!({ "name": "Ursula<" }),
+            "forbidden character",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app.patch_subscriber(subscriber_id, &body).await;
+
+        // Assert
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            description
+        );
+    }
+    let saved = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1"#,
+        subscriber_id
+    )
+    .fetch_one(app.connection_pool.as_ref())
+    .await
+    .unwrap();
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
+}
+
+#[tokio::test]
+async fn an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app
+        .patch_subscriber(
+            Uuid::new_v4(),
+            &serde_json::json!({ "status": "confirmed" }),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 404);
+}
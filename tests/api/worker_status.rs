@@ -0,0 +1,176 @@
+use crate::helpers::{
+    assert_is_redirect_to, create_confirmed_subscriber, spawn_app, spawn_app_with_config,
+};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_worker_status() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_worker_status().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login?next=%2Fadmin%2Fsystem%2Fworker%2Fstatus");
+}
+
+#[tokio::test]
+async fn the_worker_status_is_idle_before_any_task_is_processed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user.login(&app).await;
+
+    // Act
+    let response = app.get_worker_status().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(status["activity"], "idle");
+    assert_eq!(status["tasks_processed"], 0);
+    assert!(status["last_task_at"].is_null());
+    assert!(status["last_error"].is_null());
+}
+
+#[tokio::test]
+async fn the_worker_status_reflects_processed_tasks() {
+    // Arrange
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.fan_out_pending_issues().await;
+    let before = chrono::Utc::now();
+
+    // Act
+    app.dispatch_all_pending_emails().await;
+    let response = app.get_worker_status().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let status: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(status["activity"], "idle");
+    assert_eq!(status["tasks_processed"], 1);
+    let last_task_at: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(status["last_task_at"].clone()).unwrap();
+    assert!(last_task_at >= before);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_dispatch_pending_tasks() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_worker_dispatch().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn dispatching_processes_the_queue_until_it_is_empty() {
+    // Arrange
+    let app = spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(3)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.fan_out_pending_issues().await;
+
+    // Act
+    let response = app.post_worker_dispatch().await;
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let summary: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(summary["processed"], 3);
+    let remaining = sqlx::query!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.connection_pool.as_ref())
+        .await
+        .unwrap();
+    assert_eq!(remaining.count, 0);
+    let status: serde_json::Value = app.get_worker_status().await.json().await.unwrap();
+    assert_eq!(status["tasks_processed"], 3);
+}
+
+#[tokio::test]
+async fn concurrent_loops_drain_the_queue_faster_and_deliver_each_email_once() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.worker.concurrency = 4).await;
+    for _ in 0..8 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    let delay = Duration::from_millis(300);
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200).set_delay(delay))
+        .expect(8)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "text_content": "Newsletter body as plain text",
+        "idempotency_key": uuid::Uuid::new_v4().to_string(),
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.fan_out_pending_issues().await;
+
+    // Act
+    let start = Instant::now();
+    let response = app.post_worker_dispatch().await;
+    let elapsed = start.elapsed();
+
+    // Assert
+    assert_eq!(response.status().as_u16(), 200);
+    let summary: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(summary["processed"], 8);
+    // One loop would take at least 8 times the delay.
+    assert!(elapsed < delay * 8, "Draining took {:?}", elapsed);
+    let deliveries: Vec<String> = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| serde_json::from_slice::<serde_json::Value>(&request.body).unwrap())
+        .filter(|body| body["Subject"] == "Newsletter title")
+        .map(|body| body["To"].as_str().unwrap().to_owned())
+        .collect();
+    assert_eq!(deliveries.len(), 8);
+    assert_eq!(deliveries.iter().collect::<HashSet<_>>().len(), 8);
+}